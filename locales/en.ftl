@@ -0,0 +1,235 @@
+# Messages shown by git-squash, in Fluent syntax. Placeables like
+# { $count } are filled in at runtime.
+
+## Prompts
+
+message-prompt = Message (Tab pastes from the clipboard)
+message-prompt-plain = Message
+custom-message = ➜ [Enter] Custom commit message
+select-message = Select a commit message
+select-message-fuzzy = Select a commit message (type to filter)
+select-message-page = Select a commit message ({ $first }-{ $last } of { $total }, page { $page }/{ $pages })
+next-page = → Next page
+previous-page = ← Previous page
+pull-request = ➜ PR #{ $number }: { $title }
+merge-request = ➜ MR !{ $number }: { $title }
+merge-request-description = ➜ MR !{ $number }: title and description
+suggested-message = Suggested message:
+use-suggestion = Use this message?
+select-fold = Select commits to fold into their predecessor
+reorder-commits = Reorder commits, oldest first (space to grab, arrows to move)
+try-another-order = { $error }, try another order
+confirm-topology = Squash these { $count } commits into one?
+confirm-squash = Squash { $count } commits?
+action-squash = Squash
+action-preview = Preview diff
+action-abort = Abort
+confirm-sign = Sign the squash commit?
+amount-prompt = Commits to squash ({ $ahead } ahead of { $upstream })
+amount-at-least-one = Enter at least 1
+author-prompt = The commits have { $count } authors; who should author the squash commit?
+author-me = Me, { $identity }
+author-majority = { $identity }, who wrote { $count } of the { $total } commits
+author-oldest = { $identity }, who wrote the oldest commit
+shortfall-all = Squash all { $count } commits
+shortfall-other = Pick another number
+shortfall-abort = Abort
+shortfall-prompt = Commits to squash (at most { $available })
+confirm-wip = The last { $count } commits look like work in progress, squash them?
+confirm-wip-into = The last { $count } commits look like work in progress, squash them into "{ $target }"?
+confirm-ticket = Prefix the subject with { $key }?
+plain-choose = Enter a number from 1 to { $count } [{ $default }]
+plain-choose-many = Enter numbers separated by spaces, or nothing for none
+plain-order = Enter every number in the new order, or nothing to keep it
+plain-invalid = Enter a number from 1 to { $count }
+plain-invalid-order = Enter each number from 1 to { $count } exactly once
+plain-yes-no = Answer y or n
+plain-no-answer = No answer, stdin was closed
+
+## Relative commit times
+
+ago-now = now
+ago-minutes = { $count } m
+ago-hours = { $count } h
+ago-days = { $count } d
+ago-weeks = { $count } w
+ago-months = { $count } mo
+ago-years = { $count } y
+
+## Warnings and progress
+
+terminating = Terminating...
+restored-head = Restored HEAD to { $oid }
+restore-failed = Failed to restore HEAD: { $error }
+signature-discarded = Warning: squashing will discard { $count } commit signature
+signatures-discarded = Warning: squashing will discard { $count } commit signatures
+diverging-branches = Warning: these branches contain commits being rewritten and will diverge: { $branches }
+moved-tags = Moved tags along with their rewritten commits: { $tags }
+copied-notes = Copied { $count } notes to the squash commit
+warning = Warning: { $error }
+copied-to-clipboard = Copied to the clipboard
+
+## Errors
+
+dirty-worktree = Working directory has uncommitted changes; commit or stash them first
+squash-stopped = A squash stopped at a conflict, run `git squash continue` or `git squash abort` first
+operation-in-progress = A { $operation } is in progress, finish or abort it first
+invalid-setting = Invalid { $key } `{ $value }`
+invalid-number = Invalid { $key } `{ $value }`, expected a number of at least 0
+invalid-bool = Invalid { $key } `{ $value }`, expected true or false
+invalid-color = Invalid color setting `{ $value }`
+invalid-backend = Invalid squash.backend `{ $value }`: { $error }
+invalid-utf8 = { $key } is not valid UTF-8
+config-parse-failed = Failed to parse { $path }
+unsupported-locale = Unsupported locale `{ $locale }`, expected en or sv
+unsupported-gpg-format = Unsupported gpg.format `{ $format }`
+sign-failed = { $program } failed to sign the commit:
+    { $output }
+ssh-signing-key-missing = user.signingKey must be set to sign with SSH
+too-many-commits = Cannot squash { $count } commits, HEAD only has { $available }
+root-needs-plain-squash = The root commit can only be rewritten by a plain squash
+message-too-long = Subject is { $length } characters, max is { $max }
+template-not-edited = Aborting squash, the commit message was not edited
+invalid-selection = Invalid selection
+no-commits-selected = No commits selected
+bare-repository = { $path } is a bare repository; squashing needs an index and a working tree, so run it in a clone or worktree instead
+no-upstream = No amount given and the branch has no upstream to count from
+not-ahead = Nothing to squash, { $branch } is not ahead of { $upstream }
+shallow-available = Only { $limit } commits are available in this shallow clone
+shallow-limit = Only { $limit } commits can be squashed in this shallow clone; run `git fetch --unshallow` (or `--deepen=<n>`) to squash { $count }
+protected-branch = Refusing to squash protected branch `{ $branch }`
+tags-in-range = Tags point at commits that would be rewritten: { $tags }
+    Pass --retag to move them to the commits they were rewritten into
+repo-config-command = { $path } comes with the repository, so it can't set { $keys }, which run commands; set them in ~/.config/git-squash/config.toml or with `git config` instead
+tags-signed = Moving the signed tags { $tags } would drop their signatures; delete them, squash, and sign new tags instead
+empty-squash = The last { $count } commits cancel each other out, so the squash commit would be empty; pass --allow-empty to create it anyway
+aborted-by-hook = Squash aborted by hook
+empty-message = Aborting squash due to empty commit message
+already-running = Another git-squash (pid { $pid }) is already running in this repository; remove { $path } if it is stale
+rollback-failed = Failed to roll back squash: { $error }
+rollback-hint = Run `git reset --soft { $head }` to restore the original HEAD
+integrity-tree = Integrity check failed: squash commit has tree { $actual } instead of { $expected }
+integrity-worktree = Integrity check failed: the working directory changed during the squash
+hook-failed = { $hook } hook exited with { $status }
+replay-conflicts = Replaying { $commit } conflicts in: { $paths }
+replay-stopped = Resolve the conflicts, stage them with `git add` and run `git squash continue`, or run `git squash abort`
+replay-merge = Cannot replay merge commit { $commit }
+fold-first-step = Commit { $commit } has no previous commit to be folded into
+conflicts-unresolved = Resolve the conflicts and stage them with `git add` first
+nothing-to-continue = No squash to continue
+nothing-to-abort = No squash to abort
+needs-message-strategy = Nothing can be asked here, so pass -m <message>, --strategy newest or oldest, or a message command
+needs-terminal = --{ $flag } asks questions, but stdin or stderr is not a terminal; leave it out, or pass --plain to answer on stdin
+merges-need-confirmation = The last { $count } commits contain merges, which squashing flattens; run interactively to confirm
+install-shadowed = { $path } is on PATH and would run instead; remove it first
+install-link-exists = { $path } already exists; remove it or pass another --dir
+install-alias-exists = alias.squash is already `{ $alias }`; remove it with `git config --global --unset alias.squash` first
+hook-exists = { $path } already exists and wasn't installed by git-squash; pass --force to replace it
+prepush-blocked = Push refused because of work-in-progress commits; squash them first, or set squash.prePush to warn
+tag-invalid = `{ $name }` is not a valid tag name
+tag-exists = The tag { $name } already exists; pick another name or delete it first
+no-such-branch = There is no local branch { $branch }
+branch-checked-out = { $branch } is checked out, so it can't be updated without touching its files
+cherry-pick-conflicts = The squash commit doesn't apply cleanly to { $branch } ({ $paths }); { $branch } was left as it was
+push-no-upstream = --push needs a branch that tracks a remote branch; set one with `git branch --set-upstream-to`
+push-behind = { $upstream } has { $count } commits this branch doesn't, so --push couldn't go through; pull them first
+push-would-force = Only the { $ahead } commits not on { $upstream } yet can be squashed with --push, as it never forces
+push-failed = Squashed, but pushing to { $destination } failed; push once the cause is fixed
+missing-signoff = The message needs a `Signed-off-by: { $identity }` trailer; add it with `git squash -e` or drop --require-signoff
+
+## Results
+
+folded = Folded { $count } commits into their predecessors
+rewrote = Rewrote { $count } commits
+nothing-squashed = Nothing was squashed
+autosquashed = Folded { $count } commits into the commits they name
+grouped-by-author = Squashed { $count } commits into the previous commit by the same author
+nothing-to-group = No consecutive commits by the same author to squash
+grouped-by-window = Folded { $count } commits into the bursts of commits less than { $window } apart they belong to
+nothing-in-window = No commits made less than { $window } after the previous one to squash
+squashed-paths = Squashed { $count } of the last { $total } commits
+paths-too-few = Only { $count } of the commits touch { $paths }, nothing to squash
+range-invalid = { $range } is not a range like abc123..def456
+range-not-on-branch = The range { $range } doesn't end in the history of HEAD
+range-not-linear = The range { $range } contains merges or commits that aren't on the current branch
+range-too-small = The range { $range } contains { $count } commits, nothing to squash
+range-unsupported = A range can only be squashed as a whole, without --select, --interactive, --autosquash, --by-author, --window or --path
+explain-range-unsupported = --explain only explains squashing the last commits, not a range
+range-sign-unsupported = Signing is only supported when squashing the last commits, not a range
+    Pass --no-sign to squash the range unsigned
+autostash-kept = Failed to restore the autostashed changes, they are kept in `git stash list`
+continued = Finished the squash at { $oid }
+aborted = Aborted the squash, back at { $oid }
+install-already = `git squash` already runs { $path }
+install-linked = Linked { $link } to { $target }, run it as `git squash`
+install-would-link = Would link { $link } to { $target }
+install-aliased = Set the global alias.squash to { $command }, run it as `git squash`
+install-would-alias = Would set the global alias.squash to { $command }
+install-not-on-path = { $dir } is not on PATH, add it there for git to find git-squash
+hook-installed = Installed the { $hook } hook at { $path }
+prepush-flagged = Commits being pushed that look like work in progress:
+prepush-suggest = Squash them with `git squash` or `git squash --autosquash` before pushing
+analyze-fixups = Fixup commits to fold into the commits they name:
+analyze-burst = Commits made within { $minutes } minutes of each other:
+analyze-similar = Neighbouring commits with similar subjects:
+analyze-nothing = No obvious groups to squash among the last { $count } commits
+doctor-pass = ok
+doctor-warn = warn
+doctor-fail = fail
+doctor-worktree = Working tree
+doctor-operation = Operation
+doctor-upstream = Upstream
+doctor-signing = Signing
+doctor-hooks = Hooks
+doctor-branch = Branch
+doctor-clean = No uncommitted changes
+doctor-dirty = { $count } changed files; staged changes end up in the squash commit, and ranges and --path refuse to start
+doctor-idle = No merge, rebase or squash in progress
+doctor-no-upstream = No upstream, so the number of commits to squash has to be given
+doctor-not-ahead = Not ahead of { $upstream }, so there is nothing to squash by default
+doctor-ahead = { $count } commits ahead of { $upstream }
+doctor-diverged = { $ahead } commits ahead and { $behind } behind { $upstream }, the branch has diverged
+doctor-not-signing = Squash commits aren't signed and no signing key is set
+doctor-no-ssh-key = gpg.format is ssh, but user.signingKey isn't set
+doctor-no-signer = { $program } isn't installed, so commits can't be signed
+doctor-signing-with = Squash commits are signed with { $program }
+doctor-can-sign = { $program } is available, pass --sign to sign squash commits
+doctor-hook-not-executable = Skipped as not executable: { $paths }
+doctor-no-hooks = No pre-squash or post-squash hooks
+doctor-detached = HEAD is detached, the result won't be on a branch
+doctor-branch-ok = { $branch } may be squashed
+doctor-failed = { $count } checks failed, a squash would not go through
+explain-walk = List the last { $count } commits from HEAD ({ $head }) with { $backend }
+explain-parent = Resolve HEAD~{ $count } as the new parent: { $oid } { $subject }
+explain-root = No commit comes before them, so the squash commit becomes a new root commit
+explain-hook = Run the { $hook } hook: { $command }
+explain-reset = Soft reset HEAD to { $oid } with { $backend }, keeping the index and working tree
+explain-tree = Write the index as tree { $tree }
+explain-commit = Create an unsigned commit of tree { $tree } with parents [{ $parents }] and the chosen message with { $backend }, and move HEAD to it
+explain-commit-signed = Create a signed commit of tree { $tree } with parents [{ $parents }] and the chosen message with { $backend }, and move HEAD to it
+explain-verify = Check that the new commit has tree { $tree }, or else restore HEAD to { $head }
+explain-reflog = Collapse the reflog entries of the squash into one: { $message }
+explain-unchanged = Nothing was changed, run it again without --explain to squash
+tagged = Tagged { $oid } as { $name }
+cherry-picked = Cherry-picked the squash commit onto { $branch } as { $oid }
+changelog-written = Added { $count } entries to { $path }
+nothing-to-autosquash = No fixup! or squash! commits to fold
+squashed = Squashed { $count } commits
+squashed-detached = Squashed { $count } commits into { $oid }
+    HEAD is detached, run `git switch -c <branch>` to keep the result on a branch
+
+## Batch mode
+
+batch-succeeded = ✔ { $path }: { $message }
+batch-failed = ✘ { $path }: { $error }
+batch-summary = Squashed all { $total } repositories
+batch-summary-failed = { $failed } of { $total } repositories failed
+
+## Progress
+
+event-commits = Found { $count } commits to squash
+event-reset = Reset HEAD to { $oid }
+event-hook = Ran the { $hook } hook
+event-commit = Created the squash commit { $oid }
+event-rewritten = { $old } → { $new }
+event-push = Pushed { $refspec } to { $remote }
@@ -0,0 +1,235 @@
+# Svenska meddelanden för git-squash, i Fluent-syntax. Platshållare som
+# { $count } fylls i när programmet körs.
+
+## Frågor
+
+message-prompt = Meddelande (Tab klistrar in från urklipp)
+message-prompt-plain = Meddelande
+custom-message = ➜ [Enter] Eget commit-meddelande
+select-message = Välj ett commit-meddelande
+select-message-fuzzy = Välj ett commit-meddelande (skriv för att filtrera)
+select-message-page = Välj ett commit-meddelande ({ $first }-{ $last } av { $total }, sida { $page }/{ $pages })
+next-page = → Nästa sida
+previous-page = ← Föregående sida
+pull-request = ➜ PR #{ $number }: { $title }
+merge-request = ➜ MR !{ $number }: { $title }
+merge-request-description = ➜ MR !{ $number }: titel och beskrivning
+suggested-message = Föreslaget meddelande:
+use-suggestion = Använd det här meddelandet?
+select-fold = Välj commits att slå ihop med sin föregångare
+reorder-commits = Ordna om commits, äldst först (mellanslag för att greppa, pilar för att flytta)
+try-another-order = { $error }, prova en annan ordning
+confirm-topology = Slå ihop de här { $count } commits till en?
+confirm-squash = Slå ihop { $count } commits?
+action-squash = Slå ihop
+action-preview = Förhandsgranska diff
+action-abort = Avbryt
+confirm-sign = Signera den sammanslagna commiten?
+amount-prompt = Commits att slå ihop ({ $ahead } före { $upstream })
+amount-at-least-one = Ange minst 1
+author-prompt = Commitarna har { $count } författare; vem ska stå som författare till squash-commiten?
+author-me = Jag, { $identity }
+author-majority = { $identity }, som skrev { $count } av de { $total } commits
+author-oldest = { $identity }, som skrev den äldsta commiten
+shortfall-all = Slå ihop alla { $count } commits
+shortfall-other = Välj ett annat antal
+shortfall-abort = Avbryt
+shortfall-prompt = Commits att slå ihop (högst { $available })
+confirm-wip = De senaste { $count } commits ser ut som pågående arbete, slå ihop dem?
+confirm-wip-into = De senaste { $count } commits ser ut som pågående arbete, slå ihop dem med "{ $target }"?
+confirm-ticket = Inled ämnesraden med { $key }?
+plain-choose = Ange ett nummer från 1 till { $count } [{ $default }]
+plain-choose-many = Ange nummer åtskilda av mellanslag, eller inget för inga
+plain-order = Ange alla nummer i den nya ordningen, eller inget för att behålla den
+plain-invalid = Ange ett nummer från 1 till { $count }
+plain-invalid-order = Ange varje nummer från 1 till { $count } exakt en gång
+plain-yes-no = Svara y eller n
+plain-no-answer = Inget svar, stdin stängdes
+
+## Relativa commit-tider
+
+ago-now = nu
+ago-minutes = { $count } min
+ago-hours = { $count } h
+ago-days = { $count } d
+ago-weeks = { $count } v
+ago-months = { $count } mån
+ago-years = { $count } år
+
+## Varningar och förlopp
+
+terminating = Avslutar...
+restored-head = Återställde HEAD till { $oid }
+restore-failed = Kunde inte återställa HEAD: { $error }
+signature-discarded = Varning: sammanslagningen tar bort { $count } commit-signatur
+signatures-discarded = Varning: sammanslagningen tar bort { $count } commit-signaturer
+diverging-branches = Varning: de här grenarna innehåller commits som skrivs om och kommer att divergera: { $branches }
+moved-tags = Flyttade taggar med sina omskrivna commits: { $tags }
+copied-notes = Kopierade { $count } anteckningar till den sammanslagna commiten
+warning = Varning: { $error }
+copied-to-clipboard = Kopierade till urklipp
+
+## Fel
+
+dirty-worktree = Arbetskatalogen har ändringar som inte är committade; committa eller stasha dem först
+squash-stopped = En sammanslagning stannade vid en konflikt, kör `git squash continue` eller `git squash abort` först
+operation-in-progress = En { $operation } pågår, slutför eller avbryt den först
+invalid-setting = Ogiltig { $key } `{ $value }`
+invalid-number = Ogiltig { $key } `{ $value }`, förväntade ett tal som är minst 0
+invalid-bool = Ogiltig { $key } `{ $value }`, förväntade true eller false
+invalid-color = Ogiltig färginställning `{ $value }`
+invalid-backend = Ogiltig squash.backend `{ $value }`: { $error }
+invalid-utf8 = { $key } är inte giltig UTF-8
+config-parse-failed = Kunde inte tolka { $path }
+unsupported-locale = Språket `{ $locale }` stöds inte, förväntade en eller sv
+unsupported-gpg-format = gpg.format `{ $format }` stöds inte
+sign-failed = { $program } kunde inte signera commiten:
+    { $output }
+ssh-signing-key-missing = user.signingKey måste vara satt för att signera med SSH
+too-many-commits = Kan inte slå ihop { $count } commits, HEAD har bara { $available }
+root-needs-plain-squash = Rot-commiten kan bara skrivas om med en vanlig sammanslagning
+message-too-long = Ämnesraden är { $length } tecken, max är { $max }
+template-not-edited = Avbryter, commit-meddelandet redigerades inte
+invalid-selection = Ogiltigt val
+no-commits-selected = Inga commits valdes
+bare-repository = { $path } är ett bart repository; sammanslagning kräver ett index och en arbetskatalog, så kör i en klon eller ett worktree i stället
+no-upstream = Inget antal angavs och grenen har ingen upstream att räkna från
+not-ahead = Inget att slå ihop, { $branch } ligger inte före { $upstream }
+shallow-available = Bara { $limit } commits finns i den här grunda klonen
+shallow-limit = Bara { $limit } commits kan slås ihop i den här grunda klonen; kör `git fetch --unshallow` (eller `--deepen=<n>`) för att slå ihop { $count }
+protected-branch = Vägrar slå ihop den skyddade grenen `{ $branch }`
+tags-in-range = Taggar pekar på commits som skulle skrivas om: { $tags }
+    Ange --retag för att flytta dem till de commits de skrevs om till
+repo-config-command = { $path } följer med repositoryt och kan därför inte sätta { $keys }, som kör kommandon; sätt dem i ~/.config/git-squash/config.toml eller med `git config` i stället
+tags-signed = Att flytta de signerade taggarna { $tags } skulle ta bort deras signaturer; ta bort dem, slå ihop och signera nya taggar i stället
+empty-squash = De senaste { $count } commits tar ut varandra, så den sammanslagna commiten skulle bli tom; ange --allow-empty för att skapa den ändå
+aborted-by-hook = Sammanslagningen avbröts av en hook
+empty-message = Avbryter sammanslagningen eftersom commit-meddelandet är tomt
+already-running = En annan git-squash (pid { $pid }) körs redan i det här repositoryt; ta bort { $path } om den är kvarglömd
+rollback-failed = Kunde inte rulla tillbaka sammanslagningen: { $error }
+rollback-hint = Kör `git reset --soft { $head }` för att återställa det ursprungliga HEAD
+integrity-tree = Integritetskontrollen misslyckades: squash-commiten har trädet { $actual } i stället för { $expected }
+integrity-worktree = Integritetskontrollen misslyckades: arbetskatalogen ändrades under sammanslagningen
+hook-failed = Hooken { $hook } avslutades med { $status }
+replay-conflicts = Att spela upp { $commit } ger konflikter i: { $paths }
+replay-stopped = Lös konflikterna, stagea dem med `git add` och kör `git squash continue`, eller kör `git squash abort`
+replay-merge = Kan inte spela upp merge-commiten { $commit }
+fold-first-step = Commiten { $commit } har ingen tidigare commit att slås ihop med
+conflicts-unresolved = Lös konflikterna och stagea dem med `git add` först
+nothing-to-continue = Det finns ingen sammanslagning att fortsätta
+nothing-to-abort = Det finns ingen sammanslagning att avbryta
+needs-message-strategy = Inget kan frågas här, så använd -m <meddelande>, --strategy newest eller oldest, eller ett meddelandekommando
+needs-terminal = --{ $flag } ställer frågor, men stdin eller stderr är ingen terminal; utelämna den, eller använd --plain för att svara via stdin
+merges-need-confirmation = De senaste { $count } commits innehåller merges, som plattas ut vid sammanslagning; kör interaktivt för att bekräfta
+install-shadowed = { $path } finns i PATH och skulle köras i stället; ta bort den först
+install-link-exists = { $path } finns redan; ta bort den eller ange en annan --dir
+install-alias-exists = alias.squash är redan `{ $alias }`; ta bort det med `git config --global --unset alias.squash` först
+hook-exists = { $path } finns redan och installerades inte av git-squash; använd --force för att ersätta den
+prepush-blocked = Pushen nekades på grund av commits som är pågående arbete; squasha dem först, eller sätt squash.prePush till warn
+tag-invalid = `{ $name }` är inte ett giltigt taggnamn
+tag-exists = Taggen { $name } finns redan; välj ett annat namn eller ta bort den först
+no-such-branch = Det finns ingen lokal gren { $branch }
+branch-checked-out = { $branch } är utcheckad och kan inte uppdateras utan att röra dess filer
+cherry-pick-conflicts = Squash-commiten går inte att applicera rent på { $branch } ({ $paths }); { $branch } lämnades orörd
+push-no-upstream = --push kräver en gren som följer en fjärrgren; ange en med `git branch --set-upstream-to`
+push-behind = { $upstream } har { $count } commits som den här grenen saknar, så --push skulle inte gå igenom; hämta dem först
+push-would-force = Bara de { $ahead } commits som inte finns på { $upstream } än kan slås ihop med --push, eftersom den aldrig tvingar
+push-failed = Sammanslaget, men pushen till { $destination } misslyckades; pusha när orsaken är åtgärdad
+missing-signoff = Meddelandet behöver en `Signed-off-by: { $identity }`-trailer; lägg till den med `git squash -e` eller ta bort --require-signoff
+
+## Resultat
+
+folded = Slog ihop { $count } commits med sina föregångare
+rewrote = Skrev om { $count } commits
+nothing-squashed = Inget slogs ihop
+autosquashed = Slog ihop { $count } commits med de commits de pekar ut
+grouped-by-author = Slog ihop { $count } commits med föregående commit av samma författare
+nothing-to-group = Inga på varandra följande commits av samma författare att slå ihop
+grouped-by-window = Slog ihop { $count } commits med sina skurar av commits mindre än { $window } från varandra
+nothing-in-window = Inga commits gjorda mindre än { $window } efter föregående att slå ihop
+squashed-paths = Slog ihop { $count } av de senaste { $total } commits
+paths-too-few = Bara { $count } av commitsen rör { $paths }, inget att slå ihop
+range-invalid = { $range } är inte ett intervall som abc123..def456
+range-not-on-branch = Intervallet { $range } slutar inte i historiken för HEAD
+range-not-linear = Intervallet { $range } innehåller merges eller commits som inte finns på den aktuella grenen
+range-too-small = Intervallet { $range } innehåller { $count } commits, inget att slå ihop
+range-unsupported = Ett intervall kan bara slås ihop i sin helhet, utan --select, --interactive, --autosquash, --by-author, --window eller --path
+explain-range-unsupported = --explain förklarar bara squash av de senaste commitsen, inte ett intervall
+range-sign-unsupported = Signering stöds bara när de senaste commits slås ihop, inte ett intervall
+    Ange --no-sign för att slå ihop intervallet osignerat
+autostash-kept = Kunde inte återställa de undanstuvade ändringarna, de finns kvar i `git stash list`
+continued = Slutförde sammanslagningen på { $oid }
+aborted = Avbröt sammanslagningen, tillbaka på { $oid }
+install-already = `git squash` kör redan { $path }
+install-linked = Länkade { $link } till { $target }, kör det som `git squash`
+install-would-link = Skulle länka { $link } till { $target }
+install-aliased = Satte det globala alias.squash till { $command }, kör det som `git squash`
+install-would-alias = Skulle sätta det globala alias.squash till { $command }
+install-not-on-path = { $dir } finns inte i PATH, lägg till den där så att git hittar git-squash
+hook-installed = Installerade { $hook }-hooken i { $path }
+prepush-flagged = Commits som pushas och ser ut som pågående arbete:
+prepush-suggest = Squasha dem med `git squash` eller `git squash --autosquash` innan du pushar
+analyze-fixups = Fixup-commits att vika in i de commits de namnger:
+analyze-burst = Commits gjorda inom { $minutes } minuter från varandra:
+analyze-similar = Intilliggande commits med liknande ämnesrader:
+analyze-nothing = Inga uppenbara grupper att squasha bland de senaste { $count } commits
+doctor-pass = ok
+doctor-warn = varning
+doctor-fail = fel
+doctor-worktree = Arbetskatalog
+doctor-operation = Operation
+doctor-upstream = Uppströms
+doctor-signing = Signering
+doctor-hooks = Hooks
+doctor-branch = Gren
+doctor-clean = Inga ocommittade ändringar
+doctor-dirty = { $count } ändrade filer; köade ändringar hamnar i squash-commiten, och intervall och --path vägrar starta
+doctor-idle = Ingen merge, rebase eller squash pågår
+doctor-no-upstream = Ingen uppströmsgren, så antalet commits att squasha måste anges
+doctor-not-ahead = Inte före { $upstream }, så det finns inget att squasha som standard
+doctor-ahead = { $count } commits före { $upstream }
+doctor-diverged = { $ahead } commits före och { $behind } efter { $upstream }, grenarna har gått isär
+doctor-not-signing = Squash-commits signeras inte och ingen signeringsnyckel är satt
+doctor-no-ssh-key = gpg.format är ssh, men user.signingKey är inte satt
+doctor-no-signer = { $program } är inte installerat, så commits kan inte signeras
+doctor-signing-with = Squash-commits signeras med { $program }
+doctor-can-sign = { $program } finns, ange --sign för att signera squash-commits
+doctor-hook-not-executable = Hoppas över eftersom de inte är körbara: { $paths }
+doctor-no-hooks = Inga pre-squash- eller post-squash-hooks
+doctor-detached = HEAD är frikopplat, resultatet hamnar inte på en gren
+doctor-branch-ok = { $branch } får squashas
+doctor-failed = { $count } kontroller misslyckades, en squash skulle inte gå igenom
+explain-walk = Lista de senaste { $count } commits från HEAD ({ $head }) med { $backend }
+explain-parent = Slå upp HEAD~{ $count } som ny förälder: { $oid } { $subject }
+explain-root = Ingen commit kommer före dem, så squash-commiten blir en ny rotcommit
+explain-hook = Kör { $hook }-hooken: { $command }
+explain-reset = Gör en mjuk reset av HEAD till { $oid } med { $backend }, index och arbetskatalog behålls
+explain-tree = Skriv indexet som trädet { $tree }
+explain-commit = Skapa en osignerad commit av trädet { $tree } med föräldrarna [{ $parents }] och det valda meddelandet med { $backend }, och flytta HEAD dit
+explain-commit-signed = Skapa en signerad commit av trädet { $tree } med föräldrarna [{ $parents }] och det valda meddelandet med { $backend }, och flytta HEAD dit
+explain-verify = Kontrollera att den nya commiten har trädet { $tree }, annars återställs HEAD till { $head }
+explain-reflog = Slå ihop squashens reflog-poster till en: { $message }
+explain-unchanged = Inget ändrades, kör igen utan --explain för att squasha
+tagged = Taggade { $oid } som { $name }
+cherry-picked = Cherry-pickade squash-commiten till { $branch } som { $oid }
+changelog-written = Lade till { $count } poster i { $path }
+nothing-to-autosquash = Inga fixup!- eller squash!-commits att slå ihop
+squashed = Slog ihop { $count } commits
+squashed-detached = Slog ihop { $count } commits till { $oid }
+    HEAD är frikopplad, kör `git switch -c <gren>` för att behålla resultatet på en gren
+
+## Batchläge
+
+batch-succeeded = ✔ { $path }: { $message }
+batch-failed = ✘ { $path }: { $error }
+batch-summary = Slog ihop i alla { $total } repositories
+batch-summary-failed = { $failed } av { $total } repositories misslyckades
+
+## Förlopp
+
+event-commits = Hittade { $count } commits att slå ihop
+event-reset = Återställde HEAD till { $oid }
+event-hook = Körde { $hook }-hooken
+event-commit = Skapade den ihopslagna commiten { $oid }
+event-rewritten = { $old } → { $new }
+event-push = Pushade { $refspec } till { $remote }
@@ -0,0 +1,77 @@
+use std::io::Write;
+use std::path::Path;
+use anyhow::{Context, Result};
+
+/// How a workflow command annotates the run
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+  Notice,
+  Error
+}
+
+/// Whether this runs on a CI server, where nobody answers prompts. Most of
+/// them set `CI`, GitHub Actions among them.
+pub fn ci() -> bool {
+  std::env::var("CI").is_ok_and(|value| !value.is_empty() && value != "false") || github()
+}
+
+/// Whether this runs in a GitHub Actions workflow
+pub fn github() -> bool {
+  std::env::var("GITHUB_ACTIONS").is_ok_and(|value| value == "true")
+}
+
+/// Sets the step output `name` for later steps to read as
+/// `steps.<id>.outputs.<name>`, when the runner asks for outputs
+pub fn set_output(name: &str, value: &str) -> Result<()> {
+  match std::env::var_os("GITHUB_OUTPUT") {
+    Some(path) => write_output(Path::new(&path), name, value),
+    None => Ok(())
+  }
+}
+
+fn write_output(path: &Path, name: &str, value: &str) -> Result<()> {
+  let mut file = std::fs::OpenOptions::new()
+    .append(true)
+    .create(true)
+    .open(path)
+    .with_context(|| format!("Failed to open {}", path.display()))?;
+  writeln!(file, "{}={}", name, value).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Prints a workflow command that shows `message` as an annotation on the run
+pub fn annotate(level: Level, message: &str) {
+  let command = match level {
+    Level::Notice => "notice",
+    Level::Error => "error"
+  };
+  println!("::{}::{}", command, escape(message));
+}
+
+/// Workflow commands end at the first newline, so it's written as `%0A`
+fn escape(message: &str) -> String {
+  message.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_escape() {
+    assert_eq!(
+      escape("Squash violates 1 policy rule(s):\n  - max_count: 100%"),
+      "Squash violates 1 policy rule(s):%0A  - max_count: 100%25"
+    );
+  }
+
+  #[test]
+  fn test_write_output() -> Result<()> {
+    let dir = tempdir::TempDir::new("actions")?;
+    let path = dir.path().join("output");
+    std::fs::write(&path, "existing=1\n")?;
+    write_output(&path, "new_sha", "abc123")?;
+    write_output(&path, "squashed_count", "3")?;
+    assert_eq!(std::fs::read_to_string(&path)?, "existing=1\nnew_sha=abc123\nsquashed_count=3\n");
+    Ok(())
+  }
+}
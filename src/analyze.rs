@@ -0,0 +1,189 @@
+use std::collections::HashSet;
+use anyhow::Result;
+use git2::{Commit, Oid, Repository};
+use crate::{encoding, squash};
+
+/// Commits looked at on a branch without an upstream
+pub const DEFAULT_AMOUNT: usize = 20;
+
+/// Commits made at most this many seconds after the previous one belong to
+/// the same burst
+pub const BURST_GAP: i64 = 15 * 60;
+
+/// Share of words two subjects need in common to count as similar
+const SIMILARITY: f64 = 0.5;
+
+/// Prefixes `git commit --fixup` and `--squash` put in front of the target's subject
+const AUTOSQUASH: [&str; 3] = ["fixup!", "squash!", "amend!"];
+
+/// Why commits are suggested to be squashed together
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reason {
+  /// `fixup!` and `squash!` commits, folded into the commits they name
+  Fixups,
+  /// Commits made in quick succession
+  Burst,
+  /// Neighbouring commits with nearly the same subject or the same scope
+  Similar
+}
+
+/// Commits that would make sense as one, and how to squash them
+#[derive(Debug, PartialEq)]
+pub struct Suggestion {
+  pub reason:  Reason,
+  /// Oldest first
+  pub commits: Vec<Oid>,
+  pub command: String
+}
+
+/// Suggested groupings among the last `amount` commits, up to the first
+/// merge, which none of them can cross
+pub fn analyze(repo: &Repository, amount: usize) -> Result<Vec<Suggestion>> {
+  let mut commits = squash::commits(repo, amount)?
+    .into_iter()
+    .take_while(|commit| commit.parent_count() <= 1)
+    .collect::<Vec<_>>();
+  commits.reverse();
+  let subjects = commits.iter().map(encoding::summary).collect::<Vec<_>>();
+
+  let mut suggestions = Vec::new();
+  let fixups = (0..commits.len())
+    .filter(|&n| is_fixup(&subjects[n]))
+    .map(|n| commits[n].id())
+    .collect::<Vec<_>>();
+  if !fixups.is_empty() {
+    suggestions.push(Suggestion {
+      reason:  Reason::Fixups,
+      commits: fixups,
+      command: format!("git squash {} --autosquash", commits.len())
+    });
+  }
+
+  let bursts = runs(commits.len(), |n| author_time(&commits[n]) - author_time(&commits[n - 1]) <= BURST_GAP);
+  // Fixups already have their own suggestion
+  let similar = runs(commits.len(), |n| {
+    !is_fixup(&subjects[n - 1]) && !is_fixup(&subjects[n]) && similar(&subjects[n - 1], &subjects[n])
+  });
+  for (reason, runs) in [(Reason::Burst, bursts.clone()), (Reason::Similar, similar)] {
+    for (start, end) in runs {
+      // Commits already suggested as a burst aren't suggested again
+      if reason == Reason::Similar && bursts.iter().any(|&(first, last)| first <= start && end <= last) {
+        continue;
+      }
+      if let Some(command) = command(&commits, start, end) {
+        suggestions.push(Suggestion {
+          reason,
+          commits: commits[start..=end].iter().map(Commit::id).collect(),
+          command
+        });
+      }
+    }
+  }
+
+  Ok(suggestions)
+}
+
+/// Runs of at least two neighbouring commits, as inclusive index ranges,
+/// where `joined(n)` tells whether commit `n` belongs with commit `n - 1`
+fn runs(len: usize, joined: impl Fn(usize) -> bool) -> Vec<(usize, usize)> {
+  let mut runs = Vec::new();
+  let mut start = 0;
+  for n in 1..=len {
+    if n == len || !joined(n) {
+      if n - start >= 2 {
+        runs.push((start, n - 1));
+      }
+      start = n;
+    }
+  }
+  runs
+}
+
+/// `git squash N` for runs at the tip, a range otherwise. A run starting at
+/// the root commit can only be squashed from the tip.
+fn command(commits: &[Commit], start: usize, end: usize) -> Option<String> {
+  if end == commits.len() - 1 {
+    return Some(format!("git squash {}", end - start + 1));
+  }
+
+  let parent = commits[start].parent_id(0).ok()?;
+  Some(format!("git squash {}..{}", short(parent), short(commits[end].id())))
+}
+
+fn short(oid: Oid) -> String {
+  oid.to_string()[..7].to_string()
+}
+
+fn author_time(commit: &Commit) -> i64 {
+  commit.author().when().seconds()
+}
+
+fn is_fixup(subject: &str) -> bool {
+  let subject = subject.trim_start().to_lowercase();
+  AUTOSQUASH.iter().any(|prefix| subject.starts_with(prefix))
+}
+
+/// Whether two subjects share a Conventional Commits scope, like `feat(parser)`
+/// and `fix(parser)`, or most of their words
+fn similar(a: &str, b: &str) -> bool {
+  if let (Some(a), Some(b)) = (scope(a), scope(b)) {
+    return a == b;
+  }
+
+  let (a, b) = (words(a), words(b));
+  let common = a.intersection(&b).count();
+  let all = a.union(&b).count();
+  all > 0 && common as f64 / all as f64 >= SIMILARITY
+}
+
+fn scope(subject: &str) -> Option<String> {
+  let (prefix, _) = subject.split_once(':')?;
+  let (_, scope) = prefix.split_once('(')?;
+  Some(scope.strip_suffix(')')?.to_lowercase())
+}
+
+fn words(subject: &str) -> HashSet<String> {
+  subject
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .map(str::to_lowercase)
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_similar() {
+    assert!(similar("Fix parser", "Fix parser again"));
+    assert!(similar("feat(parser): add lists", "fix(parser): handle tabs"));
+    assert!(!similar("feat(parser): add lists", "feat(cli): add lists"));
+    assert!(!similar("Add lists", "Add tables"));
+  }
+
+  #[test]
+  fn test_runs() {
+    let joined = [false, true, true, false, false, true];
+    assert_eq!(runs(joined.len(), |n| joined[n]), vec![(0, 2), (4, 5)]);
+    assert_eq!(runs(1, |_| true), vec![]);
+  }
+
+  #[test]
+  fn test_analyze() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "a.txt", "a", "Add parser")?;
+    let fixup = commit_file(&repo, "a.txt", "b", "fixup! Add parser")?;
+    commit_file(&repo, "b.txt", "c", "Document the parser")?;
+
+    // The test commits are all made within the same second
+    let suggestions = analyze(&repo, 3)?;
+    assert_eq!(suggestions.len(), 2);
+    assert_eq!((suggestions[0].reason, suggestions[0].commits.clone()), (Reason::Fixups, vec![fixup]));
+    assert_eq!(suggestions[0].command, "git squash 3 --autosquash");
+    assert_eq!((suggestions[1].reason, suggestions[1].commits.len()), (Reason::Burst, 3));
+    assert_eq!(suggestions[1].command, "git squash 3");
+    Ok(())
+  }
+}
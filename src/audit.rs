@@ -0,0 +1,111 @@
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use serde::Serialize;
+use crate::config::Config;
+use crate::identity;
+
+/// One line of the audit log, written after every squash
+#[derive(Debug, Serialize)]
+pub struct Record<'a> {
+  /// Seconds since the Unix epoch
+  pub timestamp: u64,
+  pub user:      String,
+  pub branch:    Option<&'a str>,
+  pub old_head:  String,
+  pub new_head:  String,
+  pub count:     usize,
+  /// The message of the squash commit, or the reflog message when several
+  /// commits were created, e.g. by `--autosquash`
+  pub message:   &'a str
+}
+
+impl<'a> Record<'a> {
+  pub fn new(repo: &Repository, branch: Option<&'a str>, old_head: Oid, new_head: Oid, count: usize, message: &'a str) -> Self {
+    let user = identity::committer(repo)
+      .map(|sig| format!("{} <{}>", sig.name().unwrap_or_default(), sig.email().unwrap_or_default()))
+      .unwrap_or_default();
+    let timestamp = std::time::SystemTime::now()
+      .duration_since(std::time::UNIX_EPOCH)
+      .map(|d| d.as_secs())
+      .unwrap_or_default();
+
+    Self {
+      timestamp,
+      user,
+      branch,
+      old_head: old_head.to_string(),
+      new_head: new_head.to_string(),
+      count,
+      message
+    }
+  }
+}
+
+/// Resolves a configured log path; relative paths are taken relative to the
+/// working directory root (or the git dir for bare repositories)
+pub fn resolve_path(repo: &Repository, path: &Path) -> PathBuf {
+  if path.is_absolute() {
+    return path.to_path_buf();
+  }
+
+  repo.workdir().unwrap_or(repo.path()).join(path)
+}
+
+/// Appends the squash to the audit log, when one is configured. Every way
+/// of rewriting history ends here once it's done.
+pub fn record(
+  repo: &Repository, config: &Config, branch: Option<&str>, old_head: Oid, new_head: Oid, count: usize, message: &str
+) -> Result<()> {
+  let Some(path) = &config.audit_log else {
+    return Ok(());
+  };
+
+  append(&resolve_path(repo, path), &Record::new(repo, branch, old_head, new_head, count, message))
+}
+
+/// Appends `record` as a single JSON line to `path`
+pub fn append(path: &Path, record: &Record) -> Result<()> {
+  let mut line = serde_json::to_string(record).context("Failed to serialize audit record")?;
+  line.push('\n');
+
+  let mut file = OpenOptions::new()
+    .create(true)
+    .append(true)
+    .open(path)
+    .with_context(|| format!("Failed to open audit log {}", path.display()))?;
+  file
+    .write_all(line.as_bytes())
+    .with_context(|| format!("Failed to write audit log {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_appends_json_lines() -> Result<()> {
+    let (dir, repo) = repo_with_commits(2)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let path = resolve_path(&repo, Path::new("audit.jsonl"));
+    assert_eq!(path, dir.path().join("audit.jsonl"));
+
+    append(&path, &Record::new(&repo, Some("main"), head.id(), head.parent_id(0)?, 2, "First"))?;
+    append(&path, &Record::new(&repo, None, head.id(), head.parent_id(0)?, 3, "Second"))?;
+
+    let content = std::fs::read_to_string(&path)?;
+    let lines = content
+      .lines()
+      .map(serde_json::from_str::<serde_json::Value>)
+      .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[0]["branch"], "main");
+    assert_eq!(lines[0]["old_head"], head.id().to_string());
+    assert_eq!(lines[1]["count"], 3);
+    assert_eq!(lines[1]["message"], "Second");
+    Ok(())
+  }
+}
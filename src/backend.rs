@@ -0,0 +1,694 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use git2::{Cred, CredentialType, Oid, PushOptions, RemoteCallbacks, Repository, ResetType, Sort};
+use serde::Deserialize;
+use crate::identity;
+use crate::retry::retry_on_lock;
+use crate::sign;
+use crate::squash::Commitable;
+use crate::SquashError;
+
+/// The git operations a squash is made of, so each can be served by libgit2
+/// or the `git` binary and tested on its own
+pub trait GitBackend {
+  /// The last `amount` commits from `HEAD` in topological order, newest first
+  fn walk(&self, repo: &Repository, amount: usize) -> Result<Vec<Oid>>;
+  /// The commit `spec` names, e.g. `HEAD~2` or a branch
+  fn resolve(&self, repo: &Repository, spec: &str) -> Result<Oid>;
+  /// Moves `HEAD` to `base`, keeping the index and working tree
+  fn reset_soft(&self, repo: &Repository, base: Oid) -> Result<()>;
+  /// Commits the index on top of `HEAD` and moves `HEAD` to it
+  fn commit(&self, repo: &Repository, message: &str, sign: bool) -> Result<Oid>;
+  /// Pushes `refspec` to `remote`
+  fn push(&self, repo: &Repository, remote: &str, refspec: &str) -> Result<()>;
+}
+
+/// What serves an operation
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+  /// libgit2, in-process
+  #[default]
+  Libgit2,
+  /// The `git` binary, so commit hooks, the configured signing program and fsmonitor behave exactly as in git
+  Cli,
+  /// gitoxide, in-process and without C code, when built with the `gix` feature
+  #[cfg(feature = "gix")]
+  Gix
+}
+
+impl Backend {
+  pub fn name(self) -> &'static str {
+    match self {
+      Backend::Libgit2 => "libgit2",
+      Backend::Cli => "cli",
+      #[cfg(feature = "gix")]
+      Backend::Gix => "gix"
+    }
+  }
+}
+
+#[cfg(not(feature = "gix"))]
+const BACKEND_NAMES: &str = "libgit2 or cli";
+#[cfg(feature = "gix")]
+const BACKEND_NAMES: &str = "libgit2, cli or gix";
+
+/// The backend for each operation, written as `cli` for all of them or as
+/// `libgit2,commit=cli` to pick single operations
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub struct Backends {
+  pub walk:    Backend,
+  pub resolve: Backend,
+  pub reset:   Backend,
+  pub commit:  Backend,
+  pub push:    Backend
+}
+
+impl Backends {
+  pub fn all(backend: Backend) -> Self {
+    Self {
+      walk: backend, resolve: backend, reset: backend, commit: backend, push: backend
+    }
+  }
+
+  pub fn parse(value: &str) -> Result<Self, String> {
+    let mut backends = Self::default();
+    for part in value.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+      let (operation, name) = match part.split_once('=') {
+        Some((operation, name)) => (Some(operation.trim()), name.trim()),
+        None => (None, part)
+      };
+      let backend =
+        Backend::from_str(name, true).map_err(|_| format!("unknown backend `{}`, expected {}", name, BACKEND_NAMES))?;
+
+      match operation {
+        None => backends = Self::all(backend),
+        Some("walk") => backends.walk = backend,
+        Some("resolve") => backends.resolve = backend,
+        Some("reset") => backends.reset = backend,
+        Some("commit") => backends.commit = backend,
+        Some("push") => backends.push = backend,
+        Some(operation) => {
+          return Err(format!("unknown operation `{}`, expected walk, resolve, reset, commit or push", operation));
+        }
+      }
+    }
+
+    Ok(backends)
+  }
+
+  /// What walks `repo`: `git rev-list` instead of libgit2 when the repository
+  /// has a commit-graph, whose generation numbers let git stop early
+  pub fn walk_backend(&self, repo: &Repository) -> Backend {
+    match self.walk {
+      Backend::Libgit2 if has_commit_graph(repo) => Backend::Cli,
+      backend => backend
+    }
+  }
+}
+
+impl TryFrom<String> for Backends {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    Self::parse(&value)
+  }
+}
+
+impl GitBackend for Backends {
+  fn walk(&self, repo: &Repository, amount: usize) -> Result<Vec<Oid>> {
+    Router::new(*self).walk(repo, amount)
+  }
+
+  fn resolve(&self, repo: &Repository, spec: &str) -> Result<Oid> {
+    Router::new(*self).resolve(repo, spec)
+  }
+
+  fn reset_soft(&self, repo: &Repository, base: Oid) -> Result<()> {
+    Router::new(*self).reset_soft(repo, base)
+  }
+
+  fn commit(&self, repo: &Repository, message: &str, sign: bool) -> Result<Oid> {
+    Router::new(*self).commit(repo, message, sign)
+  }
+
+  fn push(&self, repo: &Repository, remote: &str, refspec: &str) -> Result<()> {
+    Router::new(*self).push(repo, remote, refspec)
+  }
+}
+
+/// Hands each operation to the implementation `backends` picks for it,
+/// which tests swap for stubs
+struct Router<'a> {
+  backends: Backends,
+  libgit2:  &'a dyn GitBackend,
+  cli:      &'a dyn GitBackend,
+  #[cfg(feature = "gix")]
+  gix:      &'a dyn GitBackend
+}
+
+impl Router<'static> {
+  fn new(backends: Backends) -> Self {
+    Self {
+      backends,
+      libgit2: &Libgit2,
+      cli: &GitCli,
+      #[cfg(feature = "gix")]
+      gix: &Gix
+    }
+  }
+}
+
+impl Router<'_> {
+  fn get(&self, backend: Backend) -> &dyn GitBackend {
+    match backend {
+      Backend::Libgit2 => self.libgit2,
+      Backend::Cli => self.cli,
+      #[cfg(feature = "gix")]
+      Backend::Gix => self.gix
+    }
+  }
+}
+
+impl GitBackend for Router<'_> {
+  fn walk(&self, repo: &Repository, amount: usize) -> Result<Vec<Oid>> {
+    self.get(self.backends.walk_backend(repo)).walk(repo, amount)
+  }
+
+  fn resolve(&self, repo: &Repository, spec: &str) -> Result<Oid> {
+    self.get(self.backends.resolve).resolve(repo, spec)
+  }
+
+  fn reset_soft(&self, repo: &Repository, base: Oid) -> Result<()> {
+    self.get(self.backends.reset).reset_soft(repo, base)
+  }
+
+  fn commit(&self, repo: &Repository, message: &str, sign: bool) -> Result<Oid> {
+    self.get(self.backends.commit).commit(repo, message, sign)
+  }
+
+  fn push(&self, repo: &Repository, remote: &str, refspec: &str) -> Result<()> {
+    self.get(self.backends.push).push(repo, remote, refspec)
+  }
+}
+
+pub struct Libgit2;
+
+impl GitBackend for Libgit2 {
+  /// Follows first parents while the history is linear, which reads only the
+  /// commits asked for. Once a merge shows up, a topological revwalk reads
+  /// the whole history before returning the first commit; `GitCli` can stop
+  /// early there when the repository has a commit-graph, so `Backends` hands
+  /// the walk to it then.
+  fn walk(&self, repo: &Repository, amount: usize) -> Result<Vec<Oid>> {
+    if let Some(commits) = linear_walk(repo, amount)? {
+      return Ok(commits);
+    }
+
+    let mut revwalk = repo.revwalk().context("Failed to get revwalk")?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL).context("Failed to set sorting")?;
+    revwalk.push_head().context("Failed to push HEAD")?;
+    revwalk.take(amount).collect::<Result<Vec<_>, _>>().context("Failed to walk history")
+  }
+
+  fn resolve(&self, repo: &Repository, spec: &str) -> Result<Oid> {
+    let object = repo.revparse_single(spec).with_context(|| format!("Failed to resolve {}", spec))?;
+    let commit = object.peel_to_commit().with_context(|| format!("{} is not a commit", spec))?;
+    Ok(commit.id())
+  }
+
+  fn reset_soft(&self, repo: &Repository, base: Oid) -> Result<()> {
+    let base = repo.find_object(base, None).context("Failed to find base")?;
+    retry_on_lock(repo, || repo.reset(&base, ResetType::Soft, None)).context("Failed to reset")
+  }
+
+  fn commit(&self, repo: &Repository, message: &str, sign: bool) -> Result<Oid> {
+    match sign {
+      true => sign::commit_signed(repo, message),
+      false => repo.commit_with_msg(message)
+    }
+  }
+
+  fn push(&self, repo: &Repository, remote: &str, refspec: &str) -> Result<()> {
+    let config = repo.config().context("Failed to get config")?;
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(|url, username, allowed| {
+      if allowed.contains(CredentialType::SSH_KEY) {
+        return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+      }
+      Cred::credential_helper(&config, url, username)
+    });
+
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    repo
+      .find_remote(remote)
+      .with_context(|| format!("Failed to find remote {}", remote))?
+      .push(&[refspec], Some(&mut options))
+      .with_context(|| format!("Failed to push {} to {}", refspec, remote))
+  }
+}
+
+/// Whether `git commit-graph write` left a single commit-graph file or a
+/// chain of them in the object database
+fn has_commit_graph(repo: &Repository) -> bool {
+  let info = crate::hooks::common_dir(repo).join("objects").join("info");
+  info.join("commit-graph").is_file() || info.join("commit-graphs").join("commit-graph-chain").is_file()
+}
+
+/// The last `amount` commits along first parents, or `None` if one of them
+/// is a merge and topological order could differ
+fn linear_walk(repo: &Repository, amount: usize) -> Result<Option<Vec<Oid>>> {
+  let mut commits = Vec::with_capacity(amount);
+  let mut next = Some(
+    repo
+      .head()
+      .context("Failed to get HEAD")?
+      .peel_to_commit()
+      .context("Failed to resolve HEAD")?
+  );
+  while let Some(commit) = next.take() {
+    if commits.len() == amount {
+      break;
+    }
+    if commit.parent_count() > 1 {
+      return Ok(None);
+    }
+
+    commits.push(commit.id());
+    next = commit.parents().next();
+  }
+
+  Ok(Some(commits))
+}
+
+pub struct GitCli;
+
+impl GitBackend for GitCli {
+  fn walk(&self, repo: &Repository, amount: usize) -> Result<Vec<Oid>> {
+    let output = git(repo, &["rev-list", "--topo-order", "-n", &amount.to_string(), "HEAD"], None)?;
+    output
+      .lines()
+      .map(|line| Oid::from_str(line).with_context(|| format!("Unexpected rev-list output `{}`", line)))
+      .collect()
+  }
+
+  fn resolve(&self, repo: &Repository, spec: &str) -> Result<Oid> {
+    let output = git(repo, &["rev-parse", "--verify", "--quiet", &format!("{}^{{commit}}", spec)], None)?;
+    Oid::from_str(output.trim()).with_context(|| format!("Failed to resolve {}", spec))
+  }
+
+  fn reset_soft(&self, repo: &Repository, base: Oid) -> Result<()> {
+    git(repo, &["reset", "--soft", &base.to_string()], None).map(|_| ())
+  }
+
+  /// Runs `git commit`, which runs the `pre-commit` and `commit-msg` hooks
+  /// and signs the way git does. The message is UTF-8 whatever
+  /// `i18n.commitEncoding` says, so git must not declare another encoding.
+  fn commit(&self, repo: &Repository, message: &str, sign: bool) -> Result<Oid> {
+    let sign = if sign { "--gpg-sign" } else { "--no-gpg-sign" };
+    let mut args = vec!["-c", "i18n.commitEncoding=UTF-8", "commit", "--quiet", "--allow-empty", "--cleanup=verbatim", sign];
+    let author = identity::chosen_author().map(|author| format!("--author={}", author));
+    args.extend(author.as_deref());
+    args.push("--file=-");
+    git(repo, &args, Some(message))?;
+    repo.refname_to_id("HEAD").context("Failed to resolve HEAD")
+  }
+
+  fn push(&self, repo: &Repository, remote: &str, refspec: &str) -> Result<()> {
+    git(repo, &["push", "--quiet", remote, refspec], None).map(|_| ())
+  }
+}
+
+/// gitoxide, in-process and without C code. Resolving and pushing are left
+/// to libgit2, and signed commits are made the way libgit2 makes them.
+#[cfg(feature = "gix")]
+pub struct Gix;
+
+#[cfg(feature = "gix")]
+impl GitBackend for Gix {
+  fn walk(&self, repo: &Repository, amount: usize) -> Result<Vec<Oid>> {
+    use gix::traverse::commit::topo::{Builder, Sorting};
+
+    let repo = open_gix(repo)?;
+    let head = repo.head_id().context("Failed to resolve HEAD")?;
+    let graph = repo.commit_graph_if_enabled().context("Failed to read the commit-graph")?;
+    Builder::from_iters(&repo.objects, [head.detach()], None::<Vec<gix::ObjectId>>)
+      .sorting(Sorting::TopoOrder)
+      .with_commit_graph(graph)
+      .build()
+      .context("Failed to get revwalk")?
+      .take(amount)
+      .map(|info| Ok(to_oid(info.context("Failed to walk history")?.id)))
+      .collect()
+  }
+
+  fn resolve(&self, repo: &Repository, spec: &str) -> Result<Oid> {
+    Libgit2.resolve(repo, spec)
+  }
+
+  fn reset_soft(&self, repo: &Repository, base: Oid) -> Result<()> {
+    use gix::refs::transaction::{PreviousValue, RefEdit};
+
+    let committer = identity::committer(repo)?;
+    let repo = open_gix(repo)?;
+    let head = repo.head_id().context("Failed to resolve HEAD")?;
+    let edit = RefEdit::update(
+      "HEAD".try_into().context("Failed to name HEAD")?,
+      to_object_id(base),
+      PreviousValue::MustExistAndMatch(gix::refs::Target::Object(head.detach())),
+      format!("reset: moving to {}", base)
+    )
+    .with_deref(true);
+    repo
+      .edit_references_as([edit], Some(gix_signature(&committer).to_ref(&mut gix::date::parse::TimeBuf::default())))
+      .context("Failed to reset")?;
+    Ok(())
+  }
+
+  fn commit(&self, repo: &Repository, message: &str, sign: bool) -> Result<Oid> {
+    if sign {
+      return sign::commit_signed(repo, message);
+    }
+    let author = gix_signature(&identity::author(repo)?);
+    let committer = gix_signature(&identity::committer(repo)?);
+    let repo = open_gix(repo)?;
+
+    // The index as a tree, the way `git write-tree` writes it
+    let index = repo.index_or_empty().context("Failed to get index")?;
+    let mut tree = repo
+      .edit_tree(gix::ObjectId::empty_tree(repo.object_hash()))
+      .context("Failed to write tree")?;
+    for entry in index.entries() {
+      if entry.flags.contains(gix::index::entry::Flags::INTENT_TO_ADD) {
+        continue;
+      }
+      if entry.stage() != gix::index::entry::Stage::Unconflicted {
+        bail!("Failed to write tree: {} has conflicts", entry.path(&index));
+      }
+      let kind = entry.mode.to_tree_entry_mode().context("Failed to write tree")?.kind();
+      tree.upsert(entry.path(&index), kind, entry.id).context("Failed to write tree")?;
+    }
+    let tree = tree.write().context("Failed to write tree")?;
+
+    let parents = repo.head_id().ok().map(|head| head.detach());
+    let (mut author_time, mut committer_time) = Default::default();
+    let oid = repo
+      .commit_as(
+        committer.to_ref(&mut committer_time),
+        author.to_ref(&mut author_time),
+        "HEAD",
+        message,
+        tree,
+        parents
+      )
+      .context("Could not commit")?;
+    Ok(to_oid(oid.detach()))
+  }
+
+  fn push(&self, repo: &Repository, remote: &str, refspec: &str) -> Result<()> {
+    Libgit2.push(repo, remote, refspec)
+  }
+}
+
+/// `repo` as gitoxide opens it, fresh each time so it sees what libgit2 wrote
+#[cfg(feature = "gix")]
+fn open_gix(repo: &Repository) -> Result<gix::Repository> {
+  gix::open(repo.path()).context("Failed to open repository")
+}
+
+#[cfg(feature = "gix")]
+fn to_oid(id: gix::ObjectId) -> Oid {
+  Oid::from_bytes(id.as_bytes()).expect("object ids are 20 bytes")
+}
+
+#[cfg(feature = "gix")]
+fn to_object_id(oid: Oid) -> gix::ObjectId {
+  gix::ObjectId::from_bytes_or_panic(oid.as_bytes())
+}
+
+#[cfg(feature = "gix")]
+fn gix_signature(signature: &git2::Signature) -> gix::actor::Signature {
+  gix::actor::Signature {
+    name:  signature.name_bytes().into(),
+    email: signature.email_bytes().into(),
+    time:  gix::date::Time {
+      seconds: signature.when().seconds(), offset: signature.when().offset_minutes() * 60
+    }
+  }
+}
+
+/// Runs `git` in the working directory and returns what it printed on
+/// stdout, failing with its output when it exits non-zero
+fn git(repo: &Repository, args: &[&str], stdin: Option<&str>) -> Result<String> {
+  let command = format!("git {}", args.join(" "));
+  let mut child = Command::new("git")
+    .args(args)
+    .current_dir(repo.workdir().unwrap_or(repo.path()))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .with_context(|| format!("Failed to run `{}`", command))?;
+
+  let input = stdin.unwrap_or_default();
+  child
+    .stdin
+    .take()
+    .expect("stdin is piped")
+    .write_all(input.as_bytes())
+    .with_context(|| format!("Failed to write to `{}`", command))?;
+
+  let output = child.wait_with_output().with_context(|| format!("Failed to run `{}`", command))?;
+  if !output.status.success() {
+    let report = [output.stdout, output.stderr].concat();
+    let report = String::from_utf8_lossy(&report);
+    bail!(SquashError::GitCommand(format!(
+      "`{}` exited with {}:\n{}",
+      command,
+      output.status,
+      report.trim_end()
+    )));
+  }
+
+  String::from_utf8(output.stdout).with_context(|| format!("`{}` printed invalid UTF-8", command))
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+  use std::os::unix::fs::PermissionsExt;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_parse_backends() {
+    assert_eq!(Backends::parse("cli"), Ok(Backends::all(Backend::Cli)));
+    assert_eq!(
+      Backends::parse("libgit2, commit=cli"),
+      Ok(Backends {
+        commit: Backend::Cli,
+        ..Backends::default()
+      })
+    );
+    #[cfg(not(feature = "gix"))]
+    assert!(Backends::parse("gix").is_err());
+    #[cfg(feature = "gix")]
+    assert_eq!(Backends::parse("gix"), Ok(Backends::all(Backend::Gix)));
+    assert!(Backends::parse("fetch=cli").is_err());
+  }
+
+  /// Fails every operation with an error naming the stub and the operation
+  struct Stub(&'static str);
+
+  impl GitBackend for Stub {
+    fn walk(&self, _repo: &Repository, _amount: usize) -> Result<Vec<Oid>> {
+      bail!("{} walk", self.0)
+    }
+
+    fn resolve(&self, _repo: &Repository, _spec: &str) -> Result<Oid> {
+      bail!("{} resolve", self.0)
+    }
+
+    fn reset_soft(&self, _repo: &Repository, _base: Oid) -> Result<()> {
+      bail!("{} reset", self.0)
+    }
+
+    fn commit(&self, _repo: &Repository, _message: &str, _sign: bool) -> Result<Oid> {
+      bail!("{} commit", self.0)
+    }
+
+    fn push(&self, _repo: &Repository, _remote: &str, _refspec: &str) -> Result<()> {
+      bail!("{} push", self.0)
+    }
+  }
+
+  #[test]
+  fn test_routes_each_operation() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let router = Router {
+      backends:                    Backends::parse("cli,walk=libgit2,push=libgit2").unwrap(),
+      libgit2:                     &Stub("libgit2"),
+      cli:                         &Stub("cli"),
+      #[cfg(feature = "gix")]
+      gix:                         &Stub("gix")
+    };
+
+    let head = repo.head()?.target().unwrap();
+    let errors = [
+      router.walk(&repo, 1).unwrap_err(),
+      router.resolve(&repo, "HEAD").unwrap_err(),
+      router.reset_soft(&repo, head).unwrap_err(),
+      router.commit(&repo, "Squashed", false).unwrap_err(),
+      router.push(&repo, "origin", "main").unwrap_err()
+    ];
+    let errors = errors.iter().map(ToString::to_string).collect::<Vec<_>>();
+    assert_eq!(errors, ["libgit2 walk", "cli resolve", "cli reset", "cli commit", "libgit2 push"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_walks_through_git_with_a_commit_graph() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let router = |backends| {
+      Router {
+        backends,
+        libgit2: &Stub("libgit2"),
+        cli: &Stub("cli"),
+        #[cfg(feature = "gix")]
+        gix: &Stub("gix")
+      }
+    };
+    assert_eq!(router(Backends::default()).walk(&repo, 2).unwrap_err().to_string(), "libgit2 walk");
+
+    git(&repo, &["commit-graph", "write", "--reachable"], None)?;
+    assert_eq!(Backends::default().walk_backend(&repo), Backend::Cli);
+    assert_eq!(router(Backends::default()).walk(&repo, 2).unwrap_err().to_string(), "cli walk");
+    assert_eq!(Backends::default().walk(&repo, 2)?, GitCli.walk(&repo, 2)?);
+
+    std::fs::remove_file(repo.path().join("objects/info/commit-graph"))?;
+    git(&repo, &["commit-graph", "write", "--reachable", "--split"], None)?;
+    assert!(has_commit_graph(&repo));
+    Ok(())
+  }
+
+  #[test]
+  fn test_push_to_a_local_remote() -> Result<()> {
+    let (dir, repo) = repo_with_commits(2)?;
+    let remote = dir.path().join("remote.git");
+    Repository::init_bare(&remote)?;
+    repo.remote("origin", remote.to_str().unwrap())?;
+    let head = repo.head()?.target().unwrap();
+    let remote = Repository::open_bare(&remote)?;
+
+    Libgit2.push(&repo, "origin", "HEAD:refs/heads/libgit2")?;
+    GitCli.push(&repo, "origin", "HEAD:refs/heads/cli")?;
+    assert_eq!(remote.refname_to_id("refs/heads/libgit2")?, head);
+    assert_eq!(remote.refname_to_id("refs/heads/cli")?, head);
+
+    // Without forcing, what's there can't be replaced
+    repo.reset(&repo.revparse_single("HEAD~1")?, ResetType::Hard, None)?;
+    commit_file(&repo, "diverged.txt", "diverged", "Diverged")?;
+    assert!(GitCli.push(&repo, "origin", "HEAD:refs/heads/cli").is_err());
+    assert!(Libgit2.push(&repo, "origin", "HEAD:refs/heads/libgit2").is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_backends_agree() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(4)?;
+    assert_eq!(Libgit2.walk(&repo, 3)?, GitCli.walk(&repo, 3)?);
+    assert_eq!(Libgit2.resolve(&repo, "HEAD~2")?, GitCli.resolve(&repo, "HEAD~2")?);
+    assert!(GitCli.resolve(&repo, "missing").is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_walk_through_merges() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let base = repo.head()?.peel_to_commit()?;
+    commit_file(&repo, "feature.txt", "feature", "Feature work")?;
+    let feature = repo.head()?.peel_to_commit()?;
+    repo.reset(base.as_object(), ResetType::Hard, None)?;
+    commit_file(&repo, "main.txt", "main", "Main work")?;
+    let main = repo.head()?.peel_to_commit()?;
+    let tree = repo.find_tree(repo.merge_commits(&main, &feature, None)?.write_tree_to(&repo)?)?;
+    let signature = repo.signature()?;
+    repo.commit(Some("HEAD"), &signature, &signature, "Merge feature", &tree, &[&main, &feature])?;
+    commit_file(&repo, "after.txt", "after", "After merge")?;
+
+    assert_eq!(linear_walk(&repo, 1)?.map(|commits| commits.len()), Some(1));
+    assert_eq!(linear_walk(&repo, 3)?, None);
+    assert_eq!(Libgit2.walk(&repo, 5)?, GitCli.walk(&repo, 5)?);
+    assert_eq!(Libgit2.walk(&repo, 10)?.len(), 6);
+
+    git(&repo, &["commit-graph", "write", "--reachable"], None)?;
+    assert_eq!(Libgit2.walk(&repo, 5)?, GitCli.walk(&repo, 5)?);
+    Ok(())
+  }
+
+  #[test]
+  fn test_reset_and_commit_through_git() -> Result<()> {
+    let (dir, repo) = repo_with_commits(3)?;
+    let base = repo.revparse_single("HEAD~2")?.id();
+    let hook = repo.path().join("hooks").join("commit-msg");
+    std::fs::create_dir_all(hook.parent().unwrap())?;
+    std::fs::write(&hook, "#!/bin/sh\ncp \"$1\" hook.out\n")?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+    GitCli.reset_soft(&repo, base)?;
+    let oid = GitCli.commit(&repo, "Squashed\n\nBody", false)?;
+
+    let head = repo.find_commit(oid)?;
+    assert_eq!(head.message(), Some("Squashed\n\nBody"));
+    assert_eq!(head.parent_id(0)?, base);
+    assert_eq!(std::fs::read_to_string(dir.path().join("hook.out"))?, "Squashed\n\nBody");
+    Ok(())
+  }
+
+  #[cfg(feature = "gix")]
+  #[test]
+  fn test_walk_through_gix() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let base = repo.head()?.peel_to_commit()?;
+    commit_file(&repo, "feature.txt", "feature", "Feature work")?;
+    let feature = repo.head()?.peel_to_commit()?;
+    repo.reset(base.as_object(), ResetType::Hard, None)?;
+    commit_file(&repo, "main.txt", "main", "Main work")?;
+    let main = repo.head()?.peel_to_commit()?;
+    let tree = repo.find_tree(repo.merge_commits(&main, &feature, None)?.write_tree_to(&repo)?)?;
+    let signature = repo.signature()?;
+    repo.commit(Some("HEAD"), &signature, &signature, "Merge feature", &tree, &[&main, &feature])?;
+    commit_file(&repo, "after.txt", "after", "After merge")?;
+
+    assert_eq!(Gix.walk(&repo, 5)?, GitCli.walk(&repo, 5)?);
+    assert_eq!(Gix.walk(&repo, 10)?, GitCli.walk(&repo, 10)?);
+    git(&repo, &["commit-graph", "write", "--reachable"], None)?;
+    assert_eq!(Gix.walk(&repo, 5)?, GitCli.walk(&repo, 5)?);
+    Ok(())
+  }
+
+  #[cfg(feature = "gix")]
+  #[test]
+  fn test_reset_and_commit_through_gix() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    commit_file(&repo, "nested/deeper/file.txt", "nested", "Nested")?;
+    let head = repo.head()?.peel_to_commit()?;
+    let base = repo.revparse_single("HEAD~2")?.id();
+
+    Gix.reset_soft(&repo, base)?;
+    assert_eq!(repo.head()?.target(), Some(base));
+    assert!(repo.head()?.is_branch());
+    let oid = Gix.commit(&repo, "Squashed\n\nBody", false)?;
+
+    let squash = repo.find_commit(oid)?;
+    assert_eq!(squash.message(), Some("Squashed\n\nBody"));
+    assert_eq!(squash.parent_ids().collect::<Vec<_>>(), [base]);
+    assert_eq!(squash.tree_id(), head.tree_id());
+    assert_eq!(repo.head()?.target(), Some(oid));
+    assert_eq!(GitCli.resolve(&repo, "HEAD@{1}")?, base);
+    Ok(())
+  }
+}
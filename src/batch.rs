@@ -0,0 +1,80 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Context, Result};
+use git2::Repository;
+use crate::i18n::t;
+
+/// Repository paths listed in a manifest, one per line. Blank lines and
+/// `#` comments are skipped, relative paths are relative to the manifest.
+pub fn read_manifest(path: &Path) -> Result<Vec<PathBuf>> {
+  let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read manifest {}", path.display()))?;
+  let dir = path.parent().unwrap_or(Path::new(""));
+
+  Ok(
+    content
+      .lines()
+      .map(str::trim)
+      .filter(|line| !line.is_empty() && !line.starts_with('#'))
+      .map(|line| dir.join(line))
+      .collect()
+  )
+}
+
+/// Runs `squash` in each repository in turn and prints how it went, without
+/// stopping at the first failure. Returns the number of repositories that failed.
+pub fn run<F>(paths: &[PathBuf], mut squash: F) -> usize
+where F: FnMut(&Repository) -> Result<String> {
+  let mut failed = 0;
+
+  for path in paths {
+    let outcome = Repository::open(path)
+      .with_context(|| format!("Failed to open repo {}", path.display()))
+      .and_then(|repo| squash(&repo));
+
+    match outcome {
+      Ok(message) => eprintln!("{}", t!("batch-succeeded", path = path.display(), message = message)),
+      Err(error) => {
+        failed += 1;
+        eprintln!("{}", t!("batch-failed", path = path.display(), error = format!("{:#}", error)));
+      }
+    }
+  }
+
+  failed
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+  use anyhow::bail;
+
+  #[test]
+  fn test_read_manifest() -> Result<()> {
+    let dir = tempdir::TempDir::new("manifest")?;
+    let manifest = dir.path().join("repos.txt");
+    std::fs::write(&manifest, "# bots\nalpha\n\n  /srv/beta  \n")?;
+
+    assert_eq!(read_manifest(&manifest)?, [dir.path().join("alpha"), PathBuf::from("/srv/beta")]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_keeps_going_after_a_failure() -> Result<()> {
+    let (first, _repo) = repo_with_commits(2)?;
+    let (second, _repo) = repo_with_commits(3)?;
+    let missing = first.path().join("missing");
+    let paths = [first.path().to_path_buf(), missing, second.path().to_path_buf()];
+
+    let mut visited = 0;
+    let failed = run(&paths, |repo| {
+      visited += 1;
+      match repo.head()?.peel_to_commit()?.summary() {
+        Some("Commit 1") => bail!("policy violated"),
+        _ => Ok("ok".to_string())
+      }
+    });
+
+    assert_eq!((visited, failed), (2, 2));
+    Ok(())
+  }
+}
@@ -0,0 +1,137 @@
+use anyhow::{bail, Context, Result};
+use git2::{BranchType, Oid, Repository};
+use crate::i18n::t;
+use crate::{encoding, identity, sign, SquashError};
+
+/// Local branches other than the current one whose history includes any of
+/// `commits`; they keep the old commits and diverge once the range is rewritten
+pub fn containing(repo: &Repository, commits: &[Oid]) -> Result<Vec<String>> {
+  let current = repo
+    .head()
+    .ok()
+    .filter(|head| head.is_branch())
+    .and_then(|head| head.name().map(String::from));
+  let mut names = Vec::new();
+
+  for branch in repo.branches(Some(BranchType::Local)).context("Failed to list branches")? {
+    let (branch, _) = branch.context("Failed to read branch")?;
+    let reference = branch.get();
+    if reference.name().map(String::from) == current {
+      continue;
+    }
+
+    let Some(tip) = reference.target() else {
+      continue;
+    };
+
+    let contains = |commit: &Oid| tip == *commit || repo.graph_descendant_of(tip, *commit).unwrap_or(false);
+    if commits.iter().any(contains) {
+      names.push(branch.name().ok().flatten().unwrap_or_default().to_string());
+    }
+  }
+
+  Ok(names)
+}
+
+/// Refuses to cherry-pick onto `name` unless it is a local branch that isn't
+/// checked out here or in another worktree, whose files would go stale
+pub fn ensure_pickable(repo: &Repository, name: &str) -> Result<()> {
+  let branch = repo
+    .find_branch(name, BranchType::Local)
+    .map_err(|_| SquashError::Refused(t!("no-such-branch", branch = name)))?;
+  let reference = branch.get().name().unwrap_or_default().to_string();
+
+  let mut heads = vec![repo.head().ok().and_then(|head| head.name().map(String::from))];
+  for worktree in repo.worktrees().context("Failed to list worktrees")?.iter().flatten() {
+    let worktree = repo.find_worktree(worktree).context("Failed to find worktree")?;
+    if let Ok(checkout) = Repository::open_from_worktree(&worktree) {
+      heads.push(checkout.head().ok().and_then(|head| head.name().map(String::from)));
+    }
+  }
+  if heads.into_iter().flatten().any(|head| head == reference) {
+    bail!(SquashError::Refused(t!("branch-checked-out", branch = name)));
+  }
+
+  Ok(())
+}
+
+/// Cherry-picks `commit` onto the local branch `name` without checking it
+/// out, returning the new tip. Conflicts leave the branch as it was. The
+/// copy is signed when `commit` is.
+pub fn cherry_pick_onto(repo: &Repository, commit: Oid, name: &str) -> Result<Oid> {
+  let commit = repo.find_commit(commit).context("Failed to find commit")?;
+  let mut branch = repo.find_branch(name, BranchType::Local).context("Failed to find branch")?;
+  let tip = branch.get().peel_to_commit().context("Failed to resolve branch")?;
+
+  let mut index = repo.cherrypick_commit(&commit, &tip, 0, None).context("Failed to cherry-pick")?;
+  if index.has_conflicts() {
+    let mut paths = Vec::new();
+    for conflict in index.conflicts().context("Failed to read conflicts")? {
+      let conflict = conflict.context("Failed to read conflict")?;
+      let entry = conflict.our.or(conflict.their).or(conflict.ancestor);
+      paths.extend(entry.map(|entry| String::from_utf8_lossy(&entry.path).into_owned()));
+    }
+    bail!(SquashError::Refused(t!("cherry-pick-conflicts", branch = name, paths = paths.join(", "))));
+  }
+
+  let tree = index.write_tree_to(repo).context("Failed to write tree")?;
+  let tree = repo.find_tree(tree).context("Failed to find tree")?;
+  let message = encoding::message(&commit);
+  let oid = match sign::is_signed(repo, commit.id()) {
+    true => sign::create_signed_with(repo, &commit.author(), &message, &tree, &[&tip])?,
+    false => {
+      let committer = identity::committer(repo)?;
+      repo
+        .commit(None, &commit.author(), &committer, &message, &tree, &[&tip])
+        .context("Failed to commit cherry-pick")?
+    }
+  };
+
+  let summary = encoding::summary(&commit);
+  branch
+    .get_mut()
+    .set_target(oid, &format!("cherry-pick: {}", summary))
+    .with_context(|| format!("Failed to update {}", name))?;
+  Ok(oid)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_finds_branches_sharing_the_range() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let middle = head.parent(0)?;
+    let base = middle.parent(0)?;
+
+    repo.branch("shares-middle", &middle, false)?;
+    repo.branch("before-range", &base, false)?;
+
+    let branches = containing(&repo, &[head.id(), middle.id()])?;
+    assert_eq!(branches, ["shares-middle"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_cherry_pick_onto() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let base = repo.head()?.peel_to_commit()?;
+    repo.branch("release", &base, false)?;
+    let head = commit_file(&repo, "fix.txt", "fixed", "Fix parser")?;
+
+    assert!(ensure_pickable(&repo, "missing").is_err());
+    let current = repo.head()?.shorthand().unwrap().to_string();
+    assert!(ensure_pickable(&repo, &current).is_err());
+    ensure_pickable(&repo, "release")?;
+
+    let picked = cherry_pick_onto(&repo, head, "release")?;
+    let picked = repo.find_commit(picked)?;
+    assert_eq!((picked.parent_id(0)?, picked.message()), (base.id(), Some("Fix parser")));
+    assert_eq!(repo.revparse_single("release")?.id(), picked.id());
+    assert!(picked.tree()?.get_name("fix.txt").is_some());
+    Ok(())
+  }
+}
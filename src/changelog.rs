@@ -0,0 +1,157 @@
+use std::path::Path;
+use anyhow::{Context, Result};
+use git2::Commit;
+use crate::conventional;
+use crate::encoding;
+
+/// Keep a Changelog sections for each commit type, in the order they are
+/// listed. Types that don't change what users get, like `docs` or `ci`, are left out.
+const SECTIONS: [(&str, &[&str]); 3] = [("Added", &["feat"]), ("Changed", &["perf", "refactor", "revert"]), ("Fixed", &["fix"])];
+
+const UNRELEASED: &str = "## [Unreleased]";
+
+/// The bullets for the squashed `commits` (newest first) per section, oldest
+/// first. Without Conventional Commits, every commit counts as a change.
+pub fn sections(commits: &[Commit]) -> Vec<(&'static str, Vec<String>)> {
+  let subjects = commits.iter().rev().map(encoding::summary).collect::<Vec<_>>();
+  let conventional = subjects.iter().any(|subject| conventional::parse(subject).is_some());
+
+  let mut sections = SECTIONS.iter().map(|(name, _)| (*name, Vec::new())).collect::<Vec<_>>();
+  for subject in &subjects {
+    let bullet = match conventional::parse(subject) {
+      Some(header) => {
+        let Some(n) = SECTIONS.iter().position(|(_, kinds)| kinds.contains(&header.kind)) else {
+          continue;
+        };
+        let scope = header.scope.map(|scope| format!("**{}:** ", scope)).unwrap_or_default();
+        let breaking = if header.breaking { "**Breaking:** " } else { "" };
+        (n, format!("- {}{}{}", breaking, scope, capitalize(header.subject)))
+      },
+      // A stray commit among conventional ones is usually noise, like a merge fix
+      None if conventional => continue,
+      None => (1, format!("- {}", subject))
+    };
+    sections[bullet.0].1.push(bullet.1);
+  }
+
+  sections.retain(|(_, bullets)| !bullets.is_empty());
+  sections
+}
+
+/// Adds the bullets of `sections` to the `[Unreleased]` part of the changelog
+/// at `path`, creating the file, the part or a section as needed. Returns
+/// how many bullets were added.
+pub fn append(path: &Path, sections: &[(&str, Vec<String>)]) -> Result<usize> {
+  let content = match std::fs::read_to_string(path) {
+    Ok(content) => content,
+    Err(error) if error.kind() == std::io::ErrorKind::NotFound => "# Changelog\n".to_string(),
+    Err(error) => return Err(error).with_context(|| format!("Failed to read {}", path.display()))
+  };
+
+  std::fs::write(path, add(&content, sections)).with_context(|| format!("Failed to write {}", path.display()))?;
+  Ok(sections.iter().map(|(_, bullets)| bullets.len()).sum())
+}
+
+fn add(content: &str, sections: &[(&str, Vec<String>)]) -> String {
+  let mut lines = content.lines().map(str::to_string).collect::<Vec<_>>();
+  let is_release = |line: &String| line.starts_with("## ");
+
+  let start = match lines.iter().position(|line| line.trim() == UNRELEASED) {
+    Some(start) => start,
+    None => {
+      // Above the latest release, or below the title and intro
+      let mut at = lines.iter().position(is_release).unwrap_or(lines.len());
+      if at > 0 && !lines[at - 1].trim().is_empty() {
+        lines.insert(at, String::new());
+        at += 1;
+      }
+      lines.insert(at, UNRELEASED.to_string());
+      at
+    }
+  };
+
+  for (name, bullets) in sections {
+    let end = lines[start + 1..].iter().position(is_release).map_or(lines.len(), |n| start + 1 + n);
+    let heading = format!("### {}", name);
+    let at = match lines[start..end].iter().position(|line| line.trim() == heading) {
+      Some(n) => {
+        // After the section's last bullet
+        let section = start + n;
+        let next = lines[section + 1..end]
+          .iter()
+          .position(|line| line.starts_with("### "))
+          .map_or(end, |n| section + 1 + n);
+        lines[..next].iter().rposition(|line| !line.trim().is_empty()).map_or(next, |n| n + 1)
+      },
+      None => {
+        let last = lines[..end].iter().rposition(|line| !line.trim().is_empty()).map_or(end, |n| n + 1);
+        lines.splice(last..last, [String::new(), heading]);
+        last + 2
+      }
+    };
+    lines.splice(at..at, bullets.iter().cloned());
+  }
+
+  // Keep a blank line before the next release
+  let end = lines[start + 1..].iter().position(is_release).map(|n| start + 1 + n);
+  if let Some(end) = end.filter(|&end| !lines[end - 1].trim().is_empty()) {
+    lines.insert(end, String::new());
+  }
+
+  let mut content = lines.join("\n");
+  content.push('\n');
+  content
+}
+
+fn capitalize(text: &str) -> String {
+  let mut chars = text.chars();
+  match chars.next() {
+    Some(first) => first.to_uppercase().chain(chars).collect(),
+    None => String::new()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_sections() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(0)?;
+    for (file, subject) in
+      [("a", "feat(parser): add lists"), ("b", "docs: explain lists"), ("c", "fix!: drop tabs"), ("d", "Tidy up")]
+    {
+      commit_file(&repo, file, file, subject)?;
+    }
+    let commits = crate::squash::commits(&repo, 4)?;
+
+    assert_eq!(sections(&commits), [
+      ("Added", vec!["- **parser:** Add lists".to_string()]),
+      ("Fixed", vec!["- **Breaking:** Drop tabs".to_string()])
+    ]);
+    assert_eq!(sections(&commits[..1]), [("Changed", vec!["- Tidy up".to_string()])]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_add() {
+    let sections = [("Added", vec!["- Lists".to_string()]), ("Fixed", vec!["- Tabs".to_string()])];
+    assert_eq!(
+      add("# Changelog\n", &sections),
+      "# Changelog\n\n## [Unreleased]\n\n### Added\n- Lists\n\n### Fixed\n- Tabs\n"
+    );
+
+    let existing = "# Changelog\n\n## [Unreleased]\n\n### Added\n- Tables\n\n## [1.0.0] - 2024-05-03\n\n### Added\n- Parser\n";
+    assert_eq!(
+      add(existing, &sections),
+      "# Changelog\n\n## [Unreleased]\n\n### Added\n- Tables\n- Lists\n\n### Fixed\n- Tabs\n\n## [1.0.0] - 2024-05-03\n\n### Added\n- Parser\n"
+    );
+
+    let released = "# Changelog\n\n## [1.0.0] - 2024-05-03\n";
+    assert_eq!(
+      add(released, &sections[1..]),
+      "# Changelog\n\n## [Unreleased]\n\n### Fixed\n- Tabs\n\n## [1.0.0] - 2024-05-03\n"
+    );
+  }
+}
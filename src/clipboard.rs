@@ -0,0 +1,122 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use dialoguer::Completion;
+use serde::Deserialize;
+
+/// What to copy to the clipboard after squashing
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum CopyTarget {
+  /// The SHA of the squash commit
+  Sha,
+  /// The full message of the squash commit
+  Message
+}
+
+/// A clipboard command line, tried in order until one is installed
+type Tool<'a> = (&'a str, &'a [&'a str]);
+
+const COPY_TOOLS: &[Tool<'static>] = &[
+  ("pbcopy", &[]),
+  ("wl-copy", &[]),
+  ("xclip", &["-selection", "clipboard"]),
+  ("xsel", &["--clipboard", "--input"]),
+  ("clip.exe", &[])
+];
+
+const PASTE_TOOLS: &[Tool<'static>] = &[
+  ("pbpaste", &[]),
+  ("wl-paste", &["--no-newline"]),
+  ("xclip", &["-selection", "clipboard", "-out"]),
+  ("xsel", &["--clipboard", "--output"]),
+  ("powershell.exe", &["-NoProfile", "-Command", "Get-Clipboard"])
+];
+
+/// Puts `text` on the system clipboard
+pub fn copy(text: &str) -> Result<()> {
+  copy_with(COPY_TOOLS, text)
+}
+
+/// The text on the system clipboard
+pub fn paste() -> Result<String> {
+  paste_with(PASTE_TOOLS)
+}
+
+/// Pastes the first line of the clipboard when Tab is pressed on an empty
+/// message prompt
+pub struct Paste;
+
+impl Completion for Paste {
+  fn get(&self, input: &str) -> Option<String> {
+    if !input.is_empty() {
+      return None;
+    }
+
+    let text = paste().ok()?;
+    text.lines().map(str::trim).find(|line| !line.is_empty()).map(str::to_string)
+  }
+}
+
+fn copy_with(tools: &[Tool<'_>], text: &str) -> Result<()> {
+  run_first(tools, |command| {
+    let mut child = command.stdin(Stdio::piped()).stdout(Stdio::null()).stderr(Stdio::piped()).spawn()?;
+    child.stdin.take().expect("stdin is piped").write_all(text.as_bytes())?;
+    child.wait_with_output()
+  })
+  .map(|_| ())
+}
+
+fn paste_with(tools: &[Tool<'_>]) -> Result<String> {
+  let output = run_first(tools, |command| command.stdin(Stdio::null()).output())?;
+  String::from_utf8(output.stdout).context("Clipboard content is not valid UTF-8")
+}
+
+/// Runs the first tool that is installed and succeeds
+fn run_first<F>(tools: &[Tool<'_>], mut run: F) -> Result<std::process::Output>
+where F: FnMut(&mut Command) -> std::io::Result<std::process::Output> {
+  let mut failures = Vec::new();
+
+  for (program, args) in tools {
+    match run(Command::new(program).args(*args)) {
+      Ok(output) if output.status.success() => return Ok(output),
+      Ok(output) => failures.push(format!("{}: {}", program, String::from_utf8_lossy(&output.stderr).trim())),
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => {},
+      Err(error) => failures.push(format!("{}: {}", program, error))
+    }
+  }
+
+  match failures.is_empty() {
+    true => {
+      let names = tools.iter().map(|(program, _)| *program).collect::<Vec<_>>();
+      bail!("No clipboard tool found, install one of {}", names.join(", "))
+    },
+    false => bail!("Failed to access the clipboard:\n{}", failures.join("\n"))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_falls_back_to_the_next_tool() -> Result<()> {
+    let dir = tempdir::TempDir::new("clipboard")?;
+    let file = dir.path().join("clipboard");
+    let copy_script = format!("cat > {}", file.display());
+    let paste_script = format!("cat {}", file.display());
+
+    assert!(copy_with(&[("no-such-clipboard-tool", &[]), ("false", &[])], "text").is_err());
+
+    copy_with(&[("no-such-clipboard-tool", &[]), ("sh", &["-c", &copy_script])], "abc1234")?;
+    assert_eq!(paste_with(&[("sh", &["-c", &paste_script])])?, "abc1234");
+    Ok(())
+  }
+
+  #[test]
+  fn test_reports_missing_tools() {
+    let error = paste_with(&[("no-such-clipboard-tool", &[])]).unwrap_err();
+    assert!(error.to_string().starts_with("No clipboard tool found"));
+  }
+}
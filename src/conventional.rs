@@ -0,0 +1,173 @@
+use anyhow::{bail, Context, Result};
+use crate::prompt;
+use regex::Regex;
+
+/// Commit types from the Conventional Commits spec and the Angular convention
+pub const TYPES: [(&str, &str); 11] = [
+  ("feat", "A new feature"),
+  ("fix", "A bug fix"),
+  ("docs", "Documentation only changes"),
+  ("style", "Formatting, missing semicolons, etc; no code change"),
+  ("refactor", "A code change that neither fixes a bug nor adds a feature"),
+  ("perf", "A code change that improves performance"),
+  ("test", "Adding or correcting tests"),
+  ("build", "Changes to the build system or dependencies"),
+  ("ci", "Changes to CI configuration and scripts"),
+  ("chore", "Other changes that don't modify source or test files"),
+  ("revert", "Reverts a previous commit")
+];
+
+/// `type(scope)!: subject`, with the subject trimmed, starting lowercase and
+/// without a trailing period
+pub fn header(kind: &str, scope: Option<&str>, breaking: bool, subject: &str) -> String {
+  let subject = subject.trim().trim_end_matches('.');
+  let mut chars = subject.chars();
+  let subject = match chars.next() {
+    Some(first) => first.to_lowercase().chain(chars).collect::<String>(),
+    None => String::new()
+  };
+
+  let scope = scope.map(str::trim).filter(|scope| !scope.is_empty()).map(|scope| format!("({})", scope));
+  format!("{}{}{}: {}", kind, scope.unwrap_or_default(), if breaking { "!" } else { "" }, subject)
+}
+
+/// The parts of a Conventional Commits header
+#[derive(Debug, PartialEq)]
+pub struct Header<'a> {
+  pub kind:     &'a str,
+  pub scope:    Option<&'a str>,
+  pub breaking: bool,
+  pub subject:  &'a str
+}
+
+/// Splits a well-formed `type(scope)!: subject` header with a known type
+pub fn parse(header: &str) -> Option<Header<'_>> {
+  let pattern =
+    Regex::new(r"^(?P<type>[a-z]+)(?:\((?P<scope>[^)\s][^)]*)\))?(?P<breaking>!)?: (?P<subject>\S.*)$").expect("valid regex");
+  let captures = pattern.captures(header.trim_end())?;
+  let kind = captures.name("type")?.as_str();
+
+  TYPES.iter().any(|(known, _)| *known == kind).then(|| {
+    Header {
+      kind,
+      scope: captures.name("scope").map(|scope| scope.as_str()),
+      breaking: captures.name("breaking").is_some(),
+      subject: captures.name("subject").map(|subject| subject.as_str()).unwrap_or_default()
+    }
+  })
+}
+
+/// Checks `message` against the Conventional Commits format, failing with
+/// every problem found
+pub fn validate(message: &str, max_length: usize) -> Result<()> {
+  let mut problems = Vec::new();
+  let mut lines = message.lines();
+  let header = lines.next().unwrap_or_default();
+
+  let pattern =
+    Regex::new(r"^(?P<type>[^(!:\s]*)(?:\((?P<scope>[^)]*)\))?!?(?P<colon>: ?)?(?P<subject>.*)$").expect("valid regex");
+  let captures = pattern.captures(header).expect("pattern matches any line");
+  let kind = &captures["type"];
+
+  if kind.is_empty() {
+    problems.push("header does not start with a type".to_string());
+  } else if !TYPES.iter().any(|(known, _)| *known == kind) {
+    let known = TYPES.iter().map(|(known, _)| *known).collect::<Vec<_>>();
+    problems.push(format!("unknown type `{}`, expected one of {}", kind, known.join(", ")));
+  }
+
+  if captures.name("scope").is_some_and(|scope| scope.as_str().trim().is_empty()) {
+    problems.push("scope is empty, drop the parentheses".to_string());
+  }
+
+  match captures.name("colon").map(|colon| colon.as_str()) {
+    None => problems.push("missing `: ` between type and subject".to_string()),
+    Some(":") => problems.push("missing space after the colon".to_string()),
+    Some(_) if captures["subject"].trim().is_empty() => problems.push("subject is empty".to_string()),
+    Some(_) => {}
+  }
+
+  let length = header.chars().count();
+  if length > max_length {
+    problems.push(format!("header is {} characters, max is {}", length, max_length));
+  }
+
+  if lines.next().is_some_and(|line| !line.trim().is_empty()) {
+    problems.push("header and body must be separated by a blank line".to_string());
+  }
+
+  if problems.is_empty() {
+    return Ok(());
+  }
+
+  bail!("Message is not a Conventional Commit:\n  - {}", problems.join("\n  - "));
+}
+
+/// Walks through type, scope, subject and body and assembles the message
+pub fn wizard(max_length: usize) -> Result<String> {
+  let items = TYPES
+    .iter()
+    .map(|(kind, description)| format!("{:<9} {}", kind, description))
+    .collect::<Vec<_>>();
+  let kind = TYPES[prompt::select("Type", &items, 0).context("Failed to get type")?].0;
+
+  let scope = prompt::text("Scope (optional)", None, |_| Ok(())).context("Failed to get scope")?;
+
+  let breaking = prompt::confirm("Breaking change?", false).context("Failed to get breaking change")?;
+
+  let subject = prompt::text("Subject", None, |subject| {
+    let length = header(kind, Some(&scope), breaking, subject).chars().count();
+    match subject.trim() {
+      "" => Err("Subject can't be empty".to_string()),
+      _ if length > max_length => Err(format!("Header is {} characters, max is {}", length, max_length)),
+      _ => Ok(())
+    }
+  })
+  .context("Failed to get subject")?;
+
+  let body = prompt::text("Body (optional)", None, |_| Ok(())).context("Failed to get body")?;
+
+  let header = header(kind, Some(&scope), breaking, &subject);
+  Ok(match body.trim() {
+    "" => header,
+    body => format!("{}\n\n{}", header, body)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_header() {
+    assert_eq!(header("feat", Some("parser"), false, " Add lists. "), "feat(parser): add lists");
+    assert_eq!(header("fix", Some(""), true, "drop v1 API"), "fix!: drop v1 API");
+    assert_eq!(header("docs", None, false, "Ünicode"), "docs: ünicode");
+  }
+
+  #[test]
+  fn test_parse() {
+    let header = parse("feat(parser)!: add lists").unwrap();
+    assert_eq!(
+      (header.kind, header.scope, header.breaking, header.subject),
+      ("feat", Some("parser"), true, "add lists")
+    );
+    assert_eq!(parse("fix: crash").unwrap().scope, None);
+    assert_eq!(parse("feature: add lists"), None);
+    assert_eq!(parse("Add lists"), None);
+  }
+
+  #[test]
+  fn test_validate() {
+    assert!(validate("feat(parser)!: add lists\n\nBody", 72).is_ok());
+    assert!(validate("fix: crash", 72).is_ok());
+
+    let error = |message: &str| validate(message, 20).unwrap_err().to_string();
+    assert!(error("feature: add lists").contains("unknown type `feature`"));
+    assert!(error("feat add lists").contains("missing `: `"));
+    assert!(error("feat:add").contains("missing space"));
+    assert!(error("feat(): add").contains("scope is empty"));
+    assert!(error("feat: a subject that is far too long").contains("max is 20"));
+    assert!(error("fix: crash\nbody").contains("blank line"));
+  }
+}
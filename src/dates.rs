@@ -0,0 +1,293 @@
+use std::fmt;
+use clap::ValueEnum;
+use git2::{Commit, Time};
+use serde::Deserialize;
+
+/// How commit dates are shown in the commit list
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateFormat {
+  /// How long ago, e.g. `3 d`
+  #[default]
+  Relative,
+  /// Date and time in the commit's own timezone, e.g. `2024-05-03 14:22 +0200`
+  Absolute
+}
+
+/// Which of a commit's timestamps is shown. They differ for rebased or
+/// cherry-picked commits, where the committer date is when they were rewritten.
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DateSource {
+  /// When the change was originally written
+  Author,
+  /// When the commit was last created or rewritten
+  #[default]
+  Committer
+}
+
+/// How commit dates are shown in the commit list
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Dates {
+  pub format: DateFormat,
+  pub source: DateSource
+}
+
+impl Dates {
+  /// The timestamp of `commit` picked by `source`
+  pub fn time(&self, commit: &Commit) -> Time {
+    match self.source {
+      DateSource::Author => commit.author().when(),
+      DateSource::Committer => commit.time()
+    }
+  }
+}
+
+/// How far apart commits can be to count as one burst of work, given like
+/// `90s`, `30m`, `2h` or `1d`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Window(pub i64);
+
+impl Window {
+  pub fn parse(value: &str) -> Result<Self, String> {
+    let error = || format!("expected a duration like 30m, 2h or 90s, not `{}`", value);
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).ok_or_else(error)?;
+    let (amount, unit) = value.split_at(split);
+    let amount = amount.parse::<i64>().map_err(|_| error())?;
+    let unit = match unit {
+      "s" => 1,
+      "m" => 60,
+      "h" => 3_600,
+      "d" => 86_400,
+      _ => return Err(error())
+    };
+
+    match amount.checked_mul(unit) {
+      Some(seconds) if seconds > 0 => Ok(Self(seconds)),
+      _ => Err(error())
+    }
+  }
+
+  pub fn seconds(self) -> i64 {
+    self.0
+  }
+}
+
+/// In the largest unit that divides the window, e.g. `30m`
+impl fmt::Display for Window {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    match self.0 {
+      seconds if seconds % 86_400 == 0 => write!(f, "{}d", seconds / 86_400),
+      seconds if seconds % 3_600 == 0 => write!(f, "{}h", seconds / 3_600),
+      seconds if seconds % 60 == 0 => write!(f, "{}m", seconds / 60),
+      seconds => write!(f, "{}s", seconds)
+    }
+  }
+}
+
+/// Formats `time` in the timezone it was recorded in, like `git log --date=iso`
+/// without the seconds
+pub fn absolute(time: Time) -> String {
+  let offset = i64::from(time.offset_minutes());
+  let local = time.seconds() + offset * 60;
+  let (year, month, day) = civil_from_days(local.div_euclid(86_400));
+  let minutes = local.rem_euclid(86_400) / 60;
+  let sign = if offset < 0 { '-' } else { '+' };
+
+  format!(
+    "{:04}-{:02}-{:02} {:02}:{:02} {}{:02}{:02}",
+    year,
+    month,
+    day,
+    minutes / 60,
+    minutes % 60,
+    sign,
+    offset.abs() / 60,
+    offset.abs() % 60
+  )
+}
+
+/// Formats `time` for a `Date:` email header (RFC 2822), like
+/// `Fri, 3 May 2024 14:22:00 +0200`
+pub fn rfc2822(time: Time) -> String {
+  const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+  const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+  let offset = i64::from(time.offset_minutes());
+  let local = time.seconds() + offset * 60;
+  let days = local.div_euclid(86_400);
+  let (year, month, day) = civil_from_days(days);
+  let seconds = local.rem_euclid(86_400);
+  let sign = if offset < 0 { '-' } else { '+' };
+
+  format!(
+    "{}, {} {} {} {:02}:{:02}:{:02} {}{:02}{:02}",
+    WEEKDAYS[days.rem_euclid(7) as usize],
+    day,
+    MONTHS[month as usize - 1],
+    year,
+    seconds / 3600,
+    seconds % 3600 / 60,
+    seconds % 60,
+    sign,
+    offset.abs() / 60,
+    offset.abs() % 60
+  )
+}
+
+/// Parses a date the way git reads `GIT_AUTHOR_DATE`: its own
+/// `[@]<seconds> <offset>` format or ISO 8601 with an offset, e.g.
+/// `2024-05-03T14:22:00+02:00`
+pub fn parse(value: &str) -> Option<Time> {
+  let value = value.trim();
+  let raw = value.strip_prefix('@').unwrap_or(value);
+  let mut parts = raw.split_whitespace();
+  if let Some(Ok(seconds)) = parts.next().map(str::parse::<i64>) {
+    let offset = match parts.next() {
+      Some(offset) => parse_offset(offset)?,
+      None => 0
+    };
+    return parts.next().is_none().then(|| Time::new(seconds, offset));
+  }
+
+  let (date, rest) = value.split_at_checked(10)?;
+  let rest = rest.strip_prefix(['T', ' '])?;
+  let zone_at = rest.find(['Z', '+', '-'])?;
+  let (clock, zone) = rest.split_at(zone_at);
+
+  let date = date.split('-').map(str::parse::<i64>).collect::<Result<Vec<_>, _>>().ok()?;
+  let clock = clock.trim().split(':').map(str::parse::<i64>).collect::<Result<Vec<_>, _>>().ok()?;
+  let [year, month, day] = date[..] else {
+    return None;
+  };
+  let (hours, minutes, seconds) = match clock[..] {
+    [hours, minutes] => (hours, minutes, 0),
+    [hours, minutes, seconds] => (hours, minutes, seconds),
+    _ => return None
+  };
+  if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hours > 23 || minutes > 59 || seconds > 60 {
+    return None;
+  }
+
+  let offset = parse_offset(zone.trim())?;
+  let local = days_from_civil(year, month, day) * 86_400 + hours * 3600 + minutes * 60 + seconds;
+  Some(Time::new(local - i64::from(offset) * 60, offset))
+}
+
+/// `Z`, `+0200` or `+02:00` in minutes east of UTC
+fn parse_offset(offset: &str) -> Option<i32> {
+  if offset == "Z" {
+    return Some(0);
+  }
+
+  let (sign, digits) = match offset.split_at_checked(1)? {
+    ("+", digits) => (1, digits),
+    ("-", digits) => (-1, digits),
+    _ => return None
+  };
+  let digits = digits.replace(':', "");
+  if digits.len() != 4 || !digits.bytes().all(|b| b.is_ascii_digit()) {
+    return None;
+  }
+  let hours = digits[..2].parse::<i32>().ok()?;
+  let minutes = digits[2..].parse::<i32>().ok()?;
+  Some(sign * (hours * 60 + minutes))
+}
+
+/// The inverse of `civil_from_days`
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+  let year = if month <= 2 { year - 1 } else { year };
+  let era = year.div_euclid(400);
+  let year_of_era = year - era * 400;
+  let shifted_month = (month + 9) % 12;
+  let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+  let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+  era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since the Unix epoch to a proleptic Gregorian date, using
+/// Howard Hinnant's `civil_from_days`
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+  let z = days + 719_468;
+  let era = z.div_euclid(146_097);
+  let day_of_era = z.rem_euclid(146_097);
+  let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+  let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+  let shifted_month = (5 * day_of_year + 2) / 153;
+  let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+  let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+  let year = year_of_era + era * 400 + i64::from(month <= 2);
+  (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_absolute_respects_offset() {
+    // 2024-05-03 12:22 UTC
+    let seconds = 1_714_738_920;
+    assert_eq!(absolute(Time::new(seconds, 0)), "2024-05-03 12:22 +0000");
+    assert_eq!(absolute(Time::new(seconds, 120)), "2024-05-03 14:22 +0200");
+    assert_eq!(absolute(Time::new(seconds, -13 * 60 - 30)), "2024-05-02 22:52 -1330");
+  }
+
+  #[test]
+  fn test_rfc2822() {
+    assert_eq!(rfc2822(Time::new(1_714_738_920, 120)), "Fri, 3 May 2024 14:22:00 +0200");
+    assert_eq!(rfc2822(Time::new(0, -60)), "Wed, 31 Dec 1969 23:00:00 -0100");
+  }
+
+  #[test]
+  fn test_picks_author_or_committer_time() -> anyhow::Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(1)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let author = git2::Signature::new("A", "a@b", &Time::new(1_000_000, 60))?;
+    let oid = head.amend(None, Some(&author), None, None, None, None)?;
+    let commit = repo.find_commit(oid)?;
+
+    let dates = |source| {
+      Dates {
+        format: DateFormat::Relative,
+        source
+      }
+    };
+    assert_eq!(dates(DateSource::Author).time(&commit).seconds(), 1_000_000);
+    assert_eq!(dates(DateSource::Committer).time(&commit), commit.time());
+    Ok(())
+  }
+
+  #[test]
+  fn test_window() {
+    assert_eq!(Window::parse("30m"), Ok(Window(1_800)));
+    assert_eq!(Window::parse("90s").map(|window| window.to_string()), Ok("90s".to_string()));
+    assert_eq!(Window::parse("120m").map(|window| window.to_string()), Ok("2h".to_string()));
+    assert!(Window::parse("30").is_err());
+    assert!(Window::parse("0h").is_err());
+    assert!(Window::parse("m").is_err());
+  }
+
+  #[test]
+  fn test_civil_from_days() {
+    assert_eq!(civil_from_days(0), (1970, 1, 1));
+    assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    assert_eq!(civil_from_days(11_016), (2000, 2, 29));
+    assert_eq!(days_from_civil(2000, 2, 29), 11_016);
+    assert_eq!(days_from_civil(1969, 12, 31), -1);
+  }
+
+  #[test]
+  fn test_parse() {
+    let time = |seconds, offset| Some(Time::new(seconds, offset));
+    assert_eq!(parse("@1714738920 +0200"), time(1_714_738_920, 120));
+    assert_eq!(parse("1714738920 -0130"), time(1_714_738_920, -90));
+    assert_eq!(parse("1714738920"), time(1_714_738_920, 0));
+    assert_eq!(parse("2024-05-03T14:22:00+02:00"), time(1_714_738_920, 120));
+    assert_eq!(parse("2024-05-03 12:22 Z"), time(1_714_738_920, 0));
+    assert_eq!(parse("2024-05-03 14:22:00 +0200"), time(1_714_738_920, 120));
+    assert_eq!(parse("2024-05-03 14:22:00"), None, "no offset");
+    assert_eq!(parse("yesterday"), None);
+  }
+}
@@ -0,0 +1,107 @@
+use git2::Commit;
+use crate::conventional;
+use crate::encoding;
+
+/// Section headings for each commit type, in the order they are listed
+const SECTIONS: [(&str, &str); 11] = [
+  ("feat", "Features"),
+  ("fix", "Bug fixes"),
+  ("perf", "Performance"),
+  ("refactor", "Refactoring"),
+  ("docs", "Documentation"),
+  ("test", "Tests"),
+  ("build", "Build"),
+  ("ci", "CI"),
+  ("style", "Style"),
+  ("chore", "Chores"),
+  ("revert", "Reverts")
+];
+
+/// Markdown body for the pull request of the squashed branch, with one
+/// bullet per commit, oldest first. When most commits follow Conventional
+/// Commits, the bullets are grouped under a heading per type.
+pub fn render(commits: &[Commit]) -> String {
+  let subjects = commits
+    .iter()
+    .rev()
+    .map(|commit| (short_id(commit), encoding::summary(commit)))
+    .collect::<Vec<_>>();
+  let conventional = subjects.iter().filter(|(_, subject)| conventional::parse(subject).is_some()).count();
+
+  if conventional * 2 < subjects.len() {
+    let bullets = subjects.iter().map(|(id, subject)| format!("- {} ({})", subject, id)).collect::<Vec<_>>();
+    return format!("## Changes\n\n{}\n", bullets.join("\n"));
+  }
+
+  let mut sections = Vec::new();
+  for (kind, title) in SECTIONS {
+    let bullets = subjects
+      .iter()
+      .filter_map(|(id, subject)| {
+        let header = conventional::parse(subject).filter(|header| header.kind == kind)?;
+        let scope = header.scope.map(|scope| format!("**{}:** ", scope)).unwrap_or_default();
+        let breaking = if header.breaking { "⚠️ " } else { "" };
+        Some(format!("- {}{}{} ({})", breaking, scope, header.subject, id))
+      })
+      .collect::<Vec<_>>();
+
+    if !bullets.is_empty() {
+      sections.push(format!("## {}\n\n{}", title, bullets.join("\n")));
+    }
+  }
+
+  let other = subjects
+    .iter()
+    .filter(|(_, subject)| conventional::parse(subject).is_none())
+    .map(|(id, subject)| format!("- {} ({})", subject, id))
+    .collect::<Vec<_>>();
+  if !other.is_empty() {
+    sections.push(format!("## Other\n\n{}", other.join("\n")));
+  }
+
+  format!("{}\n", sections.join("\n\n"))
+}
+
+fn short_id(commit: &Commit) -> String {
+  commit.id().to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+  use anyhow::Result;
+  use git2::Repository;
+
+  fn last(repo: &Repository, amount: usize) -> Result<Vec<Commit<'_>>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    Ok(revwalk.take(amount).map(|oid| repo.find_commit(oid.unwrap()).unwrap()).collect())
+  }
+
+  #[test]
+  fn test_plain_list() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let description = render(&last(&repo, 2)?);
+
+    assert!(description.starts_with("## Changes\n\n- Commit 0 ("));
+    assert!(description.contains(")\n- Commit 1 ("));
+    Ok(())
+  }
+
+  #[test]
+  fn test_groups_conventional_commits() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "a.txt", "a", "fix(parser): handle tabs")?;
+    commit_file(&repo, "b.txt", "b", "feat!: drop v1")?;
+    commit_file(&repo, "c.txt", "c", "feat: add lists")?;
+    let description = render(&last(&repo, 4)?);
+
+    let features = description.find("## Features\n\n- ⚠️ drop v1").unwrap();
+    let fixes = description.find("## Bug fixes\n\n- **parser:** handle tabs").unwrap();
+    let other = description.find("## Other\n\n- Commit 0").unwrap();
+    assert!(features < fixes && fixes < other);
+    assert!(description.contains("\n- add lists ("));
+    Ok(())
+  }
+}
@@ -0,0 +1,155 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{Context, Result};
+use git2::{Commit, DiffFormat, DiffOptions, Repository};
+
+/// The patch between `base` and `head`, i.e. exactly what squashing
+/// everything in between into a single commit would contain. Without a
+/// base the range starts at the root, so everything shows up as added.
+pub fn patch(repo: &Repository, base: Option<&Commit>, head: &Commit) -> Result<String> {
+  let old_tree = base.map(Commit::tree).transpose().context("Failed to get base tree")?;
+  let new_tree = head.tree().context("Failed to get HEAD tree")?;
+  let diff = repo
+    .diff_tree_to_tree(old_tree.as_ref(), Some(&new_tree), None)
+    .context("Failed to diff range")?;
+
+  let mut patch = String::new();
+  diff
+    .print(DiffFormat::Patch, |_delta, _hunk, line| {
+      if matches!(line.origin(), '+' | '-' | ' ') {
+        patch.push(line.origin());
+      }
+      patch.push_str(&String::from_utf8_lossy(line.content()));
+      true
+    })
+    .context("Failed to print diff")?;
+
+  Ok(patch)
+}
+
+/// `+NN/-MM (K files)` for the changes `commit` makes to its first parent
+pub fn stat(repo: &Repository, commit: &Commit) -> Result<String> {
+  let parent_tree = match commit.parent(0) {
+    Ok(parent) => Some(parent.tree().context("Failed to get parent tree")?),
+    Err(_) => None
+  };
+  let tree = commit.tree().context("Failed to get commit tree")?;
+  let stats = repo
+    .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)
+    .context("Failed to diff commit")?
+    .stats()
+    .context("Failed to get diff stats")?;
+
+  let files = stats.files_changed();
+  Ok(format!(
+    "+{}/-{} ({} {})",
+    stats.insertions(),
+    stats.deletions(),
+    files,
+    if files == 1 { "file" } else { "files" }
+  ))
+}
+
+/// Whether `commit` changes anything matching one of the `paths` pathspecs
+pub fn touches(repo: &Repository, commit: &Commit, paths: &[String]) -> Result<bool> {
+  let parent_tree = match commit.parent(0) {
+    Ok(parent) => Some(parent.tree().context("Failed to get parent tree")?),
+    Err(_) => None
+  };
+  let tree = commit.tree().context("Failed to get commit tree")?;
+
+  let mut options = DiffOptions::new();
+  for path in paths {
+    options.pathspec(path);
+  }
+  let diff = repo
+    .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut options))
+    .context("Failed to diff commit")?;
+
+  Ok(diff.deltas().len() > 0)
+}
+
+/// Colors added and removed lines like `git diff --color`, unless colors
+/// are turned off for stdout
+pub fn colorize(patch: &str) -> String {
+  patch
+    .lines()
+    .map(|line| {
+      match line.chars().next() {
+        _ if line.starts_with("+++") || line.starts_with("---") => console::style(line).bold().to_string(),
+        Some('+') => console::style(line).green().to_string(),
+        Some('-') => console::style(line).red().to_string(),
+        Some('@') => console::style(line).cyan().to_string(),
+        _ if line.starts_with("diff ") => console::style(line).bold().to_string(),
+        _ => line.to_string()
+      }
+    })
+    .collect::<Vec<String>>()
+    .join("\n")
+}
+
+/// Shows `text` through the same pager git would use, falling back to
+/// printing it to stderr when stdout isn't a terminal or no pager can be started
+pub fn show(repo: &Repository, text: &str) -> Result<()> {
+  let pager = pager_command(repo);
+  if !console::Term::stdout().is_term() || pager.is_empty() || pager == "cat" {
+    eprintln!("{}", text);
+    return Ok(());
+  }
+
+  let child = Command::new("sh")
+    .arg("-c")
+    .arg(&pager)
+    .env("LESS", std::env::var("LESS").unwrap_or_else(|_| "FRX".to_string()))
+    .stdin(Stdio::piped())
+    .spawn();
+
+  let Ok(mut child) = child else {
+    eprintln!("{}", text);
+    return Ok(());
+  };
+
+  // Quitting the pager early closes its stdin, which isn't an error
+  let _ = child.stdin.take().expect("stdin is piped").write_all(text.as_bytes());
+  child.wait().with_context(|| format!("Failed to run pager `{}`", pager))?;
+  Ok(())
+}
+
+/// `GIT_PAGER`, `core.pager`, `PAGER` and finally `less`, like git itself
+fn pager_command(repo: &Repository) -> String {
+  std::env::var("GIT_PAGER")
+    .ok()
+    .or_else(|| repo.config().ok().and_then(|config| config.get_string("core.pager").ok()))
+    .or_else(|| std::env::var("PAGER").ok())
+    .unwrap_or_else(|| "less".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_patch_spans_range() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let base = head.parent(0)?.parent(0)?;
+
+    let patch = patch(&repo, Some(&base), &head)?;
+    assert!(patch.contains("+++ b/1.txt"));
+    assert!(patch.contains("+++ b/2.txt"));
+    assert!(!patch.contains("0.txt"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_stat() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "0.txt", "changed\nlines\n", "Change")?;
+    let head = repo.head()?.peel_to_commit()?;
+
+    assert_eq!(stat(&repo, &head)?, "+2/-1 (1 file)");
+    assert_eq!(stat(&repo, &head.parent(0)?)?, "+1/-0 (1 file)");
+    Ok(())
+  }
+}
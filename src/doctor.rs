@@ -0,0 +1,226 @@
+use std::path::Path;
+use anyhow::{Context, Result};
+use git2::{Repository, StatusOptions};
+use crate::config::Config;
+use crate::hooks::{self, Hook};
+use crate::i18n::t;
+use crate::policy::Subject;
+use crate::{rebase, sign, upstream};
+
+/// How a check came out
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Status {
+  Pass,
+  /// A squash works, but maybe not as expected
+  Warn,
+  /// A squash would be refused or fail
+  Fail
+}
+
+/// One thing looked at before a squash
+#[derive(Debug)]
+pub struct Check {
+  pub name:   String,
+  pub status: Status,
+  pub detail: String
+}
+
+impl Check {
+  fn new(name: String, status: Status, detail: String) -> Self {
+    Self {
+      name,
+      status,
+      detail
+    }
+  }
+}
+
+/// Everything that decides how a squash in `repo` goes, as far as it can
+/// be told without squashing
+pub fn run(repo: &Repository, config: &Config) -> Result<Vec<Check>> {
+  Ok(vec![
+    worktree(repo)?,
+    operation(repo),
+    upstream(repo)?,
+    signing(repo, config),
+    hooks(repo, config),
+    branch(repo, config)?,
+  ])
+}
+
+fn worktree(repo: &Repository) -> Result<Check> {
+  let name = t!("doctor-worktree");
+  if repo.is_bare() {
+    return Ok(Check::new(name, Status::Fail, t!("bare-repository", path = repo.path().display())));
+  }
+
+  let mut options = StatusOptions::new();
+  options.include_untracked(false).include_ignored(false);
+  let changed = repo.statuses(Some(&mut options)).context("Failed to get status")?.len();
+  Ok(match changed {
+    0 => Check::new(name, Status::Pass, t!("doctor-clean")),
+    count => Check::new(name, Status::Warn, t!("doctor-dirty", count = count))
+  })
+}
+
+fn operation(repo: &Repository) -> Check {
+  let name = t!("doctor-operation");
+  match rebase::ensure_idle(repo) {
+    Ok(()) => Check::new(name, Status::Pass, t!("doctor-idle")),
+    Err(error) => Check::new(name, Status::Fail, error.to_string())
+  }
+}
+
+fn upstream(repo: &Repository) -> Result<Check> {
+  let name = t!("doctor-upstream");
+  let Some(tracking) = upstream::tracking(repo)? else {
+    return Ok(Check::new(name, Status::Warn, t!("doctor-no-upstream")));
+  };
+
+  let upstream = &tracking.upstream;
+  Ok(match (tracking.ahead, tracking.behind) {
+    (0, _) => Check::new(name, Status::Warn, t!("doctor-not-ahead", upstream = upstream)),
+    (ahead, 0) => Check::new(name, Status::Pass, t!("doctor-ahead", count = ahead, upstream = upstream)),
+    (ahead, behind) => Check::new(name, Status::Warn, t!("doctor-diverged", ahead = ahead, behind = behind, upstream = upstream))
+  })
+}
+
+/// A missing signer only fails when squashes are signed by default
+fn signing(repo: &Repository, config: &Config) -> Check {
+  let name = t!("doctor-signing");
+  let wanted = config.sign();
+  if !wanted && !sign::key_configured(repo) {
+    return Check::new(name, Status::Pass, t!("doctor-not-signing"));
+  }
+  let missing = if wanted { Status::Fail } else { Status::Warn };
+
+  let program = match sign::program_for(repo) {
+    Ok(program) => program,
+    Err(error) => return Check::new(name, missing, error.to_string())
+  };
+  let ssh = repo
+    .config()
+    .and_then(|config| config.get_string("gpg.format"))
+    .is_ok_and(|format| format == "ssh");
+  if ssh && !sign::key_configured(repo) {
+    return Check::new(name, missing, t!("doctor-no-ssh-key"));
+  }
+
+  match (installed(&program), wanted) {
+    (false, _) => Check::new(name, missing, t!("doctor-no-signer", program = program)),
+    (true, true) => Check::new(name, Status::Pass, t!("doctor-signing-with", program = program)),
+    (true, false) => Check::new(name, Status::Pass, t!("doctor-can-sign", program = program))
+  }
+}
+
+/// Hooks found in the hooks directory but not executable are skipped by git
+/// and git-squash alike, which is easy to miss
+fn hooks(repo: &Repository, config: &Config) -> Check {
+  let name = t!("doctor-hooks");
+  let mut found = Vec::new();
+  let mut skipped = Vec::new();
+  for hook in [Hook::PreSquash, Hook::PostSquash] {
+    let path = hooks::directory(repo).join(hook.name());
+    match hook.command(&config.hooks) {
+      Some(command) => found.push(format!("{} (`{}`)", hook.name(), command)),
+      None if hooks::is_executable(&path) => found.push(format!("{} ({})", hook.name(), path.display())),
+      None if path.exists() => skipped.push(path.display().to_string()),
+      None => {}
+    }
+  }
+
+  if !skipped.is_empty() {
+    return Check::new(name, Status::Warn, t!("doctor-hook-not-executable", paths = skipped.join(", ")));
+  }
+  match found.is_empty() {
+    true => Check::new(name, Status::Pass, t!("doctor-no-hooks")),
+    false => Check::new(name, Status::Pass, found.join(", "))
+  }
+}
+
+fn branch(repo: &Repository, config: &Config) -> Result<Check> {
+  let name = t!("doctor-branch");
+  let Some(head) = repo.head().ok().filter(|head| head.is_branch()) else {
+    return Ok(Check::new(name, Status::Warn, t!("doctor-detached")));
+  };
+
+  let branch = head.shorthand().unwrap_or_default();
+  if config.is_protected(branch) {
+    return Ok(Check::new(name, Status::Fail, t!("protected-branch", branch = branch)));
+  }
+
+  // Only the branch rules apply before anything is picked
+  let subject = Subject {
+    branch: Some(branch), count: 0, message: None
+  };
+  let violations = config.policy.evaluate(&subject)?;
+  Ok(match violations.is_empty() {
+    true => Check::new(name, Status::Pass, t!("doctor-branch-ok", branch = branch)),
+    false => {
+      let details = violations.iter().map(ToString::to_string).collect::<Vec<_>>();
+      Check::new(name, Status::Fail, details.join(", "))
+    }
+  })
+}
+
+/// Whether `program` can be run, as a path or from `PATH`
+fn installed(program: &str) -> bool {
+  let path = Path::new(program);
+  if path.components().count() > 1 {
+    return path.is_file();
+  }
+
+  std::env::var_os("PATH").is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_run() -> Result<()> {
+    use Status::*;
+    let (dir, repo) = repo_with_commits(2)?;
+    let config = Config::default();
+    let checks = run(&repo, &config)?;
+    let mut statuses = checks.iter().map(|check| check.status).collect::<Vec<_>>();
+    // No upstream, which only means the amount has to be given. Signing
+    // depends on the git config of whoever runs the tests.
+    statuses.remove(3);
+    assert_eq!(statuses, [Pass, Pass, Warn, Pass, Pass]);
+
+    std::fs::write(dir.path().join("0.txt"), "changed")?;
+    let branch = repo.head()?.shorthand().unwrap().to_string();
+    let config = Config {
+      protected_branches: Some(vec![branch]),
+      ..Config::default()
+    };
+    let checks = run(&repo, &config)?;
+    assert_eq!((checks[0].status, checks[5].status), (Warn, Fail));
+    Ok(())
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_hooks() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let dir = hooks::directory(&repo);
+    std::fs::create_dir_all(&dir)?;
+    std::fs::write(dir.join("post-squash"), "#!/bin/sh\n")?;
+    assert_eq!(hooks(&repo, &Config::default()).status, Status::Warn);
+
+    let config = Config {
+      hooks: hooks::Hooks {
+        pre_squash: Some("cargo test".to_string()), post_squash: Some("true".to_string())
+      },
+      ..Config::default()
+    };
+    let check = hooks(&repo, &config);
+    assert_eq!(
+      (check.status, check.detail.as_str()),
+      (Status::Pass, "pre-squash (`cargo test`), post-squash (`true`)")
+    );
+    Ok(())
+  }
+}
@@ -0,0 +1,163 @@
+use anyhow::{bail, Context, Result};
+use dialoguer::Editor;
+use git2::{Oid, Repository};
+use crate::SquashError;
+use crate::encoding;
+use crate::i18n::t;
+
+/// The file configured as `commit.template`, if any
+pub fn template(repo: &Repository) -> Result<Option<String>> {
+  let config = repo.config().context("Failed to get config")?;
+  let Ok(path) = config.get_path("commit.template") else {
+    return Ok(None);
+  };
+
+  // Relative paths are relative to the working directory, like git does
+  let path = repo.workdir().map(|workdir| workdir.join(&path)).unwrap_or(path);
+  let template = std::fs::read_to_string(&path).with_context(|| format!("Failed to read commit template {}", path.display()))?;
+  Ok(Some(template))
+}
+
+const SCISSORS: &str = "# ------------------------ >8 ------------------------";
+
+/// Whether `commit.verbose` asks for the diff in the editor, as a boolean or
+/// a verbosity level
+pub fn verbose(repo: &Repository) -> bool {
+  let Ok(config) = repo.config() else {
+    return false;
+  };
+  config
+    .get_bool("commit.verbose")
+    .or_else(|_| config.get_i32("commit.verbose").map(|level| level > 0))
+    .unwrap_or(false)
+}
+
+/// `patch` below a scissors line, which `strip_comments` cuts off together
+/// with everything after it, like `git commit -v`
+pub fn scissors(patch: &str) -> String {
+  format!(
+    "{}\n# Do not modify or remove the line above.\n# Everything below it will be ignored.\n{}",
+    SCISSORS, patch
+  )
+}
+
+/// Drops `#` comment lines, everything below a scissors line and
+/// surrounding blank lines, like git's default `commit.cleanup=strip`
+pub fn strip_comments(text: &str) -> String {
+  text
+    .lines()
+    .take_while(|line| *line != SCISSORS)
+    .filter(|line| !line.starts_with('#'))
+    .map(str::trim_end)
+    .collect::<Vec<_>>()
+    .join("\n")
+    .trim()
+    .to_string()
+}
+
+/// Commented lines listing what the squash replaces, appended to the editor
+/// buffer for context and stripped again on save
+pub fn summary(repo: &Repository, commits: &[Oid]) -> Result<String> {
+  let mut summary = String::from(
+    "# Please enter the message for the squash commit. Lines starting\n# with '#' will be ignored, and an empty message aborts the squash.\n#\n# This squash replaces the following commits:\n"
+  );
+  for oid in commits {
+    let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+    summary.push_str(&format!("#   {} {}\n", &oid.to_string()[..7], encoding::summary(&commit)));
+  }
+  Ok(summary)
+}
+
+/// The editor git would use, which may come with arguments like `code --wait`
+pub fn command(repo: &Repository) -> String {
+  let core_editor = repo.config().ok().and_then(|config| config.get_string("core.editor").ok());
+  pick([std::env::var("GIT_EDITOR").ok(), core_editor, std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok()])
+}
+
+/// The first of `GIT_EDITOR`, `core.editor`, `VISUAL` and `EDITOR` that is
+/// set, falling back to `vi` like git
+fn pick(candidates: [Option<String>; 4]) -> String {
+  candidates
+    .into_iter()
+    .flatten()
+    .find(|editor| !editor.trim().is_empty())
+    .unwrap_or_else(|| "vi".to_string())
+}
+
+/// Opens `editor` on `initial` followed by the `comments` until it yields
+/// a message whose subject fits `max_length`. Fails when the message is empty.
+pub fn compose(editor: &str, initial: &str, comments: &str, max_length: usize) -> Result<String> {
+  let mut text = format!("{}\n\n{}", initial.trim_end(), comments);
+
+  loop {
+    let edited = Editor::new()
+      .executable(editor)
+      .require_save(false)
+      .edit(&text)
+      .with_context(|| format!("Failed to run editor `{}`", editor))?
+      .unwrap_or_default();
+    let message = strip_comments(&edited);
+
+    if message.is_empty() {
+      bail!(SquashError::Aborted(t!("empty-message")));
+    }
+
+    let subject = message.lines().next().unwrap_or_default();
+    if subject.chars().count() <= max_length {
+      return Ok(message);
+    }
+
+    eprintln!("{}", t!("message-too-long", length = subject.chars().count(), max = max_length));
+    text = edited;
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_pick_editor_like_git() {
+    let set = |editor: &str| Some(editor.to_string());
+    assert_eq!(pick([None, set("code --wait"), set("nano"), set("emacs")]), "code --wait");
+    assert_eq!(pick([set("ed"), set("code --wait"), None, None]), "ed");
+    assert_eq!(pick([set(""), None, None, set("emacs")]), "emacs");
+    assert_eq!(pick([None, None, None, None]), "vi");
+  }
+
+  #[test]
+  fn test_strip_comments() {
+    let text = "\n# Please enter a message\nSubject  \n\nBody\n# trailing comment\n";
+    assert_eq!(strip_comments(text), "Subject\n\nBody");
+  }
+
+  #[test]
+  fn test_scissors_cut_the_diff() {
+    let text = format!("Subject\n\n{}", scissors("diff --git a/x b/x\n+added line\n"));
+    assert_eq!(strip_comments(&text), "Subject");
+  }
+
+  #[test]
+  fn test_summary_is_stripped() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let summary = summary(&repo, &[head.id(), head.parent_id(0)?])?;
+
+    assert!(summary.contains(&format!("#   {} Commit 1\n", &head.id().to_string()[..7])));
+    assert!(summary.contains("Commit 0"));
+    assert_eq!(strip_comments(&format!("Subject\n\n{}", summary)), "Subject");
+    Ok(())
+  }
+
+  #[test]
+  fn test_reads_commit_template() -> Result<()> {
+    let (dir, repo) = repo_with_commits(1)?;
+    assert_eq!(template(&repo)?, None);
+
+    std::fs::write(dir.path().join("template.txt"), "Subject\n\n# Why?\n")?;
+    repo.config()?.set_str("commit.template", "template.txt")?;
+    assert_eq!(template(&repo)?.as_deref(), Some("Subject\n\n# Why?\n"));
+    Ok(())
+  }
+}
@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use git2::{Commit, DiffFormat, DiffOptions, DiffStatsFormat, Repository};
+use crate::{dates, encoding};
+
+/// The date git puts on the `From ` line that starts each message of an
+/// mbox it writes, so they can be told apart from a real mailbox
+const MBOX_DATE: &str = "Mon Sep 17 00:00:00 2001";
+
+/// Width of the diffstat, as in `git format-patch`
+const STAT_WIDTH: usize = 72;
+
+/// Longest encoded word allowed in a header (RFC 2047)
+const ENCODED_WORD: usize = 75;
+
+/// `commit` as a patch email in mbox format, like `git format-patch --stdout`
+/// writes, listing the `squashed` commits (newest first) it replaced below
+/// the `---` line, where `git am` leaves it out of the commit message
+pub fn format(repo: &Repository, commit: &Commit, squashed: &[Commit]) -> Result<String> {
+  let author = commit.author();
+  let name = String::from_utf8_lossy(author.name_bytes());
+  let address = String::from_utf8_lossy(author.email_bytes());
+  let message = encoding::message(commit);
+  let (subject, body) = split_message(&message);
+
+  let mut summary = format!("Squashed from {} commits:\n", squashed.len());
+  for original in squashed.iter().rev() {
+    summary.push_str(&format!("  {} {}\n", &original.id().to_string()[..7], encoding::summary(original)));
+  }
+
+  let (stat, patch) = diff(repo, commit)?;
+  let mut text = String::new();
+  if !body.is_empty() {
+    text.push_str(&format!("{}\n\n", body));
+  }
+  text.push_str(&format!("---\n{}\n{}\n{}-- \ngit-squash {}\n\n", summary, stat, patch, env!("CARGO_PKG_VERSION")));
+
+  let mut email = format!("From {} {}\n", commit.id(), MBOX_DATE);
+  email.push_str(&format!("From: {} <{}>\n", phrase(&name), address));
+  email.push_str(&format!("Date: {}\n", dates::rfc2822(author.when())));
+  email.push_str(&format!("Subject: [PATCH] {}\n", header(&subject)));
+  if !text.is_ascii() {
+    email.push_str("MIME-Version: 1.0\nContent-Type: text/plain; charset=UTF-8\nContent-Transfer-Encoding: 8bit\n");
+  }
+  email.push('\n');
+  email.push_str(&text);
+  Ok(email)
+}
+
+/// The subject on one line and the rest of `message`
+fn split_message(message: &str) -> (String, &str) {
+  let message = message.trim();
+  let (subject, body) = message.split_once("\n\n").unwrap_or((message, ""));
+  (subject.split_whitespace().collect::<Vec<_>>().join(" "), body.trim())
+}
+
+/// The diffstat and the patch of `commit` against its first parent, with
+/// binary files included so the patch applies
+fn diff(repo: &Repository, commit: &Commit) -> Result<(String, String)> {
+  let parent_tree = match commit.parent(0) {
+    Ok(parent) => Some(parent.tree().context("Failed to get parent tree")?),
+    Err(_) => None
+  };
+  let tree = commit.tree().context("Failed to get commit tree")?;
+  let diff = repo
+    .diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(DiffOptions::new().show_binary(true)))
+    .context("Failed to diff commit")?;
+
+  let stat = diff
+    .stats()
+    .context("Failed to get diff stats")?
+    .to_buf(DiffStatsFormat::FULL | DiffStatsFormat::INCLUDE_SUMMARY, STAT_WIDTH)
+    .context("Failed to format diff stats")?;
+
+  let mut patch = String::new();
+  diff
+    .print(DiffFormat::Patch, |_delta, _hunk, line| {
+      if matches!(line.origin(), '+' | '-' | ' ') {
+        patch.push(line.origin());
+      }
+      patch.push_str(&String::from_utf8_lossy(line.content()));
+      true
+    })
+    .context("Failed to print diff")?;
+
+  Ok((String::from_utf8_lossy(&stat).into_owned(), patch))
+}
+
+/// A display name for an address header, quoted when it has characters
+/// with a meaning there and encoded when it isn't ASCII
+fn phrase(name: &str) -> String {
+  if !name.is_ascii() {
+    return header(name);
+  }
+  if name.chars().any(|c| "()<>[]:;@\\,.\"".contains(c)) {
+    return format!("\"{}\"", name.replace('\\', "\\\\").replace('"', "\\\""));
+  }
+  name.to_string()
+}
+
+/// `text` as is when it's ASCII, otherwise as RFC 2047 encoded words, one
+/// per folded line
+fn header(text: &str) -> String {
+  if text.is_ascii() {
+    return text.to_string();
+  }
+
+  let mut words = Vec::new();
+  let mut word = String::new();
+  for c in text.chars() {
+    let encoded = match c {
+      ' ' => "_".to_string(),
+      c if c.is_ascii_alphanumeric() || "!*+-/".contains(c) => c.to_string(),
+      c => c.to_string().bytes().map(|byte| format!("={:02X}", byte)).collect()
+    };
+    // Characters are never split across words
+    if "=?UTF-8?q?".len() + word.len() + encoded.len() + "?=".len() > ENCODED_WORD {
+      words.push(std::mem::take(&mut word));
+    }
+    word.push_str(&encoded);
+  }
+  words.push(word);
+
+  words.iter().map(|word| format!("=?UTF-8?q?{}?=", word)).collect::<Vec<_>>().join("\n ")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_header() {
+    assert_eq!(header("[PATCH] Add lists"), "[PATCH] Add lists");
+    assert_eq!(header("Björn"), "=?UTF-8?q?Bj=C3=B6rn?=");
+    let long = header(&"ö".repeat(20));
+    assert!(long.lines().all(|line| line.trim().len() <= ENCODED_WORD));
+    assert_eq!(long.lines().count(), 2);
+    assert_eq!(phrase("Doe, Jane"), "\"Doe, Jane\"");
+  }
+
+  #[test]
+  fn test_format() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "a.txt", "a\n", "Add a")?;
+    let oid = commit_file(&repo, "a.txt", "a\nb\n", "Add b\n\nWith a body.")?;
+    let commit = repo.find_commit(oid)?;
+    let squashed = crate::squash::commits(&repo, 2)?;
+
+    let email = format(&repo, &commit, &squashed)?;
+    let oldest = &squashed[1].id().to_string()[..7];
+    assert!(email.starts_with(&format!("From {} {}\nFrom: ", oid, MBOX_DATE)));
+    assert!(email.contains("\nSubject: [PATCH] Add b\n\nWith a body.\n\n---\nSquashed from 2 commits:\n"));
+    assert!(email.contains(&format!("  {} Add a\n", oldest)));
+    assert!(email.contains(" 1 file changed, 1 insertion(+)\n"));
+    assert!(email.contains("diff --git a/a.txt b/a.txt\n"));
+    assert!(email.contains("\n+b\n-- \ngit-squash "));
+    assert!(!email.contains("MIME-Version"));
+    Ok(())
+  }
+}
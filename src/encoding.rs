@@ -0,0 +1,109 @@
+use git2::Commit;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// `commit`'s message as UTF-8. libgit2 hands out the raw bytes, so messages
+/// written with another `i18n.commitEncoding`, like ISO-2022-JP, are decoded
+/// from the encoding their header declares, through `iconv` like git does.
+pub fn message(commit: &Commit) -> String {
+  decode(commit.message_bytes(), commit.message_encoding())
+}
+
+/// The first paragraph of `commit`'s message on one line, like `git log --oneline`
+pub fn summary(commit: &Commit) -> String {
+  message(commit)
+    .trim_start()
+    .split("\n\n")
+    .next()
+    .unwrap_or_default()
+    .split_whitespace()
+    .collect::<Vec<_>>()
+    .join(" ")
+}
+
+/// `bytes` in `encoding`, which is UTF-8 when git wrote no header
+pub fn decode(bytes: &[u8], encoding: Option<&str>) -> String {
+  let Some(encoding) = encoding.filter(|encoding| !is_utf8(encoding)) else {
+    return String::from_utf8_lossy(bytes).into_owned();
+  };
+
+  match encoding.to_lowercase().as_str() {
+    // Every byte is the code point of the same value
+    "iso-8859-1" | "iso8859-1" | "latin1" | "latin-1" => bytes.iter().map(|&byte| byte as char).collect(),
+    _ => iconv(bytes, encoding).unwrap_or_else(|| String::from_utf8_lossy(bytes).into_owned())
+  }
+}
+
+fn is_utf8(encoding: &str) -> bool {
+  matches!(encoding.to_lowercase().as_str(), "utf-8" | "utf8")
+}
+
+fn iconv(bytes: &[u8], encoding: &str) -> Option<String> {
+  let mut child = Command::new("iconv")
+    .args(["-f", encoding, "-t", "UTF-8"])
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::null())
+    .spawn()
+    .ok()?;
+  child.stdin.take()?.write_all(bytes).ok()?;
+
+  let output = child.wait_with_output().ok()?;
+  match output.status.success() {
+    true => String::from_utf8(output.stdout).ok(),
+    false => None
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use anyhow::Result;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_decode() {
+    assert_eq!(decode("Fix å".as_bytes(), None), "Fix å");
+    assert_eq!(decode(b"Fix \xe5", Some("ISO-8859-1")), "Fix å");
+    assert_eq!(decode(b"Fix \xe5", Some("UTF-8")), "Fix \u{fffd}");
+  }
+
+  #[test]
+  fn test_message_in_declared_encoding() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let signature = head.author().to_owned();
+
+    // libgit2 can't write an encoding header, so the object is put together by hand
+    let raw = format!(
+      "tree {}\nparent {}\nauthor {}\ncommitter {}\nencoding ISO-8859-1\n\n",
+      head.tree_id(),
+      head.id(),
+      raw_signature(&signature),
+      raw_signature(&signature)
+    );
+    let mut bytes = raw.into_bytes();
+    bytes.extend_from_slice(b"R\xe4tta stavning\n\nI br\xf6dtexten\n");
+    let oid = repo.odb()?.write(git2::ObjectType::Commit, &bytes)?;
+    let commit = repo.find_commit(oid)?;
+
+    assert_eq!(commit.message(), None);
+    assert_eq!(message(&commit), "Rätta stavning\n\nI brödtexten\n");
+    assert_eq!(summary(&commit), "Rätta stavning");
+    Ok(())
+  }
+
+  fn raw_signature(signature: &git2::Signature) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    format!(
+      "{} <{}> {} {}{:02}{:02}",
+      signature.name().unwrap(),
+      signature.email().unwrap(),
+      when.seconds(),
+      if offset < 0 { '-' } else { '+' },
+      offset.abs() / 60,
+      offset.abs() % 60
+    )
+  }
+}
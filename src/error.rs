@@ -0,0 +1,160 @@
+use std::path::PathBuf;
+use std::process::ExitCode;
+use thiserror::Error;
+use crate::i18n::t;
+use crate::policy::Violations;
+
+/// Why `squash` failed, for library callers and scripts to match on. The
+/// messages are the ones the CLI prints. Other library functions return
+/// `anyhow::Result`, and `SquashError::from` recovers this from their errors.
+#[derive(Debug, Error)]
+pub enum SquashError {
+  #[error("{}", t!("dirty-worktree"))]
+  DirtyWorktree,
+  #[error("{}", t!("too-many-commits", count = requested, available = available))]
+  NotEnoughCommits { requested: usize, available: usize },
+  #[error("{}", t!("shallow-limit", limit = limit, count = requested))]
+  ShallowClone { limit: usize, requested: usize },
+  #[error("{}", t!("protected-branch", branch = branch))]
+  ProtectedBranch { branch: String },
+  #[error("{}", t!("bare-repository", path = path.display()))]
+  BareRepository { path: PathBuf },
+  #[error("{}", t!("squash-stopped"))]
+  Stopped,
+  /// Git is in the middle of a merge, rebase or the like
+  #[error("{}", t!("operation-in-progress", operation = operation))]
+  InProgress { operation: String },
+  #[error("{}", t!("aborted-by-hook"))]
+  HookRejected(#[source] anyhow::Error),
+  /// There is nothing to squash, e.g. the branch is not ahead of its upstream
+  #[error("{0}")]
+  NothingToSquash(String),
+  /// A safety check refused to rewrite history
+  #[error("{0}")]
+  Refused(String),
+  /// The user declined or left the message empty
+  #[error("{0}")]
+  Aborted(String),
+  #[error(transparent)]
+  Git(#[from] git2::Error),
+  /// The `git` binary failed
+  #[error("{0}")]
+  GitCommand(String),
+  #[error(transparent)]
+  Other(anyhow::Error)
+}
+
+/// The exit codes of the CLI, so scripts can branch on the outcome
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ExitStatus {
+  Success         = 0,
+  /// Anything not covered below, like invalid arguments or configuration
+  Failure         = 1,
+  NothingToSquash = 2,
+  /// A safety check or policy refused the squash
+  Refused         = 3,
+  Aborted         = 4,
+  Git             = 5
+}
+
+impl ExitStatus {
+  /// The status for `error`, going by the first typed error in its chain
+  pub fn of(error: &anyhow::Error) -> Self {
+    for cause in error.chain() {
+      if let Some(error) = cause.downcast_ref::<SquashError>() {
+        return error.status();
+      }
+      if cause.is::<Violations>() {
+        return ExitStatus::Refused;
+      }
+      if cause.is::<git2::Error>() {
+        return ExitStatus::Git;
+      }
+    }
+
+    ExitStatus::Failure
+  }
+}
+
+impl From<ExitStatus> for ExitCode {
+  fn from(status: ExitStatus) -> Self {
+    ExitCode::from(status as u8)
+  }
+}
+
+impl SquashError {
+  pub fn status(&self) -> ExitStatus {
+    match self {
+      SquashError::NotEnoughCommits {
+        ..
+      }
+      | SquashError::NothingToSquash(_) => ExitStatus::NothingToSquash,
+      SquashError::DirtyWorktree
+      | SquashError::ShallowClone {
+        ..
+      }
+      | SquashError::ProtectedBranch {
+        ..
+      }
+      | SquashError::BareRepository {
+        ..
+      }
+      | SquashError::Stopped
+      | SquashError::InProgress {
+        ..
+      }
+      | SquashError::HookRejected(_)
+      | SquashError::Refused(_) => ExitStatus::Refused,
+      SquashError::Aborted(_) => ExitStatus::Aborted,
+      SquashError::Git(_) | SquashError::GitCommand(_) => ExitStatus::Git,
+      SquashError::Other(error) => ExitStatus::of(error)
+    }
+  }
+}
+
+impl From<anyhow::Error> for SquashError {
+  /// Recovers a `SquashError` raised further down, even if context was added
+  /// on the way up
+  fn from(error: anyhow::Error) -> Self {
+    let error = match error.downcast::<SquashError>() {
+      Ok(error) => return error,
+      Err(error) => error
+    };
+
+    match error.downcast::<git2::Error>() {
+      Ok(error) => SquashError::Git(error),
+      Err(error) => SquashError::Other(error)
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use anyhow::Context;
+
+  #[test]
+  fn test_recovers_typed_errors() {
+    let error = anyhow::Error::new(SquashError::DirtyWorktree).context("Failed to squash");
+    assert!(matches!(SquashError::from(error), SquashError::DirtyWorktree));
+
+    let error = Err::<(), _>(git2::Error::from_str("locked")).context("Failed to reset").unwrap_err();
+    assert!(matches!(SquashError::from(error), SquashError::Git(_)));
+
+    let error = anyhow::anyhow!("something else");
+    assert!(matches!(SquashError::from(error), SquashError::Other(_)));
+  }
+
+  #[test]
+  fn test_exit_status() {
+    let error = anyhow::Error::new(SquashError::Refused("tags".to_string())).context("Failed to squash");
+    assert_eq!(ExitStatus::of(&error), ExitStatus::Refused);
+
+    let error = Err::<(), _>(git2::Error::from_str("locked")).context("Failed to reset").unwrap_err();
+    assert_eq!(ExitStatus::of(&error), ExitStatus::Git);
+
+    let error = anyhow::Error::new(SquashError::Other(anyhow::Error::new(SquashError::Aborted("no".to_string()))));
+    assert_eq!(ExitStatus::of(&error), ExitStatus::Aborted);
+    assert_eq!(ExitStatus::of(&anyhow::anyhow!("bad config")), ExitStatus::Failure);
+  }
+}
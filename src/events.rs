@@ -0,0 +1,66 @@
+use git2::Oid;
+use crate::hooks::Hook;
+use crate::i18n::t;
+use crate::upstream::Destination;
+
+/// Steps of a squash, reported to an `Observer` as they happen
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+  /// The commits to squash were found, oldest first
+  CommitsEnumerated(Vec<Oid>),
+  /// `HEAD` was soft-reset onto this commit
+  ResetPerformed(Oid),
+  /// A hook ran and exited successfully
+  HookRan(Hook),
+  /// The squash commit was created and `HEAD` moved to it
+  CommitCreated(Oid),
+  /// Each replaced commit and the commit it became, oldest first
+  CommitsRewritten(Vec<(Oid, Oid)>),
+  /// The squashed branch was pushed to its upstream
+  PushFinished(Destination)
+}
+
+/// Follows the progress of a squash, e.g. to drive a progress bar
+pub trait Observer {
+  fn notify(&mut self, event: &Event);
+}
+
+impl<F: FnMut(&Event)> Observer for F {
+  fn notify(&mut self, event: &Event) {
+    self(event)
+  }
+}
+
+/// Ignores every event
+pub struct Silent;
+
+impl Observer for Silent {
+  fn notify(&mut self, _event: &Event) {}
+}
+
+/// Prints every event to stderr, for `--verbose`
+pub struct Verbose;
+
+impl Observer for Verbose {
+  fn notify(&mut self, event: &Event) {
+    let line = match event {
+      Event::CommitsEnumerated(commits) => t!("event-commits", count = commits.len()),
+      Event::ResetPerformed(base) => t!("event-reset", oid = short(base)),
+      Event::HookRan(hook) => t!("event-hook", hook = hook.name()),
+      Event::CommitCreated(oid) => t!("event-commit", oid = short(oid)),
+      Event::CommitsRewritten(rewritten) => {
+        rewritten
+          .iter()
+          .map(|(old, new)| t!("event-rewritten", old = short(old), new = short(new)))
+          .collect::<Vec<_>>()
+          .join("\n")
+      },
+      Event::PushFinished(destination) => t!("event-push", refspec = destination.refspec, remote = destination.remote)
+    };
+    eprintln!("{}", line);
+  }
+}
+
+fn short(oid: &Oid) -> String {
+  oid.to_string()[..7].to_string()
+}
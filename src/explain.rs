@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use crate::config::Config;
+use crate::hooks::{self, Hook};
+use crate::i18n::t;
+use crate::squash::Squashed;
+use crate::encoding;
+
+/// The steps squashing `squashed` takes, in order and with the objects
+/// involved, without taking any of them. The squash commit's tree is
+/// written to the object database to name it, which changes no ref.
+pub fn steps(repo: &Repository, squashed: &Squashed, config: &Config, reflog: &str) -> Result<Vec<String>> {
+  let backends = config.backend();
+  let head = repo.head().context("Failed to get HEAD")?.target().context("Failed to resolve HEAD")?;
+  let count = squashed.commits.len();
+
+  let mut steps = vec![t!("explain-walk", count = count, head = short(head), backend = backends.walk_backend(repo).name())];
+  let base = squashed.base_commit(repo)?;
+  match &base {
+    Some(base) => {
+      let subject = encoding::summary(base);
+      steps.push(t!("explain-parent", count = count, oid = short(base.id()), subject = subject));
+    },
+    None => steps.push(t!("explain-root"))
+  }
+
+  if let Some(hook) = hooks::describe(repo, &config.hooks, Hook::PreSquash) {
+    steps.push(t!("explain-hook", hook = Hook::PreSquash.name(), command = hook));
+  }
+
+  let tree = repo
+    .index()
+    .context("Failed to get index")?
+    .write_tree()
+    .context("Failed to write tree")?;
+  let sign = config.sign();
+  match &base {
+    Some(base) => {
+      steps.push(t!("explain-reset", oid = short(base.id()), backend = backends.reset.name()));
+      steps.push(t!("explain-tree", tree = short(tree)));
+      let backend = backends.commit.name();
+      steps.push(match sign {
+        true => t!("explain-commit-signed", tree = short(tree), parents = short(base.id()), backend = backend),
+        false => t!("explain-commit", tree = short(tree), parents = short(base.id()), backend = backend)
+      });
+    },
+    None => {
+      // Squashing down to the root always goes through libgit2
+      steps.push(t!("explain-tree", tree = short(tree)));
+      steps.push(match sign {
+        true => t!("explain-commit-signed", tree = short(tree), parents = "", backend = "libgit2"),
+        false => t!("explain-commit", tree = short(tree), parents = "", backend = "libgit2")
+      });
+    }
+  }
+  steps.push(t!("explain-verify", tree = short(tree), head = short(head)));
+  steps.push(t!("explain-reflog", message = reflog));
+
+  if let Some(hook) = hooks::describe(repo, &config.hooks, Hook::PostSquash) {
+    steps.push(t!("explain-hook", hook = Hook::PostSquash.name(), command = hook));
+  }
+
+  Ok(steps)
+}
+
+fn short(oid: Oid) -> String {
+  oid.to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::Libgit2;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_steps() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let base = head.parent(0)?.parent(0)?;
+    let tree = short(head.tree_id());
+
+    let squash = steps(&repo, &Squashed::walk(&repo, &Libgit2, 2)?, &Config::default(), "squash")?;
+    assert_eq!(squash.len(), 7);
+    assert!(squash[1].contains(&short(base.id())) && squash[1].contains("Commit 0"));
+    assert!(squash[4].contains(&tree) && squash[4].contains(&format!("[{}]", short(base.id()))));
+    assert_eq!(repo.head()?.target(), Some(head.id()), "nothing is changed");
+
+    let root = steps(&repo, &Squashed::walk(&repo, &Libgit2, 3)?, &Config::default(), "squash")?;
+    assert!(root[3].contains("[]"));
+    Ok(())
+  }
+}
@@ -0,0 +1,150 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use crate::audit::{self, Record};
+use crate::config::Config;
+use crate::hooks::{self, Hook, HookEnv, Hooks};
+use crate::i18n::t;
+use crate::squash;
+use crate::state;
+use crate::tags;
+use crate::transaction::Transaction;
+
+/// What a squash does once its commits are rewritten. Saved along with a
+/// plan stopped at a conflict, so `git squash continue` ends the squash the
+/// way it would have ended without stopping.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default)]
+pub struct Finish {
+  /// The tags to move along with their rewritten commits
+  pub tags:      Vec<String>,
+  /// Where to record the squash, `None` without an audit log
+  pub audit_log: Option<PathBuf>,
+  pub branch:    Option<String>,
+  pub count:     usize,
+  /// The message the audit log records and the post-squash hook gets
+  pub message:   String,
+  /// The hooks to run the post-squash hook from, `None` to skip it
+  pub hooks:     Option<Hooks>,
+  /// The commit the squash is put on top of, for the post-squash hook
+  #[serde(with = "state::oid::optional")]
+  pub base:      Option<Oid>,
+  /// The newest squashed commit, for the post-squash hook
+  #[serde(with = "state::oid::optional")]
+  pub tip:       Option<Oid>,
+  /// Whether changes were stashed before the squash, to be restored after it
+  pub unstash:   bool
+}
+
+/// How a squash ended
+#[derive(Debug, PartialEq)]
+pub struct Finished {
+  pub old_head:  Oid,
+  pub new_head:  Oid,
+  /// Each replaced commit and the commit it became, oldest first
+  pub rewritten: Vec<(Oid, Oid)>,
+  /// The tags moved along with their commits
+  pub tags:      Vec<String>,
+  pub hook_ran:  bool,
+  /// What went wrong after the squash was made, like a failing post-squash
+  /// hook, for the caller to report
+  pub warnings:  Vec<String>
+}
+
+impl Finish {
+  /// Records a squash of `count` commits on `branch` in the audit log
+  /// `config` sets, if any, and does nothing else
+  pub fn new(repo: &Repository, config: &Config, branch: Option<&str>, count: usize, message: &str) -> Self {
+    Self {
+      audit_log: config.audit_log.as_deref().map(|path| audit::resolve_path(repo, path)),
+      branch: branch.map(str::to_string),
+      count,
+      message: message.to_string(),
+      ..Self::default()
+    }
+  }
+
+  /// Moves the tags and records the squash from `old_head` to `new_head`
+  /// before committing `transaction`, then restores the stashed changes and
+  /// runs the post-squash hook
+  pub fn complete(
+    &self, repo: &Repository, transaction: Transaction, old_head: Oid, new_head: Oid, rewritten: Vec<(Oid, Oid)>
+  ) -> Result<Finished> {
+    let mut moved = Vec::new();
+    if !self.tags.is_empty() {
+      let old = rewritten.iter().map(|(old, _)| *old).collect::<Vec<_>>();
+      moved = tags::pointing_into(repo, &old)?;
+      moved.retain(|tag| self.tags.contains(&tag.name));
+      tags::move_along(repo, &moved, &rewritten)?;
+    }
+    if let Some(path) = &self.audit_log {
+      audit::append(path, &Record::new(repo, self.branch.as_deref(), old_head, new_head, self.count, &self.message))?;
+    }
+    transaction.commit();
+
+    if self.unstash {
+      squash::unstash(repo).with_context(|| t!("autostash-kept"))?;
+    }
+
+    let mut hook_ran = false;
+    let mut warnings = Vec::new();
+    if let Some(hooks) = &self.hooks {
+      let env = HookEnv {
+        base: self.base,
+        tip: self.tip.unwrap_or(old_head),
+        old_head,
+        new_head: Some(new_head),
+        count: self.count,
+        message: &self.message
+      };
+      match hooks::run(repo, hooks, Hook::PostSquash, &env) {
+        Ok(ran) => hook_ran = ran,
+        Err(error) => warnings.push(format!("{:#}", error))
+      }
+    }
+
+    Ok(Finished {
+      old_head,
+      new_head,
+      rewritten,
+      tags: moved.into_iter().map(|tag| tag.name).collect(),
+      hook_ran,
+      warnings
+    })
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_complete_moves_tags_and_audits() -> Result<()> {
+    let (dir, repo) = repo_with_commits(3)?;
+    let old_head = repo.head()?.peel_to_commit()?;
+    let parent = old_head.parent(0)?;
+    repo.tag_lightweight("v1", old_head.as_object(), false)?;
+    repo.tag_lightweight("v0", parent.as_object(), false)?;
+
+    let config = Config {
+      audit_log: Some(PathBuf::from("audit.jsonl")),
+      ..Config::default()
+    };
+    let finish = Finish {
+      tags: vec!["v1".to_string()],
+      ..Finish::new(&repo, &config, Some("main"), 2, "Squashed")
+    };
+    let transaction = Transaction::begin(&repo)?;
+    let rewritten = vec![(parent.id(), parent.id()), (old_head.id(), parent.id())];
+    let finished = finish.complete(&repo, transaction, old_head.id(), parent.id(), rewritten)?;
+
+    assert_eq!(finished.tags, ["v1"]);
+    assert_eq!(repo.refname_to_id("refs/tags/v1")?, parent.id());
+    let log = std::fs::read_to_string(dir.path().join("audit.jsonl"))?;
+    assert!(log.contains("\"branch\":\"main\""));
+    assert!(log.contains("\"count\":2,\"message\":\"Squashed\""));
+    Ok(())
+  }
+}
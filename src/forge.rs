@@ -0,0 +1,205 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{bail, Context, Result};
+use git2::Repository;
+use crate::gitlab::{self, MergeRequest};
+use crate::github;
+use crate::i18n::t;
+
+/// A message offered in the selection menu next to the squashed commits' own
+#[derive(Clone, Debug, PartialEq)]
+pub struct Suggestion {
+  pub label:   String,
+  pub message: String
+}
+
+/// Host and repository path (`owner/repo`, or `group/subgroup/repo`) of a remote
+#[derive(Debug, PartialEq)]
+pub struct Remote {
+  pub host: String,
+  pub path: String
+}
+
+/// Parses https, ssh and scp-like (`git@host:owner/repo.git`) remote URLs
+pub fn parse_remote(url: &str) -> Option<Remote> {
+  let url = url.trim().trim_end_matches('/');
+  let url = url.strip_suffix(".git").unwrap_or(url);
+
+  let (host, path) = match url.split_once("://") {
+    Some((_scheme, rest)) => {
+      let (authority, path) = rest.split_once('/')?;
+      let host = authority.rsplit('@').next()?;
+      (host.split(':').next()?, path)
+    },
+    None => {
+      let (authority, path) = url.split_once(':')?;
+      (authority.rsplit('@').next()?, path)
+    }
+  };
+
+  (!host.is_empty() && path.contains('/')).then(|| {
+    Remote {
+      host: host.to_lowercase(), path: path.trim_start_matches('/').to_string()
+    }
+  })
+}
+
+/// The remote `branch` tracks, falling back to `origin`
+pub fn remote(repo: &Repository, branch: &str) -> Option<Remote> {
+  let name = repo
+    .branch_upstream_remote(&format!("refs/heads/{}", branch))
+    .ok()
+    .and_then(|name| name.as_str().map(str::to_string))
+    .unwrap_or_else(|| "origin".to_string());
+  let remote = repo.find_remote(&name).ok()?;
+  parse_remote(remote.url()?)
+}
+
+/// Titles of the open pull or merge request for `branch`, plus the merge
+/// request's description. Lookups that fail are reported as warnings, since
+/// they are only a convenience.
+pub fn suggestions(repo: &Repository, branch: Option<&str>) -> Vec<Suggestion> {
+  let Some((branch, remote)) = branch.and_then(|branch| Some((branch, remote(repo, branch)?))) else {
+    return Vec::new();
+  };
+
+  let mut suggestions = Vec::new();
+  if github::is_github(&remote.host) {
+    match github::pull_request(repo, &remote, branch) {
+      Ok(Some(pull)) => {
+        suggestions
+          .push(Suggestion {
+            label: t!("pull-request", number = pull.number, title = pull.title), message: pull.title
+          })
+      },
+      Ok(None) => {},
+      Err(error) => eprintln!("{}", t!("warning", error = format!("{:#}", error)))
+    }
+  }
+
+  if gitlab::is_gitlab(&remote.host) {
+    match gitlab::merge_request(&remote, branch) {
+      Ok(Some(request)) => suggestions.extend(merge_request_suggestions(request)),
+      Ok(None) => {},
+      Err(error) => eprintln!("{}", t!("warning", error = format!("{:#}", error)))
+    }
+  }
+
+  suggestions
+}
+
+/// The title on its own and, when there is one, together with the description as body
+fn merge_request_suggestions(request: MergeRequest) -> Vec<Suggestion> {
+  let mut suggestions = vec![Suggestion {
+    label:   t!("merge-request", number = request.iid, title = request.title),
+    message: request.title.clone()
+  }];
+
+  if let Some(description) = request
+    .description
+    .as_deref()
+    .map(str::trim)
+    .filter(|description| !description.is_empty())
+  {
+    suggestions.push(Suggestion {
+      label:   t!("merge-request-description", number = request.iid),
+      message: format!("{}\n\n{}", request.title, description.replace("\r\n", "\n"))
+    });
+  }
+
+  suggestions
+}
+
+/// Fetches `url` with curl and parses the response as JSON. The headers,
+/// which carry tokens, go to curl on stdin, as its arguments are visible
+/// to every user through `ps`.
+pub fn get_json(url: &str, headers: &[String]) -> Result<serde_json::Value> {
+  let mut command = Command::new("curl");
+  command.args(["--silent", "--show-error", "--fail", "--location", "--max-time", "10"]);
+  if !headers.is_empty() {
+    command.args(["--header", "@-"]);
+  }
+
+  let mut child = command
+    .arg(url)
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .context("Failed to run curl")?;
+  let mut stdin = child.stdin.take().context("Failed to open curl's stdin")?;
+  for header in headers {
+    writeln!(stdin, "{}", header).context("Failed to pass headers to curl")?;
+  }
+  drop(stdin);
+
+  let output = child.wait_with_output().context("Failed to run curl")?;
+  if !output.status.success() {
+    bail!("Request to {} failed: {}", url, String::from_utf8_lossy(&output.stderr).trim());
+  }
+
+  serde_json::from_slice(&output.stdout).with_context(|| format!("Failed to parse response from {}", url))
+}
+
+/// Percent-encodes `text` for use in a URL query or path segment
+pub fn encode(text: &str) -> String {
+  text
+    .bytes()
+    .map(|byte| {
+      match byte {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+        _ => format!("%{:02X}", byte)
+      }
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_remote() {
+    let expected = Some(Remote {
+      host: "github.com".to_string(), path: "oleander/git-squash-rs".to_string()
+    });
+    assert_eq!(parse_remote("git@github.com:oleander/git-squash-rs.git"), expected);
+    assert_eq!(parse_remote("https://github.com/oleander/git-squash-rs"), expected);
+    assert_eq!(parse_remote("ssh://git@github.com:22/oleander/git-squash-rs.git/"), expected);
+    assert_eq!(parse_remote("https://token@GitHub.com/oleander/git-squash-rs.git"), expected);
+
+    let nested = parse_remote("git@gitlab.com:group/sub/project.git").unwrap();
+    assert_eq!(nested.path, "group/sub/project");
+    assert_eq!(parse_remote("/srv/git/project.git"), None);
+  }
+
+  #[test]
+  fn test_merge_request_suggestions() {
+    let request =
+      MergeRequest {
+        iid: 7, title: "Add squash".to_string(), description: Some("Details\r\nhere\n".to_string())
+      };
+    let suggestions = merge_request_suggestions(request);
+
+    assert_eq!(suggestions.len(), 2);
+    assert_eq!(suggestions[0].label, "➜ MR !7: Add squash");
+    assert_eq!(suggestions[1].message, "Add squash\n\nDetails\nhere");
+  }
+
+  #[test]
+  fn test_encode() {
+    assert_eq!(encode("feature/x y#1"), "feature%2Fx%20y%231");
+  }
+
+  #[test]
+  fn test_get_json_passes_headers_on_stdin() -> Result<()> {
+    let dir = tempdir::TempDir::new("forge")?;
+    let path = dir.path().join("pulls.json");
+    std::fs::write(&path, r#"[{"number": 7}]"#)?;
+
+    let url = format!("file://{}", path.display());
+    let json = get_json(&url, &["Authorization: Bearer secret".to_string()])?;
+    assert_eq!(json[0]["number"], 7);
+    Ok(())
+  }
+}
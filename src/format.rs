@@ -0,0 +1,301 @@
+use anyhow::{Context, Result};
+use console::measure_text_width;
+use git2::{Commit, Mailmap, Oid, Repository, Time};
+use crate::dates::{self, DateFormat, Dates};
+use crate::diff;
+use crate::encoding;
+use crate::i18n::t;
+use crate::sign;
+
+pub const SECONDS_IN_MINUTE: i64 = 60;
+pub const SECONDS_IN_HOUR: i64 = 60 * SECONDS_IN_MINUTE;
+pub const SECONDS_IN_DAY: i64 = 24 * SECONDS_IN_HOUR;
+pub const SECONDS_IN_WEEK: i64 = 7 * SECONDS_IN_DAY;
+pub const SECONDS_IN_MONTH: i64 = 30 * SECONDS_IN_DAY;
+pub const SECONDS_IN_YEAR: i64 = 365 * SECONDS_IN_DAY;
+pub const AUTHOR_WIDTH: usize = 16;
+pub const MIN_SUMMARY_WIDTH: usize = 20;
+pub const FALLBACK_WIDTH: usize = 120;
+/// Below this many commits formatting on one thread is faster than starting more
+pub const PARALLEL_THRESHOLD: usize = 32;
+
+pub trait FormatCommit {
+  /// Age, short SHA, author, subject and diffstat in columns fitted to the
+  /// terminal width, with the author `mailmap` maps them to. The diffstat is
+  /// computed on each call, so only format the commits that are about to be
+  /// shown, and load the mailmap once for all of them.
+  fn format(&self, repo: &Repository, mailmap: &Mailmap, dates: Dates) -> Result<String>;
+}
+
+impl<'a> FormatCommit for Commit<'a> {
+  fn format(&self, repo: &Repository, mailmap: &Mailmap, dates: Dates) -> Result<String> {
+    Ok(Entry::new(repo, mailmap, self, dates)?.render(dates))
+  }
+}
+
+/// What a list entry shows for a commit, owned so it can be computed on
+/// another thread than the one rendering it
+#[derive(Clone, Debug)]
+pub struct Entry {
+  pub short_id: String,
+  pub author:   String,
+  pub signed:   bool,
+  pub time:     Time,
+  pub summary:  String,
+  pub stat:     String
+}
+
+impl Entry {
+  pub fn new(repo: &Repository, mailmap: &Mailmap, commit: &Commit, dates: Dates) -> Result<Self> {
+    let short_id = commit.as_object().short_id().context("Failed to get short id")?;
+    // Show canonical identities from .mailmap rather than whatever was used at the time
+    let author = commit.author_with_mailmap(mailmap).context("Failed to resolve author")?;
+
+    Ok(Self {
+      short_id: short_id.as_str().unwrap_or_default().to_string(),
+      author:   author.name().unwrap_or_default().to_string(),
+      signed:   sign::is_signed(repo, commit.id()),
+      time:     dates.time(commit),
+      summary:  encoding::summary(commit),
+      stat:     diff::stat(repo, commit)?
+    })
+  }
+
+  /// Age, short SHA, author, subject and diffstat in columns fitted to the
+  /// terminal width
+  pub fn render(&self, dates: Dates) -> String {
+    let date = match dates.format {
+      DateFormat::Relative => self.time.ago(),
+      DateFormat::Absolute => dates::absolute(self.time)
+    };
+    let signed = if self.signed { "✓" } else { " " };
+    let author = truncate(&self.author, AUTHOR_WIDTH);
+    let prefix = format!("{} {}{} {}", date, self.short_id, signed, pad(&author, AUTHOR_WIDTH));
+
+    // Leave room for the selection marker the prompt puts in front of each item
+    let room = terminal_width().saturating_sub(measure_text_width(&prefix) + measure_text_width(&self.stat) + 4);
+    let summary = truncate(&self.summary, room.max(MIN_SUMMARY_WIDTH));
+    format!("{} {} {}", prefix, pad(&summary, room), self.stat)
+  }
+}
+
+/// Formats `commits` in order, computing the diffstats and author lookups
+/// of large ranges on all cores. Each thread opens its own handle since a
+/// `Repository` can't be shared between threads.
+pub fn format_all(repo: &Repository, commits: &[Oid], dates: Dates) -> Result<Vec<String>> {
+  let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+  if threads == 1 || commits.len() < PARALLEL_THRESHOLD {
+    return Ok(entries(repo, commits, dates)?.iter().map(|entry| entry.render(dates)).collect());
+  }
+
+  let path = repo.path().to_path_buf();
+  let chunks = std::thread::scope(|scope| {
+    let workers = commits
+      .chunks(commits.len().div_ceil(threads))
+      .map(|chunk| {
+        let path = &path;
+        scope.spawn(move || {
+          let repo = Repository::open(path).context("Failed to open repository")?;
+          entries(&repo, chunk, dates)
+        })
+      })
+      .collect::<Vec<_>>();
+
+    workers
+      .into_iter()
+      .map(|worker| worker.join().expect("formatting thread panicked"))
+      .collect::<Result<Vec<_>>>()
+  })?;
+
+  Ok(chunks.iter().flatten().map(|entry| entry.render(dates)).collect())
+}
+
+fn entries(repo: &Repository, commits: &[Oid], dates: Dates) -> Result<Vec<Entry>> {
+  let mailmap = repo.mailmap().context("Failed to load mailmap")?;
+  commits
+    .iter()
+    .map(|oid| {
+      let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+      Entry::new(repo, &mailmap, &commit, dates)
+    })
+    .collect()
+}
+
+/// Shortens `text` to at most `width` terminal columns, marking the cut with
+/// "...". Wide characters count double and combining marks stay with the
+/// character they belong to.
+pub fn truncate(text: &str, width: usize) -> String {
+  if measure_text_width(text) <= width {
+    return text.to_string();
+  }
+
+  let limit = width.saturating_sub(3);
+  let mut truncated = String::new();
+  let mut used = 0;
+  for c in text.chars() {
+    let columns = measure_text_width(c.encode_utf8(&mut [0; 4]));
+    if columns > 0 && used + columns > limit {
+      break;
+    }
+    used += columns;
+    truncated.push(c);
+  }
+
+  // Don't leave half of an emoji sequence behind
+  let mut truncated = truncated.trim_end_matches('\u{200d}').to_string();
+  truncated.push_str("...");
+  truncated
+}
+
+/// Pads `text` with spaces to `width` terminal columns
+pub fn pad(text: &str, width: usize) -> String {
+  format!("{}{}", text, " ".repeat(width.saturating_sub(measure_text_width(text))))
+}
+
+pub fn terminal_width() -> usize {
+  console::Term::stdout()
+    .size_checked()
+    .map(|(_rows, columns)| columns as usize)
+    .unwrap_or(FALLBACK_WIDTH)
+}
+
+pub trait TimeAgo {
+  /// How long ago this was, e.g. "3 d", padded to a fixed-width column
+  fn ago(&self) -> String;
+}
+
+impl TimeAgo for Time {
+  fn ago(&self) -> String {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+    // `seconds()` is UTC, the offset only matters when showing the wall-clock time
+    pad(&humanize(now - self.seconds()), 8)
+  }
+}
+
+/// Renders `elapsed` seconds in the largest unit that still reads well,
+/// rounded to the nearest whole unit. Times in the future (clock skew) count as now.
+pub fn humanize(elapsed: i64) -> String {
+  let round = |unit: i64| (elapsed + unit / 2) / unit;
+
+  match elapsed {
+    n if n < SECONDS_IN_MINUTE => t!("ago-now"),
+    n if n < SECONDS_IN_HOUR - SECONDS_IN_MINUTE / 2 => t!("ago-minutes", count = round(SECONDS_IN_MINUTE)),
+    n if n < SECONDS_IN_DAY - SECONDS_IN_HOUR / 2 => t!("ago-hours", count = round(SECONDS_IN_HOUR)),
+    n if n < 2 * SECONDS_IN_WEEK - SECONDS_IN_DAY / 2 => t!("ago-days", count = round(SECONDS_IN_DAY)),
+    n if n < 2 * SECONDS_IN_MONTH => t!("ago-weeks", count = round(SECONDS_IN_WEEK)),
+    n if n < SECONDS_IN_YEAR - SECONDS_IN_MONTH / 2 => t!("ago-months", count = round(SECONDS_IN_MONTH)),
+    _ => t!("ago-years", count = round(SECONDS_IN_YEAR))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::{GitBackend, Libgit2};
+  use crate::squash::Commitable;
+
+  #[test]
+  fn test_2_hours_ago() {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let two_hours_ago = now - (SECONDS_IN_HOUR * 2);
+    let hours = Time::new(two_hours_ago, 0).ago();
+    assert_eq!(hours.trim(), "2 h");
+  }
+
+  #[test]
+  fn test_just_now() {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
+    let time = Time::new(now, 0);
+    assert_eq!(time.ago().trim(), "now");
+    assert_eq!(Time::new(now + 600, 0).ago().trim(), "now", "future commits");
+  }
+
+  #[test]
+  fn test_humanize() {
+    assert_eq!(humanize(89), "1 m");
+    assert_eq!(humanize(90), "2 m");
+    assert_eq!(humanize(59 * SECONDS_IN_MINUTE + 40), "1 h");
+    assert_eq!(humanize(37 * SECONDS_IN_HOUR), "2 d");
+    assert_eq!(humanize(3 * SECONDS_IN_DAY), "3 d");
+    assert_eq!(humanize(13 * SECONDS_IN_DAY), "13 d");
+    assert_eq!(humanize(16 * SECONDS_IN_DAY), "2 w");
+    assert_eq!(humanize(59 * SECONDS_IN_DAY), "8 w");
+    assert_eq!(humanize(100 * SECONDS_IN_DAY), "3 mo");
+    assert_eq!(humanize(400 * SECONDS_IN_DAY), "1 y");
+    assert_eq!(humanize(1000 * SECONDS_IN_DAY), "3 y");
+  }
+
+  #[test]
+  fn test_format_commit() {
+    let repo = Repository::init("temp_test_repo").unwrap();
+    let commit_id = repo.commit_with_msg("This is a test commit".as_ref()).unwrap();
+    let commit = repo.find_commit(commit_id).unwrap();
+    let formatted = commit.format(&repo, &repo.mailmap().unwrap(), Dates::default()).unwrap();
+    assert!(formatted.contains("This is a test commit"));
+    assert!(formatted.ends_with("+0/-0 (0 files)"));
+    std::fs::remove_dir_all("temp_test_repo").unwrap();
+  }
+
+  #[test]
+  fn test_format_columns() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(1)?;
+    let commit = repo.head()?.peel_to_commit()?;
+    let mailmap = repo.mailmap()?;
+    let formatted = commit.format(&repo, &mailmap, Dates::default())?;
+
+    assert!(formatted.contains("Commit 0"));
+    assert!(formatted.contains(&commit.id().to_string()[..7]));
+    assert!(formatted.contains("agent"));
+    let absolute = Dates {
+      format: DateFormat::Absolute,
+      ..Dates::default()
+    };
+    assert!(commit.format(&repo, &mailmap, absolute)?.starts_with(&dates::absolute(commit.time())));
+    Ok(())
+  }
+
+  #[test]
+  fn test_format_uses_mailmap() -> Result<()> {
+    let (dir, repo) = crate::testing::repo_with_commits(1)?;
+    std::fs::write(dir.path().join(".mailmap"), "Canonical Name <agent@local>\n")?;
+    let commit = repo.head()?.peel_to_commit()?;
+
+    assert!(commit.format(&repo, &repo.mailmap()?, Dates::default())?.contains("Canonical Name"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_format_all_keeps_order() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(PARALLEL_THRESHOLD + 8)?;
+    let commits = Libgit2.walk(&repo, PARALLEL_THRESHOLD + 8)?;
+
+    let formatted = format_all(&repo, &commits, Dates::default())?;
+    let mailmap = repo.mailmap()?;
+    let expected = commits
+      .iter()
+      .map(|oid| repo.find_commit(*oid)?.format(&repo, &mailmap, Dates::default()))
+      .collect::<Result<Vec<_>>>()?;
+    assert_eq!(formatted, expected);
+    Ok(())
+  }
+
+  #[test]
+  fn test_truncate() {
+    assert_eq!(truncate("short", 10), "short");
+    assert_eq!(truncate("a long subject", 10), "a long ...");
+    assert_eq!(truncate("ééééé", 4), "é...");
+    assert_eq!(
+      truncate("e\u{301}e\u{301}e\u{301}e\u{301}e\u{301}", 4),
+      "e\u{301}...",
+      "combining accents stay attached"
+    );
+    assert_eq!(truncate("日本語のコミット", 8), "日本...", "wide characters take two columns");
+    assert_eq!(measure_text_width(&truncate("日本語のコミット", 9)), 9);
+  }
+
+  #[test]
+  fn test_pad() {
+    assert_eq!(pad("日本", 6), "日本  ");
+    assert_eq!(pad("toolong", 3), "toolong");
+  }
+}
@@ -0,0 +1,106 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{bail, Context, Result};
+use git2::{Commit, Repository};
+
+/// Asks an external program (e.g. an LLM wrapper) for a squash message. The
+/// original messages and the combined diff of the range are written to its
+/// stdin and whatever it prints on stdout becomes the suggestion.
+pub fn generate(repo: &Repository, base: Option<&Commit>, commits: &[Commit], command: &str) -> Result<String> {
+  let input = prompt_input(repo, base, commits)?;
+
+  let mut child = Command::new("sh")
+    .arg("-c")
+    .arg(command)
+    .current_dir(repo.workdir().unwrap_or(repo.path()))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .spawn()
+    .with_context(|| format!("Failed to run `{}`", command))?;
+
+  // Written from a thread while stdout is read, as a program that prints
+  // before reading everything would otherwise block on a full pipe with us.
+  // It may also exit without reading everything, which is fine.
+  let mut stdin = child.stdin.take().expect("stdin is piped");
+  let writer = std::thread::spawn(move || {
+    let _ = stdin.write_all(input.as_bytes());
+  });
+
+  let output = child.wait_with_output().with_context(|| format!("Failed to run `{}`", command))?;
+  let _ = writer.join();
+  if !output.status.success() {
+    bail!("`{}` exited with {}", command, output.status);
+  }
+
+  let message = String::from_utf8(output.stdout).context("Generated message is not valid UTF-8")?;
+  let message = message.trim();
+  if message.is_empty() {
+    bail!("`{}` did not print a message", command);
+  }
+
+  Ok(message.to_string())
+}
+
+fn prompt_input(repo: &Repository, base: Option<&Commit>, commits: &[Commit]) -> Result<String> {
+  let mut input = String::from("# Commit messages\n\n");
+  for commit in commits {
+    input.push_str(&String::from_utf8_lossy(commit.message_bytes()));
+    input.push_str("\n---\n");
+  }
+
+  if let Some(head) = commits.first() {
+    input.push_str("\n# Diff\n\n");
+    input.push_str(&crate::diff::patch(repo, base, head)?);
+  }
+
+  Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_pipes_messages_and_diff() -> Result<()> {
+    let (dir, repo) = repo_with_commits(3)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let middle = head.parent(0)?;
+    let base = middle.parent(0)?;
+
+    let message = generate(&repo, Some(&base), &[head, middle], "cat > input.txt; echo 'feat: generated'")?;
+    assert_eq!(message, "feat: generated");
+
+    let input = std::fs::read_to_string(dir.path().join("input.txt"))?;
+    assert!(input.contains("Commit 2\n---"));
+    assert!(input.contains("Commit 1\n---"));
+    assert!(!input.contains("Commit 0"));
+    assert!(input.contains("+++ b/2.txt"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_large_diff_does_not_block() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let base = repo.head()?.peel_to_commit()?;
+    let line = "a line long enough to fill a pipe buffer quickly\n";
+    crate::testing::commit_file(&repo, "large.txt", &line.repeat(20_000), "Add large file")?;
+    let head = repo.head()?.peel_to_commit()?;
+
+    // `cat` echoes the input while it's still being written
+    let message = generate(&repo, Some(&base), &[head], "cat")?;
+    assert!(message.starts_with("# Commit messages"));
+    assert!(message.len() > line.len() * 20_000);
+    Ok(())
+  }
+
+  #[test]
+  fn test_rejects_empty_output() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let base = head.parent(0)?;
+
+    assert!(generate(&repo, Some(&base), &[head], "cat > /dev/null").is_err());
+    Ok(())
+  }
+}
@@ -0,0 +1,95 @@
+use std::process::Command;
+use anyhow::{bail, Context, Result};
+use git2::Repository;
+use serde::Deserialize;
+use crate::forge::{encode, get_json, Remote};
+
+/// The parts of a pull request the message menu needs
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct PullRequest {
+  pub number: u64,
+  pub title:  String,
+  #[serde(default)]
+  pub body:   Option<String>
+}
+
+/// github.com or the GitHub Enterprise host in `GH_HOST`
+pub fn is_github(host: &str) -> bool {
+  host == "github.com" || std::env::var("GH_HOST").is_ok_and(|gh_host| gh_host.eq_ignore_ascii_case(host))
+}
+
+/// The open pull request for `branch`, asked from the `gh` CLI when it is
+/// installed and from the REST API with `GITHUB_TOKEN` or `GH_TOKEN` otherwise
+pub fn pull_request(repo: &Repository, remote: &Remote, branch: &str) -> Result<Option<PullRequest>> {
+  let output = Command::new("gh")
+    .args(["pr", "view", branch, "--json", "number,title,body,state", "--repo"])
+    .arg(format!("{}/{}", remote.host, remote.path))
+    .current_dir(repo.workdir().unwrap_or(repo.path()))
+    .output();
+
+  match output {
+    Ok(output) if output.status.success() => parse_gh(&output.stdout),
+    Ok(output) => {
+      let error = String::from_utf8_lossy(&output.stderr);
+      if error.contains("no pull requests found") {
+        return Ok(None);
+      }
+      bail!("gh pr view failed: {}", error.trim())
+    },
+    Err(error) if error.kind() == std::io::ErrorKind::NotFound => from_api(remote, branch),
+    Err(error) => Err(error).context("Failed to run gh")
+  }
+}
+
+fn from_api(remote: &Remote, branch: &str) -> Result<Option<PullRequest>> {
+  let Some(token) = ["GITHUB_TOKEN", "GH_TOKEN"].iter().find_map(|key| std::env::var(key).ok()) else {
+    return Ok(None);
+  };
+
+  let api = match remote.host.as_str() {
+    "github.com" => "https://api.github.com".to_string(),
+    host => format!("https://{}/api/v3", host)
+  };
+  let owner = remote.path.split('/').next().unwrap_or_default();
+  let url = format!("{}/repos/{}/pulls?state=open&head={}", api, remote.path, encode(&format!("{}:{}", owner, branch)));
+  let headers = [format!("Authorization: Bearer {}", token), "Accept: application/vnd.github+json".to_string()];
+
+  let pulls = get_json(&url, &headers)?;
+  let pulls = serde_json::from_value::<Vec<PullRequest>>(pulls).context("Unexpected pull request response")?;
+  Ok(pulls.into_iter().next())
+}
+
+/// `gh pr view` also finds closed and merged pull requests, which are of no use here
+fn parse_gh(json: &[u8]) -> Result<Option<PullRequest>> {
+  #[derive(Deserialize)]
+  struct View {
+    state: String,
+    #[serde(flatten)]
+    pull:  PullRequest
+  }
+
+  let view = serde_json::from_slice::<View>(json).context("Unexpected gh pr view output")?;
+  Ok((view.state == "OPEN").then_some(view.pull))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_gh() -> Result<()> {
+    let open = br#"{"number": 12, "title": "Add squash", "body": "", "state": "OPEN"}"#;
+    let pull = parse_gh(open)?.unwrap();
+    assert_eq!((pull.number, pull.title.as_str()), (12, "Add squash"));
+
+    let merged = br#"{"number": 12, "title": "Add squash", "body": null, "state": "MERGED"}"#;
+    assert_eq!(parse_gh(merged)?, None);
+    Ok(())
+  }
+
+  #[test]
+  fn test_is_github() {
+    assert!(is_github("github.com"));
+    assert!(!is_github("gitlab.com"));
+  }
+}
@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use crate::forge::{encode, get_json, Remote};
+
+/// The parts of a merge request the message menu needs
+#[derive(Debug, Deserialize, PartialEq)]
+pub struct MergeRequest {
+  pub iid:         u64,
+  pub title:       String,
+  #[serde(default)]
+  pub description: Option<String>
+}
+
+/// gitlab.com, or the self-managed host in `GITLAB_HOST` (as used by `glab`)
+/// or `CI_SERVER_HOST` inside GitLab CI
+pub fn is_gitlab(host: &str) -> bool {
+  host == "gitlab.com"
+    || ["GITLAB_HOST", "CI_SERVER_HOST"].iter().any(|key| {
+      std::env::var(key).is_ok_and(|value| value.trim_start_matches("https://").trim_end_matches('/').eq_ignore_ascii_case(host))
+    })
+}
+
+/// The open merge request from `branch`, authenticated with `GITLAB_TOKEN`
+/// or the CI job token. Without either nothing is asked, so squashing
+/// doesn't wait on the network unless it's been set up to.
+pub fn merge_request(remote: &Remote, branch: &str) -> Result<Option<MergeRequest>> {
+  let header = match (std::env::var("GITLAB_TOKEN"), std::env::var("CI_JOB_TOKEN")) {
+    (Ok(token), _) => format!("PRIVATE-TOKEN: {}", token),
+    (_, Ok(token)) => format!("JOB-TOKEN: {}", token),
+    _ => return Ok(None)
+  };
+
+  let url = format!(
+    "https://{}/api/v4/projects/{}/merge_requests?state=opened&source_branch={}",
+    remote.host,
+    encode(&remote.path),
+    encode(branch)
+  );
+
+  let requests = get_json(&url, &[header])?;
+  let requests = serde_json::from_value::<Vec<MergeRequest>>(requests).context("Unexpected merge request response")?;
+  Ok(requests.into_iter().next())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_merge_requests() -> Result<()> {
+    let json = r#"[{"iid": 7, "title": "Add squash", "description": null, "state": "opened"}]"#;
+    let requests = serde_json::from_str::<Vec<MergeRequest>>(json)?;
+    assert_eq!(requests, [MergeRequest {
+      iid:         7,
+      title:       "Add squash".to_string(),
+      description: None
+    }]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_is_gitlab() {
+    assert!(is_gitlab("gitlab.com"));
+    assert!(!is_gitlab("github.com"));
+  }
+}
@@ -0,0 +1,84 @@
+use anyhow::{Context, Result};
+use crate::prompt;
+
+/// The most used entries of https://gitmoji.dev
+pub const GITMOJIS: [(&str, &str, &str); 30] = [
+  ("🎨", "art", "Improve structure / format of the code"),
+  ("⚡️", "zap", "Improve performance"),
+  ("🔥", "fire", "Remove code or files"),
+  ("🐛", "bug", "Fix a bug"),
+  ("🚑️", "ambulance", "Critical hotfix"),
+  ("✨", "sparkles", "Introduce new features"),
+  ("📝", "memo", "Add or update documentation"),
+  ("🚀", "rocket", "Deploy stuff"),
+  ("💄", "lipstick", "Add or update the UI and style files"),
+  ("🎉", "tada", "Begin a project"),
+  ("✅", "white-check-mark", "Add, update, or pass tests"),
+  ("🔒️", "lock", "Fix security or privacy issues"),
+  ("🔖", "bookmark", "Release / Version tags"),
+  ("🚨", "rotating-light", "Fix compiler / linter warnings"),
+  ("🚧", "construction", "Work in progress"),
+  ("💚", "green-heart", "Fix CI Build"),
+  ("⬇️", "arrow-down", "Downgrade dependencies"),
+  ("⬆️", "arrow-up", "Upgrade dependencies"),
+  ("📌", "pushpin", "Pin dependencies to specific versions"),
+  ("👷", "construction-worker", "Add or update CI build system"),
+  ("♻️", "recycle", "Refactor code"),
+  ("➕", "heavy-plus-sign", "Add a dependency"),
+  ("➖", "heavy-minus-sign", "Remove a dependency"),
+  ("🔧", "wrench", "Add or update configuration files"),
+  ("🌐", "globe-with-meridians", "Internationalization and localization"),
+  ("✏️", "pencil2", "Fix typos"),
+  ("⏪️", "rewind", "Revert changes"),
+  ("🔀", "twisted-rightwards-arrows", "Merge branches"),
+  ("🗑️", "wastebasket", "Deprecate code that needs to be cleaned up"),
+  ("🏷️", "label", "Add or update types")
+];
+
+/// Lets the user search the list by name or description. `None` when they
+/// choose to go without.
+pub fn pick() -> Result<Option<&'static str>> {
+  let mut items = vec!["  No emoji".to_string()];
+  items.extend(
+    GITMOJIS
+      .iter()
+      .map(|(emoji, name, description)| format!("{} :{}: {}", emoji, name, description))
+  );
+
+  let selection = prompt::fuzzy_select("Gitmoji (type to filter)", &items, 0).context("Failed to get gitmoji")?;
+
+  Ok(selection.checked_sub(1).map(|n| GITMOJIS[n].0))
+}
+
+/// Asks for a gitmoji when `enabled` and the message doesn't start with one yet
+pub fn pick_for(message: &str, enabled: bool) -> Result<Option<&'static str>> {
+  match enabled && !has_gitmoji(message) {
+    true => pick(),
+    false => Ok(None)
+  }
+}
+
+/// Whether the subject already starts with a gitmoji
+pub fn has_gitmoji(message: &str) -> bool {
+  GITMOJIS
+    .iter()
+    .any(|(emoji, name, _)| message.starts_with(emoji) || message.starts_with(&format!(":{}:", name)))
+}
+
+pub fn prefix(message: &str, emoji: &str) -> String {
+  format!("{} {}", emoji, message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_prefix() {
+    let message = prefix("Add login\n\nBody", "✨");
+    assert_eq!(message, "✨ Add login\n\nBody");
+    assert!(has_gitmoji(&message));
+    assert!(has_gitmoji(":bug: Fix crash"));
+    assert!(!has_gitmoji("Fix crash"));
+  }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+use anyhow::{Context, Result};
+use git2::{Commit, Oid};
+use crate::encoding;
+
+/// Renders `commits` (newest first, topologically sorted) like
+/// `git log --graph --oneline`. Parents outside the given commits end their
+/// line, so only the topology of the range itself is drawn.
+pub fn render(commits: &[Commit]) -> Result<Vec<String>> {
+  let in_range = commits.iter().map(Commit::id).collect::<HashSet<Oid>>();
+  let mut columns: Vec<Oid> = Vec::new();
+  let mut lines = Vec::new();
+
+  for commit in commits {
+    let index = match columns.iter().position(|oid| *oid == commit.id()) {
+      Some(index) => index,
+      None => {
+        columns.push(commit.id());
+        columns.len() - 1
+      }
+    };
+
+    let row = (0..columns.len())
+      .map(|n| if n == index { "*" } else { "|" })
+      .collect::<Vec<_>>()
+      .join(" ");
+    lines.push(format!("{}  {} {}", row, short_id(commit)?, encoding::summary(commit)));
+
+    let parents = commit.parent_ids().filter(|oid| in_range.contains(oid)).collect::<Vec<Oid>>();
+    let Some((first, rest)) = parents.split_first() else {
+      columns.remove(index);
+      if index < columns.len() {
+        lines.push(collapse(columns.len() + 1, index, false));
+      }
+      continue;
+    };
+
+    match columns.iter().position(|oid| oid == first) {
+      Some(existing) => {
+        // The first parent already has a line, so this one merges into it
+        let removed = existing.max(index);
+        columns[existing.min(index)] = *first;
+        columns.remove(removed);
+        lines.push(collapse(columns.len() + 1, removed, true));
+      },
+      None => columns[index] = *first
+    }
+
+    let index = columns.iter().position(|oid| oid == first).unwrap_or(index);
+    let branches = rest.iter().filter(|oid| !columns.contains(oid)).copied().collect::<Vec<Oid>>();
+    for (offset, parent) in branches.into_iter().enumerate() {
+      lines.push(expand(columns.len(), index + offset));
+      columns.insert(index + offset + 1, parent);
+    }
+  }
+
+  Ok(lines)
+}
+
+/// Connector drawn when a new line branches off to the right of `index`
+fn expand(width: usize, index: usize) -> String {
+  let mut line = vec![' '; width * 2 + 1];
+  for n in 0..width {
+    line[2 * n + usize::from(n > index)] = if n > index { '\\' } else { '|' };
+  }
+  line[2 * index + 1] = '\\';
+  line.into_iter().collect::<String>().trim_end().to_string()
+}
+
+/// Connector drawn when line `removed` (out of `width`) ends and the lines to
+/// its right shift left. `joins` draws the removed line merging into its neighbour.
+fn collapse(width: usize, removed: usize, joins: bool) -> String {
+  let mut line = vec![' '; width * 2];
+  for n in 0..width {
+    match n {
+      n if n < removed => line[2 * n] = '|',
+      n if n == removed && joins => line[2 * n - 1] = '/',
+      n if n > removed => line[2 * n - 1] = '/',
+      _ => {}
+    }
+  }
+  line.into_iter().collect::<String>().trim_end().to_string()
+}
+
+fn short_id(commit: &Commit) -> Result<String> {
+  let short_id = commit.as_object().short_id().context("Failed to get short id")?;
+  Ok(short_id.as_str().unwrap_or_default().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+  use git2::{Repository, Sort};
+
+  fn walk(repo: &Repository) -> Result<Vec<Commit<'_>>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL)?;
+    revwalk.push_head()?;
+    Ok(revwalk.map(|oid| repo.find_commit(oid.unwrap()).unwrap()).collect())
+  }
+
+  fn strip(lines: Vec<String>) -> Vec<String> {
+    // Drop the SHA so the expected output stays stable
+    lines
+      .into_iter()
+      .map(|line| {
+        match line.split_once("  ") {
+          Some((graph, label)) => format!("{} {}", graph, label.split_once(' ').unwrap().1),
+          None => line
+        }
+      })
+      .collect()
+  }
+
+  #[test]
+  fn test_linear_history() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let lines = strip(render(&walk(&repo)?)?);
+    assert_eq!(lines, ["* Commit 2", "* Commit 1", "* Commit 0"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_merge() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let base = repo.head()?.peel_to_commit()?;
+    commit_file(&repo, "main.txt", "main", "Main work")?;
+    let main = repo.head()?.peel_to_commit()?;
+
+    repo.reset(base.as_object(), git2::ResetType::Hard, None)?;
+    commit_file(&repo, "feature.txt", "feature", "Feature work")?;
+    let feature = repo.head()?.peel_to_commit()?;
+
+    repo.reset(main.as_object(), git2::ResetType::Hard, None)?;
+    let mut index = repo.merge_commits(&main, &feature, None)?;
+    let tree = repo.find_tree(index.write_tree_to(&repo)?)?;
+    let signature = repo.signature()?;
+    repo.commit(Some("HEAD"), &signature, &signature, "Merge feature", &tree, &[&main, &feature])?;
+
+    let lines = strip(render(&walk(&repo)?)?);
+    assert_eq!(lines[0], "* Merge feature");
+    assert_eq!(lines[1], "|\\");
+    assert_eq!(lines.last().unwrap(), "* Commit 0");
+    assert!(lines.contains(&"|/".to_string()));
+    Ok(())
+  }
+}
@@ -0,0 +1,97 @@
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use dialoguer::History;
+use git2::Repository;
+use crate::i18n::t;
+
+const HISTORY_FILE: &str = "squash-history";
+
+/// The last custom messages typed in this repository, newest first, kept in
+/// `.git/squash-history` so the message prompt can recall them with the arrow keys
+#[derive(Clone, Debug)]
+pub struct MessageHistory {
+  path:    PathBuf,
+  entries: VecDeque<String>,
+  limit:   usize
+}
+
+impl MessageHistory {
+  /// Loads at most `limit` messages; a limit of 0 turns the history off
+  pub fn load(repo: &Repository, limit: usize) -> Result<Self> {
+    let path = repo.path().join(HISTORY_FILE);
+    let entries = match std::fs::read_to_string(&path) {
+      Ok(content) => content.lines().filter(|line| !line.is_empty()).take(limit).map(str::to_string).collect(),
+      Err(error) if error.kind() == std::io::ErrorKind::NotFound => VecDeque::new(),
+      Err(error) => return Err(error).with_context(|| format!("Failed to read {}", path.display()))
+    };
+
+    Ok(Self {
+      path,
+      entries,
+      limit
+    })
+  }
+
+  fn save(&self) -> Result<()> {
+    let mut content = self.entries.iter().map(String::as_str).collect::<Vec<_>>().join("\n");
+    content.push('\n');
+    std::fs::write(&self.path, content).with_context(|| format!("Failed to write {}", self.path.display()))
+  }
+}
+
+impl History<String> for MessageHistory {
+  fn read(&self, pos: usize) -> Option<String> {
+    self.entries.get(pos).cloned()
+  }
+
+  /// Moves `message` to the front, dropping older duplicates and whatever
+  /// falls beyond the limit
+  fn write(&mut self, message: &String) {
+    if self.limit == 0 || message.trim().is_empty() {
+      return;
+    }
+
+    self.entries.retain(|entry| entry != message);
+    self.entries.push_front(message.clone());
+    self.entries.truncate(self.limit);
+
+    // Losing the history is no reason to fail the squash
+    if let Err(error) = self.save() {
+      eprintln!("{}", t!("warning", error = format!("{:#}", error)));
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_remembers_recent_messages() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let mut history = MessageHistory::load(&repo, 2)?;
+    assert_eq!(history.read(0), None);
+
+    for message in ["First", "Second", "First", "Third"] {
+      history.write(&message.to_string());
+    }
+
+    let history = MessageHistory::load(&repo, 2)?;
+    assert_eq!(history.read(0).as_deref(), Some("Third"));
+    assert_eq!(history.read(1).as_deref(), Some("First"));
+    assert_eq!(history.read(2), None);
+    Ok(())
+  }
+
+  #[test]
+  fn test_disabled_history() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let mut history = MessageHistory::load(&repo, 0)?;
+    history.write(&"Message".to_string());
+
+    assert!(!repo.path().join(HISTORY_FILE).exists());
+    Ok(())
+  }
+}
@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{bail, Context, Result};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use crate::i18n::t;
+
+/// Commands configured under `[hooks]`, run through `sh -c` instead of the
+/// scripts in `.git/hooks`
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hooks {
+  pub pre_squash:  Option<String>,
+  pub post_squash: Option<String>
+}
+
+impl Hooks {
+  /// Values set in `other` take precedence over the ones in `self`
+  pub fn merge(self, other: Self) -> Self {
+    Self {
+      pre_squash: other.pre_squash.or(self.pre_squash), post_squash: other.post_squash.or(self.post_squash)
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hook {
+  /// Runs before the reset; a non-zero exit aborts the squash
+  PreSquash,
+  /// Runs after the squash commit has been created
+  PostSquash
+}
+
+impl Hook {
+  pub fn name(&self) -> &'static str {
+    match self {
+      Hook::PreSquash => "pre-squash",
+      Hook::PostSquash => "post-squash"
+    }
+  }
+
+  pub(crate) fn command<'a>(&self, hooks: &'a Hooks) -> Option<&'a str> {
+    match self {
+      Hook::PreSquash => hooks.pre_squash.as_deref(),
+      Hook::PostSquash => hooks.post_squash.as_deref()
+    }
+  }
+}
+
+/// What the hook gets to see, passed as `GIT_SQUASH_*` environment variables
+pub struct HookEnv<'a> {
+  /// The commit the squash is put on top of, `None` when squashing down to the root
+  pub base:     Option<Oid>,
+  /// The newest squashed commit, `old_head` unless a range ending below
+  /// `HEAD` is squashed
+  pub tip:      Oid,
+  pub old_head: Oid,
+  pub new_head: Option<Oid>,
+  pub count:    usize,
+  pub message:  &'a str
+}
+
+/// Runs `hook` if configured or present in the hooks directory, returning
+/// whether there was one. Fails when the hook exits non-zero.
+pub fn run(repo: &Repository, hooks: &Hooks, hook: Hook, env: &HookEnv) -> Result<bool> {
+  let mut command = match (hook.command(hooks), discover(repo, hook)) {
+    (Some(script), _) => {
+      let mut command = Command::new("sh");
+      command.arg("-c").arg(script);
+      command
+    },
+    (None, Some(path)) => Command::new(path),
+    (None, None) => return Ok(false)
+  };
+
+  command
+    .current_dir(repo.workdir().unwrap_or(repo.path()))
+    .env("GIT_SQUASH_RANGE", match env.base {
+      Some(base) => format!("{}..{}", base, env.tip),
+      None => env.tip.to_string()
+    })
+    .env("GIT_SQUASH_BASE", env.base.map(|base| base.to_string()).unwrap_or_default())
+    .env("GIT_SQUASH_OLD_HEAD", env.old_head.to_string())
+    .env("GIT_SQUASH_COUNT", env.count.to_string())
+    .env("GIT_SQUASH_MESSAGE", env.message);
+
+  if let Some(new_head) = env.new_head {
+    command.env("GIT_SQUASH_NEW_HEAD", new_head.to_string());
+  }
+
+  let status = command.status().with_context(|| format!("Failed to run {} hook", hook.name()))?;
+  if !status.success() {
+    bail!(t!("hook-failed", hook = hook.name(), status = status));
+  }
+
+  Ok(true)
+}
+
+/// Where git looks for hooks: `core.hooksPath`, as set up by husky or
+/// lefthook, relative to the working tree like git resolves it, or else
+/// `hooks` in the git directory shared by all worktrees
+pub fn directory(repo: &Repository) -> PathBuf {
+  let configured = repo.config().ok().and_then(|config| config.get_path("core.hooksPath").ok());
+  match configured {
+    Some(path) if path.is_relative() => repo.workdir().unwrap_or(repo.path()).join(path),
+    Some(path) => path,
+    None => common_dir(repo).join("hooks")
+  }
+}
+
+/// A linked worktree's git directory names the shared one in `commondir`
+pub(crate) fn common_dir(repo: &Repository) -> PathBuf {
+  match std::fs::read_to_string(repo.path().join("commondir")) {
+    Ok(common) => repo.path().join(common.trim()),
+    Err(_) => repo.path().to_path_buf()
+  }
+}
+
+/// What `run` would run for `hook`: the configured command or the hook's path
+pub fn describe(repo: &Repository, hooks: &Hooks, hook: Hook) -> Option<String> {
+  match (hook.command(hooks), discover(repo, hook)) {
+    (Some(script), _) => Some(format!("`{}`", script)),
+    (None, Some(path)) => Some(path.display().to_string()),
+    (None, None) => None
+  }
+}
+
+fn discover(repo: &Repository, hook: Hook) -> Option<PathBuf> {
+  let path = directory(repo).join(hook.name());
+  is_executable(&path).then_some(path)
+}
+
+#[cfg(unix)]
+pub(crate) fn is_executable(path: &Path) -> bool {
+  use std::os::unix::fs::PermissionsExt;
+  path
+    .metadata()
+    .map(|meta| meta.is_file() && meta.permissions().mode() & 0o111 != 0)
+    .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn is_executable(path: &Path) -> bool {
+  path.is_file()
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+  use std::os::unix::fs::PermissionsExt;
+  use crate::testing::repo_with_commits;
+
+  fn env(repo: &Repository) -> Result<HookEnv<'static>> {
+    let head = repo.head()?.peel_to_commit()?;
+    Ok(HookEnv {
+      base:     Some(head.parent_id(0)?),
+      tip:      head.id(),
+      old_head: head.id(),
+      new_head: None,
+      count:    1,
+      message:  "Squashed"
+    })
+  }
+
+  #[test]
+  fn test_discovered_hook_receives_env() -> Result<()> {
+    let (dir, repo) = repo_with_commits(2)?;
+    let hook = repo.path().join("hooks").join("pre-squash");
+    std::fs::create_dir_all(hook.parent().unwrap())?;
+    std::fs::write(&hook, "#!/bin/sh\necho \"$GIT_SQUASH_COUNT $GIT_SQUASH_MESSAGE\" > hook.out\n")?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+    assert!(run(&repo, &Hooks::default(), Hook::PreSquash, &env(&repo)?)?);
+    assert_eq!(std::fs::read_to_string(dir.path().join("hook.out"))?, "1 Squashed\n");
+    Ok(())
+  }
+
+  #[test]
+  fn test_hooks_path() -> Result<()> {
+    let (dir, repo) = repo_with_commits(2)?;
+    assert_eq!(directory(&repo), repo.path().join("hooks"));
+
+    repo.config()?.set_str("core.hooksPath", ".husky")?;
+    assert_eq!(directory(&repo), dir.path().join(".husky"));
+
+    let hook = dir.path().join(".husky").join("pre-squash");
+    std::fs::create_dir_all(hook.parent().unwrap())?;
+    std::fs::write(
+      &hook, "#!/bin/sh
+touch hook.out
+"
+    )?;
+    std::fs::set_permissions(&hook, std::fs::Permissions::from_mode(0o755))?;
+
+    assert!(run(&repo, &Hooks::default(), Hook::PreSquash, &env(&repo)?)?);
+    assert!(dir.path().join("hook.out").exists());
+    Ok(())
+  }
+
+  #[test]
+  fn test_failing_configured_hook_aborts() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let hooks = Hooks {
+      pre_squash: Some("exit 3".to_string()), post_squash: None
+    };
+
+    let error = run(&repo, &hooks, Hook::PreSquash, &env(&repo)?).unwrap_err();
+    assert!(error.to_string().contains("pre-squash"));
+    assert!(!run(&repo, &hooks, Hook::PostSquash, &env(&repo)?)?);
+    Ok(())
+  }
+}
@@ -0,0 +1,138 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::sync::{OnceLock, RwLock};
+
+/// Languages the prompts and messages are available in
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+  #[default]
+  English,
+  Swedish
+}
+
+static CURRENT: RwLock<Locale> = RwLock::new(Locale::English);
+
+impl Locale {
+  /// Parses a POSIX locale like `sv_SE.UTF-8` or a bare language code
+  pub fn parse(name: &str) -> Option<Self> {
+    let language = name.split(['_', '-', '.', '@']).next().unwrap_or_default().to_lowercase();
+    match language.as_str() {
+      "en" | "c" | "posix" => Some(Self::English),
+      "sv" => Some(Self::Swedish),
+      _ => None
+    }
+  }
+
+  /// The locale picked by `LC_ALL`, `LC_MESSAGES` or `LANG`, in that order
+  pub fn from_env() -> Self {
+    ["LC_ALL", "LC_MESSAGES", "LANG"]
+      .iter()
+      .filter_map(|key| std::env::var(key).ok())
+      .find(|value| !value.is_empty())
+      .and_then(|value| Self::parse(&value))
+      .unwrap_or_default()
+  }
+
+  fn catalog(self) -> &'static HashMap<String, String> {
+    static ENGLISH: OnceLock<HashMap<String, String>> = OnceLock::new();
+    static SWEDISH: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+    match self {
+      Self::English => ENGLISH.get_or_init(|| parse(include_str!("../locales/en.ftl"))),
+      Self::Swedish => SWEDISH.get_or_init(|| parse(include_str!("../locales/sv.ftl")))
+    }
+  }
+}
+
+/// Switches the language of everything printed from here on
+pub fn set(locale: Locale) {
+  *CURRENT.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = locale;
+}
+
+/// The message `id` in the current locale with its `{ $name }` placeables
+/// filled in from `args`. Untranslated messages fall back to English.
+pub fn message(id: &str, args: &[(&str, &dyn Display)]) -> String {
+  let locale = *CURRENT.read().unwrap_or_else(|poisoned| poisoned.into_inner());
+  let Some(pattern) = locale.catalog().get(id).or_else(|| Locale::English.catalog().get(id)) else {
+    return id.to_string();
+  };
+
+  args
+    .iter()
+    .fold(pattern.clone(), |text, (name, value)| text.replace(&format!("{{ ${} }}", name), &value.to_string()))
+}
+
+/// Looks up a message in the current locale, e.g. `t!("squashed", count = 3)`
+#[macro_export]
+macro_rules! t {
+  ($id:literal) => {
+    $crate::i18n::message($id, &[])
+  };
+  ($id:literal, $($name:ident = $value:expr),+ $(,)?) => {
+    $crate::i18n::message($id, &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+])
+  };
+}
+pub use t;
+
+/// Reads the subset of Fluent used by the catalogs: `id = value` entries,
+/// indented continuation lines and `#` comments
+fn parse(source: &str) -> HashMap<String, String> {
+  let mut messages = HashMap::new();
+  let mut current: Option<(String, String)> = None;
+
+  for line in source.lines() {
+    if line.starts_with(' ') && !line.trim().is_empty() {
+      if let Some((_, value)) = &mut current {
+        value.push('\n');
+        value.push_str(line.trim());
+      }
+      continue;
+    }
+
+    messages.extend(current.take());
+    if let Some((id, value)) = line.split_once('=').filter(|_| !line.starts_with('#')) {
+      current = Some((id.trim().to_string(), value.trim().to_string()));
+    }
+  }
+
+  messages.extend(current);
+  messages
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_parse_locale() {
+    assert_eq!(Locale::parse("sv_SE.UTF-8"), Some(Locale::Swedish));
+    assert_eq!(Locale::parse("en-US"), Some(Locale::English));
+    assert_eq!(Locale::parse("C"), Some(Locale::English));
+    assert_eq!(Locale::parse("xx_XX"), None);
+  }
+
+  #[test]
+  fn test_catalogs_are_complete() {
+    let english = Locale::English.catalog();
+    let swedish = Locale::Swedish.catalog();
+
+    let mut missing = english.keys().filter(|id| !swedish.contains_key(*id)).collect::<Vec<_>>();
+    missing.sort();
+    assert!(missing.is_empty(), "missing Swedish translations: {:?}", missing);
+
+    for (id, pattern) in swedish {
+      let english = english.get(id).unwrap_or_else(|| panic!("{} is not an English message", id));
+      let placeables = |text: &str| text.matches("{ $").count();
+      assert_eq!(placeables(pattern), placeables(english), "placeables of {} differ", id);
+    }
+  }
+
+  #[test]
+  fn test_parse_catalog() {
+    let messages = parse("# comment\nhello = Hello { $name }\nlong = First line\n    second line\n\nother = x");
+    assert_eq!(messages["hello"], "Hello { $name }");
+    assert_eq!(messages["long"], "First line\nsecond line");
+    assert_eq!(messages["other"], "x");
+    assert_eq!(message("squashed", &[("count", &3)]), "Squashed 3 commits");
+  }
+}
@@ -0,0 +1,173 @@
+use std::fmt;
+use std::sync::RwLock;
+use anyhow::{Context, Result};
+use git2::{Config, Oid, Repository, Signature, Time};
+use crate::dates;
+
+static CHOSEN_AUTHOR: RwLock<Option<Identity>> = RwLock::new(None);
+
+/// Whose identity a signature records
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+  Author,
+  Committer
+}
+
+impl Role {
+  fn name(self) -> &'static str {
+    match self {
+      Role::Author => "author",
+      Role::Committer => "committer"
+    }
+  }
+}
+
+/// A name and email, shown as `Name <email>` like in trailers
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Identity {
+  pub name:  String,
+  pub email: String
+}
+
+impl Identity {
+  pub fn of(signature: &Signature) -> Self {
+    Self {
+      name: signature.name().unwrap_or_default().to_string(), email: signature.email().unwrap_or_default().to_string()
+    }
+  }
+}
+
+impl fmt::Display for Identity {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} <{}>", self.name, self.email)
+  }
+}
+
+/// Makes `author` the author of every commit created from here on, over what
+/// git config and `GIT_AUTHOR_*` say, or goes back to those with `None`
+pub fn set_author(author: Option<Identity>) {
+  *CHOSEN_AUTHOR.write().unwrap_or_else(|poisoned| poisoned.into_inner()) = author;
+}
+
+pub fn chosen_author() -> Option<Identity> {
+  CHOSEN_AUTHOR.read().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+}
+
+/// The distinct authors of `commits` after `.mailmap`, in the order they
+/// first appear, with how many of the commits each wrote
+pub fn authors(repo: &Repository, commits: &[Oid]) -> Result<Vec<(Identity, usize)>> {
+  let mailmap = repo.mailmap().context("Failed to load mailmap")?;
+  let mut authors: Vec<(Identity, usize)> = Vec::new();
+  for oid in commits {
+    let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+    let author = Identity::of(&commit.author_with_mailmap(&mailmap).context("Failed to resolve author")?);
+    match authors.iter_mut().find(|(known, _)| *known == author) {
+      Some((_, count)) => *count += 1,
+      None => authors.push((author, 1))
+    }
+  }
+
+  Ok(authors)
+}
+
+/// The author git would record for a new commit, unless another one was chosen
+pub fn author(repo: &Repository) -> Result<Signature<'static>> {
+  signature(repo, Role::Author)
+}
+
+/// The committer git would record for a new commit, which also goes into
+/// reflogs, notes and tags
+pub fn committer(repo: &Repository) -> Result<Signature<'static>> {
+  signature(repo, Role::Committer)
+}
+
+pub fn signature(repo: &Repository, role: Role) -> Result<Signature<'static>> {
+  let config = repo.config().context("Failed to get config")?;
+  let chosen = chosen_author().filter(|_| role == Role::Author);
+  resolve(&config, role, |name| {
+    match (&chosen, name) {
+      (Some(chosen), "GIT_AUTHOR_NAME") => Some(chosen.name.clone()),
+      (Some(chosen), "GIT_AUTHOR_EMAIL") => Some(chosen.email.clone()),
+      _ => std::env::var(name).ok()
+    }
+  })
+}
+
+/// Picks name, email and date with git's precedence: `GIT_AUTHOR_NAME` and
+/// friends, then `author.name` or `committer.name`, then `user.name`, with
+/// `EMAIL` as a last resort for the address
+fn resolve(config: &Config, role: Role, env: impl Fn(&str) -> Option<String>) -> Result<Signature<'static>> {
+  let role_name = role.name();
+  let var = |field: &str| env(&format!("GIT_{}_{}", role_name.to_uppercase(), field)).filter(|value| !value.is_empty());
+  let get = |key: &str| config.get_string(key).ok().filter(|value| !value.is_empty());
+
+  let name = var("NAME")
+    .or_else(|| get(&format!("{}.name", role_name)))
+    .or_else(|| get("user.name"))
+    .with_context(|| format!("No {} name configured, set user.name", role_name))?;
+  let email = var("EMAIL")
+    .or_else(|| get(&format!("{}.email", role_name)))
+    .or_else(|| get("user.email"))
+    .or_else(|| env("EMAIL").filter(|value| !value.is_empty()))
+    .with_context(|| format!("No {} email configured, set user.email", role_name))?;
+
+  let signature = match var("DATE") {
+    Some(date) => {
+      let time: Time =
+        dates::parse(&date).with_context(|| format!("Invalid GIT_{}_DATE `{}`", role_name.to_uppercase(), date))?;
+      Signature::new(&name, &email, &time)
+    },
+    None => Signature::now(&name, &email)
+  };
+  signature.with_context(|| format!("Failed to create {} signature", role_name))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::collections::HashMap;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_precedence() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(0)?;
+    let mut config = repo.config()?.open_level(git2::ConfigLevel::Local)?;
+    config.set_str("user.name", "User")?;
+    config.set_str("user.email", "user@example.com")?;
+    config.set_str("committer.name", "Committer")?;
+
+    let env = HashMap::from([
+      ("GIT_AUTHOR_NAME", "Env Author"),
+      ("GIT_AUTHOR_DATE", "@1714738920 +0200"),
+      ("GIT_COMMITTER_EMAIL", "ci@example.com")
+    ]);
+    let lookup = |name: &str| env.get(name).map(|value| value.to_string());
+
+    let author = resolve(&config, Role::Author, lookup)?;
+    assert_eq!((author.name(), author.email()), (Some("Env Author"), Some("user@example.com")));
+    assert_eq!(author.when(), Time::new(1_714_738_920, 120));
+
+    let committer = resolve(&config, Role::Committer, lookup)?;
+    assert_eq!((committer.name(), committer.email()), (Some("Committer"), Some("ci@example.com")));
+
+    let invalid = |name: &str| (name == "GIT_AUTHOR_DATE").then(|| "soon".to_string());
+    assert!(resolve(&config, Role::Author, invalid).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_authors() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(0)?;
+    let mut commits = Vec::new();
+    for (name, file) in [("Ada", "a"), ("Linus", "b"), ("Linus", "c")] {
+      repo.config()?.set_str("user.name", name)?;
+      repo.config()?.set_str("user.email", &format!("{}@example.com", name.to_lowercase()))?;
+      commits.push(crate::testing::commit_file(&repo, file, file, file)?);
+    }
+
+    let authors = authors(&repo, &commits)?;
+    let authors = authors.iter().map(|(author, count)| (author.to_string(), *count)).collect::<Vec<_>>();
+    assert_eq!(authors, [("Ada <ada@example.com>".to_string(), 1), ("Linus <linus@example.com>".to_string(), 2)]);
+    Ok(())
+  }
+}
@@ -0,0 +1,213 @@
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use git2::Config;
+use crate::i18n::t;
+use crate::SquashError;
+
+/// What git runs for `git squash`: any `git-squash` on `PATH`, which wins
+/// over an alias
+pub const EXECUTABLE: &str = if cfg!(windows) { "git-squash.exe" } else { "git-squash" };
+
+/// What installing has to do to make `git squash` run this binary
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Step {
+  /// Nothing, it already does
+  Installed(PathBuf),
+  /// Put a `git-squash` link to `target` at `link`
+  Link { link: PathBuf, target: PathBuf },
+  /// Set the global `alias.squash` to `command`
+  Alias { command: String }
+}
+
+impl Step {
+  /// Carries out the step, with `global` the path of the global git config
+  pub fn apply(&self, global: &Path) -> Result<()> {
+    match self {
+      Step::Installed(_) => Ok(()),
+      Step::Link {
+        link,
+        target
+      } => {
+        if let Some(dir) = link.parent() {
+          std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        make_link(target, link).with_context(|| format!("Failed to link {}", link.display()))
+      },
+      Step::Alias {
+        command
+      } => {
+        let mut config = Config::open(global).with_context(|| format!("Failed to open {}", global.display()))?;
+        config.set_str("alias.squash", command).context("Failed to set alias.squash")
+      }
+    }
+  }
+
+  /// What was done, or with `dry_run` what would be done
+  pub fn describe(&self, dry_run: bool) -> String {
+    match self {
+      Step::Installed(path) => t!("install-already", path = path.display()),
+      Step::Link {
+        link,
+        target
+      } => {
+        match dry_run {
+          true => t!("install-would-link", link = link.display(), target = target.display()),
+          false => t!("install-linked", link = link.display(), target = target.display())
+        }
+      },
+      Step::Alias {
+        command
+      } => {
+        match dry_run {
+          true => t!("install-would-alias", command = command),
+          false => t!("install-aliased", command = command)
+        }
+      },
+    }
+  }
+}
+
+/// Plans a `git-squash` link to `exe` in `dir`, refusing to replace another
+/// file there or to install behind a different `git-squash` earlier on `path`
+pub fn link(exe: &Path, dir: &Path, path: &OsStr) -> Result<Step> {
+  match find_before(path, Some(dir)) {
+    Some(found) if same_file(&found, exe) => return Ok(Step::Installed(found)),
+    Some(found) => bail!(SquashError::Refused(t!("install-shadowed", path = found.display()))),
+    None => {}
+  }
+
+  let link = dir.join(EXECUTABLE);
+  match link.symlink_metadata() {
+    Ok(_) if same_file(&link, exe) => Ok(Step::Installed(link)),
+    Ok(_) => bail!(SquashError::Refused(t!("install-link-exists", path = link.display()))),
+    Err(_) => {
+      Ok(Step::Link {
+        link,
+        target: exe.to_path_buf()
+      })
+    },
+  }
+}
+
+/// Plans pointing `alias.squash` at `exe`, refusing to replace an alias
+/// that runs something else or one a `git-squash` on `path` would win over
+pub fn alias(exe: &Path, config: &Config, path: &OsStr) -> Result<Step> {
+  if let Some(found) = find_before(path, None) {
+    return match same_file(&found, exe) {
+      true => Ok(Step::Installed(found)),
+      false => bail!(SquashError::Refused(t!("install-shadowed", path = found.display())))
+    };
+  }
+
+  let command = format!("!\"{}\"", exe.display());
+  match config.get_string("alias.squash") {
+    Ok(existing) if existing == command => Ok(Step::Installed(exe.to_path_buf())),
+    Ok(existing) => bail!(SquashError::Refused(t!("install-alias-exists", alias = existing))),
+    Err(_) => {
+      Ok(Step::Alias {
+        command
+      })
+    },
+  }
+}
+
+/// Where links go unless another directory is given
+pub fn default_dir() -> Result<PathBuf> {
+  Ok(home()?.join(".local").join("bin"))
+}
+
+/// The global git config, which may not exist yet
+pub fn global_config() -> Result<PathBuf> {
+  match Config::find_global() {
+    Ok(path) => Ok(path),
+    Err(_) => Ok(home()?.join(".gitconfig"))
+  }
+}
+
+fn home() -> Result<PathBuf> {
+  let home = std::env::var_os("HOME")
+    .or_else(|| std::env::var_os("USERPROFILE"))
+    .context("HOME is not set")?;
+  Ok(PathBuf::from(home))
+}
+
+/// Whether `dir` is one of the directories in `path`
+pub fn on_path(dir: &Path, path: &OsStr) -> bool {
+  std::env::split_paths(path).any(|entry| same_file(&entry, dir))
+}
+
+/// The first `git-squash` on `path`, only looking at the directories
+/// before `dir` if given
+fn find_before(path: &OsStr, dir: Option<&Path>) -> Option<PathBuf> {
+  std::env::split_paths(path)
+    .take_while(|entry| dir.is_none_or(|dir| !same_file(entry, dir)))
+    .map(|entry| entry.join(EXECUTABLE))
+    .find(|candidate| candidate.is_file())
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+  match (a.canonicalize(), b.canonicalize()) {
+    (Ok(a), Ok(b)) => a == b,
+    _ => false
+  }
+}
+
+#[cfg(unix)]
+fn make_link(target: &Path, link: &Path) -> std::io::Result<()> {
+  std::os::unix::fs::symlink(target, link)
+}
+
+/// Symlinks need extra privileges on Windows, so the binary is copied instead
+#[cfg(not(unix))]
+fn make_link(target: &Path, link: &Path) -> std::io::Result<()> {
+  std::fs::copy(target, link).map(|_| ())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+  use super::*;
+  use tempdir::TempDir;
+
+  #[test]
+  fn test_link() -> Result<()> {
+    let dir = TempDir::new("install")?;
+    let exe = dir.path().join("target").join("git-squash");
+    std::fs::create_dir_all(exe.parent().unwrap())?;
+    std::fs::write(&exe, "")?;
+    let bin = dir.path().join("bin");
+
+    let step = link(&exe, &bin, OsStr::new(""))?;
+    assert_eq!(step, Step::Link {
+      link: bin.join("git-squash"), target: exe.clone()
+    });
+    step.apply(&dir.path().join("gitconfig"))?;
+    assert_eq!(link(&exe, &bin, OsStr::new(""))?, Step::Installed(bin.join("git-squash")));
+
+    // Another git-squash earlier on PATH would run instead
+    let other = dir.path().join("other");
+    std::fs::create_dir_all(&other)?;
+    std::fs::write(other.join("git-squash"), "")?;
+    assert!(link(&exe, &bin, other.as_os_str()).is_err());
+    assert!(link(&exe, &other, OsStr::new("")).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_alias() -> Result<()> {
+    let dir = TempDir::new("install")?;
+    let exe = dir.path().join("git-squash");
+    std::fs::write(&exe, "")?;
+    let global = dir.path().join("gitconfig");
+    let mut config = Config::open(&global)?;
+
+    let step = alias(&exe, &config, OsStr::new(""))?;
+    step.apply(&global)?;
+    assert_eq!(config.get_string("alias.squash")?, format!("!\"{}\"", exe.display()));
+    assert_eq!(alias(&exe, &config, OsStr::new(""))?, Step::Installed(exe.clone()));
+
+    config.set_str("alias.squash", "rebase -i")?;
+    assert!(alias(&exe, &config, OsStr::new("")).is_err());
+    Ok(())
+  }
+}
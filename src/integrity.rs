@@ -0,0 +1,77 @@
+use anyhow::{bail, Context, Result};
+use git2::{Oid, Repository, Status, StatusOptions};
+use crate::i18n::t;
+
+/// What a squash must leave unchanged: the content it commits and the
+/// working directory
+pub struct Snapshot {
+  /// The tree of the index, which is what the squash commit should contain
+  tree:     Oid,
+  worktree: Vec<(String, Status)>
+}
+
+impl Snapshot {
+  pub fn capture(repo: &Repository) -> Result<Self> {
+    let tree = repo
+      .index()
+      .context("Failed to get index")?
+      .write_tree()
+      .context("Failed to write tree")?;
+    Ok(Self {
+      tree,
+      worktree: worktree_changes(repo)?
+    })
+  }
+
+  /// Fails when `new_head` doesn't contain exactly the captured tree or the
+  /// working directory changed relative to the index
+  pub fn verify(&self, repo: &Repository, new_head: Oid) -> Result<()> {
+    let commit = repo.find_commit(new_head).context("Failed to find new HEAD")?;
+    if commit.tree_id() != self.tree {
+      bail!(t!("integrity-tree", actual = commit.tree_id(), expected = self.tree));
+    }
+
+    if worktree_changes(repo)? != self.worktree {
+      bail!(t!("integrity-worktree"));
+    }
+
+    Ok(())
+  }
+}
+
+/// Unstaged changes and untracked files, which a soft reset must not touch
+fn worktree_changes(repo: &Repository) -> Result<Vec<(String, Status)>> {
+  let mut options = StatusOptions::new();
+  options.include_untracked(true).include_ignored(false);
+
+  let statuses = repo.statuses(Some(&mut options)).context("Failed to get status")?;
+  let worktree = Status::WT_NEW | Status::WT_MODIFIED | Status::WT_DELETED | Status::WT_TYPECHANGE | Status::WT_RENAMED;
+  Ok(
+    statuses
+      .iter()
+      .filter(|entry| entry.status().intersects(worktree))
+      .map(|entry| (entry.path().unwrap_or_default().to_string(), entry.status() & worktree))
+      .collect()
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_detects_tree_and_worktree_changes() -> Result<()> {
+    let (dir, repo) = repo_with_commits(2)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let snapshot = Snapshot::capture(&repo)?;
+
+    snapshot.verify(&repo, head.id())?;
+    assert!(snapshot.verify(&repo, head.parent_id(0)?).is_err());
+
+    std::fs::write(dir.path().join("1.txt"), "changed")?;
+    let error = snapshot.verify(&repo, head.id()).unwrap_err();
+    assert!(error.to_string().contains("working directory"));
+    Ok(())
+  }
+}
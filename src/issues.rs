@@ -0,0 +1,88 @@
+use std::collections::BTreeSet;
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use regex::Regex;
+use crate::encoding;
+use crate::trailers;
+
+const REFERENCE: &str = r"(?:[\w.-]+/[\w.-]+)?#\d+|\bGH-\d+";
+const CLOSING: &str = r"(?i)\b(close[sd]?|fix(?:e[sd])?|resolve[sd]?):?\s+((?:[\w.-]+/[\w.-]+)?#\d+|GH-\d+)";
+
+/// Appends the issue references of the squashed commits that the final
+/// message lacks, so auto-closing keywords and links survive the squash.
+/// Closing references become `Fixes:`/`Closes:`/`Resolves:` trailers and
+/// the remaining ones are listed in a single `Refs:` trailer.
+pub fn collect(repo: &Repository, range: &[Oid], message: &str) -> Result<String> {
+  let reference = Regex::new(REFERENCE).expect("valid regex");
+  let closing = Regex::new(CLOSING).expect("valid regex");
+
+  let mentioned = reference
+    .find_iter(message)
+    .map(|found| found.as_str().to_string())
+    .collect::<BTreeSet<_>>();
+  let closed = closing
+    .captures_iter(message)
+    .map(|captures| captures[2].to_string())
+    .collect::<BTreeSet<_>>();
+
+  let mut closes = Vec::new();
+  let mut refs = Vec::new();
+  for oid in range.iter().rev() {
+    let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+    let text = &encoding::message(&commit);
+
+    for captures in closing.captures_iter(text) {
+      let entry = (keyword(&captures[1]), captures[2].to_string());
+      if !closed.contains(&entry.1) && !closes.contains(&entry) {
+        closes.push(entry);
+      }
+    }
+
+    for found in reference.find_iter(text).map(|found| found.as_str().to_string()) {
+      if !mentioned.contains(&found) && !refs.contains(&found) {
+        refs.push(found);
+      }
+    }
+  }
+
+  // A reference that gets closed anywhere in the range only needs its closing trailer
+  refs.retain(|found| !closed.contains(found) && !closes.iter().any(|(_, issue)| issue == found));
+
+  let mut message = message.to_string();
+  for (keyword, issue) in closes {
+    message = trailers::append(&message, keyword, &issue)?;
+  }
+  if !refs.is_empty() {
+    message = trailers::append(&message, "Refs", &refs.join(", "))?;
+  }
+
+  Ok(message)
+}
+
+fn keyword(word: &str) -> &'static str {
+  match word.to_lowercase() {
+    word if word.starts_with("close") => "Closes",
+    word if word.starts_with("fix") => "Fixes",
+    _ => "Resolves"
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_collects_references() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "a.txt", "a", "Start parser, see #12 and GH-7")?;
+    commit_file(&repo, "b.txt", "b", "Finish parser\n\nfixes #12\nCloses: org/repo#3")?;
+    commit_file(&repo, "c.txt", "c", "Tweak, refs #40")?;
+    let head = repo.head()?.peel_to_commit()?;
+    let range = [head.id(), head.parent_id(0)?, head.parent(0)?.parent_id(0)?];
+
+    let message = collect(&repo, &range, "Add parser (#40)")?;
+    assert_eq!(message, "Add parser (#40)\n\nFixes: #12\nCloses: org/repo#3\nRefs: GH-7");
+    Ok(())
+  }
+}
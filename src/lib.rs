@@ -0,0 +1,67 @@
+//! Squash the last commits of a branch into one, choosing the message from
+//! the squashed commits or writing a new one. The `git-squash` binary is a
+//! thin CLI over this library, which editor plugins and bots can embed.
+
+pub mod squash;
+pub mod actions;
+pub mod format;
+pub mod error;
+pub mod backend;
+pub mod events;
+pub mod transaction;
+pub mod reflog;
+pub mod retry;
+pub mod lock;
+pub mod install;
+pub mod config;
+pub mod sign;
+pub mod identity;
+pub mod policy;
+pub mod audit;
+pub mod hooks;
+pub mod generate;
+pub mod rebase;
+pub mod pager;
+pub mod graph;
+pub mod diff;
+pub mod email;
+pub mod explain;
+pub mod tags;
+pub mod branches;
+pub mod upstream;
+pub mod integrity;
+pub mod notes;
+pub mod trailers;
+pub mod issues;
+pub mod ticket;
+pub mod conventional;
+pub mod gitmoji;
+pub mod lint;
+pub mod editor;
+pub mod wrap;
+pub mod i18n;
+pub mod encoding;
+pub mod color;
+pub mod prompt;
+pub mod dates;
+pub mod history;
+pub mod clipboard;
+pub mod forge;
+pub mod github;
+pub mod gitlab;
+pub mod description;
+pub mod batch;
+pub mod wip;
+pub mod prepush;
+pub mod analyze;
+pub mod doctor;
+pub mod changelog;
+pub mod range;
+pub mod state;
+pub mod finish;
+#[cfg(test)]
+mod testing;
+
+pub use error::{ExitStatus, SquashError};
+pub use events::{Event, Observer};
+pub use squash::{squash, squash_with, MessageStrategy, SquashOptions, SquashOutcome};
@@ -0,0 +1,46 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{bail, Context, Result};
+use git2::Repository;
+
+/// Pipes `message` into an external linter (e.g. `commitlint`) and fails
+/// with whatever it printed when it exits non-zero
+pub fn run(repo: &Repository, command: &str, message: &str) -> Result<()> {
+  let mut child = Command::new("sh")
+    .arg("-c")
+    .arg(command)
+    .current_dir(repo.workdir().unwrap_or(repo.path()))
+    .stdin(Stdio::piped())
+    .stdout(Stdio::piped())
+    .stderr(Stdio::piped())
+    .spawn()
+    .with_context(|| format!("Failed to run `{}`", command))?;
+
+  // The linter may exit without reading everything, which is fine
+  let _ = child.stdin.take().expect("stdin is piped").write_all(message.as_bytes());
+
+  let output = child.wait_with_output().with_context(|| format!("Failed to run `{}`", command))?;
+  if !output.status.success() {
+    let report = [output.stdout, output.stderr].concat();
+    bail!("Message rejected by `{}`:\n{}", command, String::from_utf8_lossy(&report).trim_end());
+  }
+
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_reports_linter_output() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let linter = "grep -q '^feat' || { echo 'subject must start with feat' >&2; exit 1; }";
+
+    run(&repo, linter, "feat: add lists")?;
+    let error = run(&repo, linter, "add lists").unwrap_err();
+    assert!(error.to_string().contains("subject must start with feat"));
+    Ok(())
+  }
+}
@@ -0,0 +1,100 @@
+use std::fs::OpenOptions;
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use anyhow::{bail, Context, Result};
+use git2::Repository;
+use crate::SquashError;
+use crate::i18n::t;
+
+const LOCK_FILE: &str = "squash.lock";
+
+/// Path of the lock held by this process, so the Ctrl-C handler can release
+/// it even though `process::exit` skips destructors
+static HELD: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Advisory lock under `.git/` preventing two `git-squash` invocations from
+/// operating on the same repository at once. Released on drop.
+pub struct OperationLock {
+  path:  PathBuf,
+  /// Whether this is the outermost lock, which removes the lock file. The
+  /// CLI holds the lock around library calls that take it again.
+  owned: bool
+}
+
+impl OperationLock {
+  pub fn acquire(repo: &Repository) -> Result<Self> {
+    let path = repo.path().join(LOCK_FILE);
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    if held.as_ref() == Some(&path) {
+      return Ok(Self {
+        path,
+        owned: false
+      });
+    }
+
+    match OpenOptions::new().write(true).create_new(true).open(&path) {
+      Ok(mut file) => {
+        writeln!(file, "{}", std::process::id()).context("Failed to write lock file")?;
+        *held = Some(path.clone());
+        Ok(Self {
+          path,
+          owned: true
+        })
+      },
+      Err(error) if error.kind() == ErrorKind::AlreadyExists => {
+        let owner = std::fs::read_to_string(&path).unwrap_or_default();
+        bail!(SquashError::Refused(t!("already-running", pid = owner.trim(), path = path.display())))
+      },
+      Err(error) => Err(error).with_context(|| format!("Failed to create {}", path.display()))
+    }
+  }
+}
+
+impl Drop for OperationLock {
+  fn drop(&mut self) {
+    if !self.owned {
+      return;
+    }
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    if held.as_ref() == Some(&self.path) {
+      held.take();
+    }
+    let _ = std::fs::remove_file(&self.path);
+  }
+}
+
+/// Removes the lock held by this process, if any
+pub fn release_held() {
+  if let Some(path) = HELD.lock().unwrap_or_else(|e| e.into_inner()).take() {
+    let _ = std::fs::remove_file(path);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_second_acquire_fails_until_released() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let path = repo.path().join(LOCK_FILE);
+
+    // Another process holds it
+    std::fs::write(&path, "12345\n")?;
+    let error = OperationLock::acquire(&repo).err().context("Expected lock to be held")?;
+    assert!(error.to_string().contains("12345"));
+    std::fs::remove_file(&path)?;
+
+    // Taking it again in the same process nests
+    let lock = OperationLock::acquire(&repo)?;
+    drop(OperationLock::acquire(&repo)?);
+    assert!(path.exists());
+
+    drop(lock);
+    assert!(!repo.path().join(LOCK_FILE).exists());
+    OperationLock::acquire(&repo)?;
+    Ok(())
+  }
+}
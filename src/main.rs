@@ -1,311 +1,1732 @@
 #![allow(clippy::needless_borrow)]
 
-use git2::{Commit, Repository, ResetType, Sort, Time};
+use git2::{Commit, Oid, Repository};
+use serde::Serialize;
+use std::cell::OnceCell;
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
 use std::process::{ExitCode, Termination};
 use anyhow::{bail, Context, Result};
-use dialoguer::theme::ColorfulTheme;
-use dialoguer::{Input, Select};
-use std::path::Path;
-use clap::Parser;
+use clap::{Parser, Subcommand, ValueEnum};
+use git_squash::{
+  batch, branches, clipboard, install, description, editor, encoding, forge, generate, gitmoji, graph, i18n, notes, prompt, range, rebase, reflog, sign, tags, ticket, transaction, upstream, wip, conventional, diff
+};
+use git_squash::squash::{
+  check_message, commit_message, ensure_worktree, iter_topological_commits, plan_range, shallow_limit, squash_is_empty, combined_message, normalize_message, squash_in_plan, squash_with, MessageStrategy, SquashOptions, SquashOutcome, Squashed
+};
+use git_squash::{ExitStatus, SquashError};
+use git_squash::backend::{Backends, GitBackend};
+use git_squash::format::{self, FormatCommit};
+use git_squash::events::{Event, Observer, Silent, Verbose};
+use git_squash::finish::{Finish, Finished};
+use git_squash::lock::{self, OperationLock};
+use git_squash::color::ColorChoice;
+use git_squash::config::{Config, Strategy};
+use git_squash::dates::{DateFormat, DateSource, Dates, Window};
+use git_squash::history::MessageHistory;
+use git_squash::clipboard::CopyTarget;
+use git_squash::forge::Suggestion;
+use git_squash::i18n::t;
+use git_squash::policy::{Subject, Violations};
+use git_squash::actions::{self, Level};
+use git_squash::rebase::{Action, Plan};
+use git_squash::pager::Pages;
+use git_squash::prepush::{self, PrePush};
+use git_squash::analyze::{self, Reason};
+use git_squash::explain;
+use git_squash::doctor::{self, Status};
+use git_squash::changelog;
+use git_squash::email;
+use git_squash::identity::{self, Identity};
+use git_squash::range::{CommitRange, Target};
 
-const MAX_MESSAGE_LENGTH: usize = 80;
-const SECONDS_IN_HOUR: i64 = 3600;
+const PAGE_SIZE: usize = 20;
+const WIP_SCAN_LIMIT: usize = 50;
 
 struct Message(String);
 impl Termination for Message {
   fn report(self) -> ExitCode {
-    println!("{}", self.0);
+    if !self.0.is_empty() {
+      eprintln!("{}", self.0);
+    }
     0.into()
   }
 }
 
-trait FormatCommit {
-  fn format(&self) -> Result<String>;
+#[derive(Parser, Debug)]
+#[clap(author, version, about, args_conflicts_with_subcommands = true)]
+struct Cli {
+  /// Number of commits to squash, or a range like abc123..def456 [default: commits ahead of upstream]
+  #[clap(value_parser = Target::parse, value_name = "AMOUNT|RANGE")]
+  amount: Option<Target>,
+
+  /// How to choose the squash message [default: select]
+  #[clap(long, value_enum)]
+  strategy: Option<Strategy>,
+
+  /// Use this message for the squash commit instead of choosing one
+  #[clap(short, long, conflicts_with_all = ["strategy", "message_cmd", "select", "interactive", "autosquash", "by_author", "window"])]
+  message: Option<String>,
+
+  /// Sign the squash commit using the configured gpg/ssh key
+  #[clap(long, overrides_with = "no_sign")]
+  sign: bool,
+
+  /// Don't sign the squash commit, even if configured to
+  #[clap(long, overrides_with = "sign")]
+  no_sign: bool,
+
+  /// Command that gets the squashed messages and diff on stdin and prints a suggested message
+  #[clap(long, value_name = "CMD")]
+  message_cmd: Option<String>,
+
+  /// Pick individual commits among the last <AMOUNT> to fold into their predecessor
+  #[clap(long, conflicts_with = "interactive")]
+  select: bool,
+
+  /// Fold fixup! and squash! commits among the last <AMOUNT> into the commits they name
+  #[clap(long, conflicts_with_all = ["select", "interactive"])]
+  autosquash: bool,
+
+  /// Squash each run of consecutive commits by the same author among the last <AMOUNT>
+  #[clap(long, conflicts_with_all = ["select", "interactive", "autosquash"])]
+  by_author: bool,
+
+  /// Squash each burst of commits less than DURATION apart (e.g. 30m, 2h) among the last <AMOUNT>
+  #[clap(long, value_parser = Window::parse, value_name = "DURATION", conflicts_with_all = ["select", "interactive", "autosquash", "by_author"])]
+  window: Option<Window>,
+
+  /// Squash only the commits among the last <AMOUNT> that touch these pathspecs, leaving the others in place
+  #[clap(long = "path", value_name = "PATHSPEC", conflicts_with_all = ["select", "interactive", "autosquash", "by_author", "window"])]
+  paths: Vec<String>,
+
+  /// Filter the message list by typing instead of paging through it
+  #[clap(long)]
+  fuzzy: bool,
+
+  /// Ask with numbered lists and plain lines read from stdin, without arrow keys or ANSI codes
+  #[clap(long)]
+  plain: bool,
+
+  /// Choose pick, reword, squash or fixup for each of the last <AMOUNT> commits
+  #[clap(short, long)]
+  interactive: bool,
+
+  /// Show the range as a graph and ask before squashing (always done when it contains merges)
+  #[clap(long)]
+  graph: bool,
+
+  /// Offer to page through the combined diff of the squash commit before creating it
+  #[clap(long)]
+  preview: bool,
+
+  /// Move tags pointing at rewritten commits to the commits they became instead of aborting
+  #[clap(long)]
+  retag: bool,
+
+  /// Create an annotated tag NAME on the squash commit, with the squash message
+  #[clap(long, value_name = "NAME", conflicts_with_all = ["autosquash", "by_author", "window", "paths", "repos", "manifest"])]
+  tag: Option<String>,
+
+  /// Also cherry-pick the squash commit onto BRANCH, without checking it out
+  #[clap(long, value_name = "BRANCH", conflicts_with_all = ["autosquash", "by_author", "window", "paths", "repos", "manifest"])]
+  also_onto: Vec<String>,
+
+  /// Push the branch to its upstream afterwards, without forcing, so commits already there can't be replaced
+  #[clap(long, conflicts_with_all = ["select", "interactive", "autosquash", "by_author", "window", "paths", "explain"])]
+  push: bool,
+
+  /// Add the squashed commits to the [Unreleased] part of a Keep a Changelog FILE
+  #[clap(long, value_name = "FILE", conflicts_with_all = ["autosquash", "by_author", "window", "paths", "repos", "manifest"])]
+  changelog: Option<PathBuf>,
+
+  /// Print the squash commit on stdout as a patch email in mbox format, like `git format-patch --stdout`
+  #[clap(
+    long,
+    conflicts_with_all = ["porcelain", "json", "autosquash", "by_author", "window", "paths", "repos", "manifest"]
+  )]
+  format_email: bool,
+
+  /// Sign the tag created with --tag [default: tag.gpgSign]
+  #[clap(long, requires = "tag")]
+  sign_tag: bool,
+
+  /// Print each step the squash would take, with the commits and trees involved, and stop there
+  #[clap(
+    long,
+    conflicts_with_all = ["select", "interactive", "autosquash", "by_author", "window", "paths", "preview", "json", "porcelain", "repos", "manifest"]
+  )]
+  explain: bool,
+
+  /// Squash even if the commits cancel each other out and the result changes nothing
+  #[clap(long)]
+  allow_empty: bool,
+
+  /// Credit the other authors of the squashed commits with Co-authored-by trailers
+  #[clap(long)]
+  co_authors: bool,
+
+  /// Write custom messages step by step as type(scope): subject and body
+  #[clap(long)]
+  wizard: bool,
+
+  /// Longest subject line a typed message may have, shown as a live count in the prompt [default: 72]
+  #[clap(long, value_name = "N")]
+  max_subject_length: Option<usize>,
+
+  /// Reject the final message unless it follows Conventional Commits
+  #[clap(long)]
+  conventional: bool,
+
+  /// Pick a gitmoji to put in front of the subject
+  #[clap(long)]
+  gitmoji: bool,
+
+  /// Command that gets the final message on stdin and rejects it by exiting non-zero
+  #[clap(long, value_name = "CMD")]
+  lint_cmd: Option<String>,
+
+  /// Refuse to squash unless the message has a Signed-off-by trailer for the committer (DCO)
+  #[clap(long)]
+  require_signoff: bool,
+
+  /// Write or touch up the message in your editor, next to a list of the squashed commits
+  #[clap(short, long)]
+  edit: bool,
+
+  /// Show the diff of the squash below the message in the editor, like `git commit -v` [default: commit.verbose]
+  #[clap(long)]
+  verbose_message: bool,
+
+  /// Re-wrap the message body at 72 columns, keeping lists, code and trailers intact
+  #[clap(long)]
+  wrap: bool,
+
+  /// Show commit dates as relative ages or absolute dates in each commit's timezone
+  #[clap(long, value_enum, value_name = "FORMAT")]
+  date: Option<DateFormat>,
+
+  /// Show the author date instead of the committer date, which differ for rebased commits
+  #[clap(long, value_enum, value_name = "DATE")]
+  date_source: Option<DateSource>,
+
+  /// Color prompts and diffs [default: auto, off when NO_COLOR is set]
+  #[clap(long, value_enum, value_name = "WHEN")]
+  color: Option<ColorChoice>,
+
+  /// Copy the SHA or the message of the squash commit to the clipboard
+  #[clap(long, value_enum, value_name = "WHAT")]
+  copy: Option<CopyTarget>,
+
+  /// Print a markdown pull request description listing the squashed commits
+  #[clap(long)]
+  pr_description: bool,
+
+  /// libgit2 or cli (running git itself), for every operation or per operation as in libgit2,commit=cli
+  #[clap(long, value_parser = Backends::parse, value_name = "BACKEND")]
+  backend: Option<Backends>,
+
+  /// Report each step of the squash as it happens
+  #[clap(short, long)]
+  verbose: bool,
+
+  /// Print only the SHA of the new HEAD on stdout, for scripts
+  #[clap(long)]
+  porcelain: bool,
+
+  /// Print the old and new HEAD and what each replaced commit became as JSON on stdout
+  #[clap(long, conflicts_with_all = ["porcelain", "select", "autosquash", "by_author", "window", "paths", "repos", "manifest"])]
+  json: bool,
+
+  /// Squash the last <AMOUNT> commits in each of these repositories, without prompting
+  #[clap(long = "repo", value_name = "PATH", requires = "amount", conflicts_with_all = BATCH_CONFLICTS)]
+  repos: Vec<PathBuf>,
+
+  /// Like --repo, for every repository listed in FILE (one path per line)
+  #[clap(long, value_name = "FILE", requires = "amount", conflicts_with_all = BATCH_CONFLICTS)]
+  manifest: Option<PathBuf>,
+
+  #[clap(subcommand)]
+  command: Option<Command>
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+  /// Resume a squash that stopped at a conflict, once the conflicts are resolved and staged
+  Continue {
+    /// Print the old and new HEAD and what each replaced commit became as JSON on stdout
+    #[clap(long)]
+    json:    bool,
+    /// Report what each replaced commit became
+    #[clap(short, long)]
+    verbose: bool
+  },
+  /// Give up a squash that stopped at a conflict and restore the branch
+  Abort,
+  /// Make this binary run as `git squash`, through a git-squash link on PATH or a git alias
+  Install {
+    /// Set the global alias.squash instead of linking
+    #[clap(long)]
+    alias:   bool,
+    /// Where to put the link [default: ~/.local/bin]
+    #[clap(long, value_name = "DIR", conflicts_with = "alias")]
+    dir:     Option<PathBuf>,
+    /// Only print what would be done
+    #[clap(long)]
+    dry_run: bool
+  },
+  /// Suggest groups of commits to squash, by fixup markers, bursts of work and similar subjects
+  Analyze {
+    /// Number of commits to look at [default: commits ahead of upstream, or 20]
+    amount: Option<usize>
+  },
+  /// Check everything a squash depends on, like uncommitted changes, the upstream, signing and hooks
+  Doctor,
+  /// Install a git hook into this repository's hooks directory
+  InstallHook {
+    #[clap(value_enum)]
+    hook:  GitHook,
+    /// Replace a hook that git-squash didn't install
+    #[clap(long)]
+    force: bool
+  },
+  /// Run by the pre-push hook with the refs being pushed on stdin
+  #[clap(hide = true)]
+  PrePush { remote: Option<String>, url: Option<String> }
+}
+
+#[derive(Clone, Copy, Debug, ValueEnum)]
+enum GitHook {
+  /// Flag work-in-progress commits before they are pushed, as set by squash.prePush
+  PrePush
 }
 
-impl<'a> FormatCommit for Commit<'a> {
-  fn format(&self) -> Result<String> {
-    let message = self.summary().unwrap_or_default().to_string();
-    let hours = self.time().hours_ago();
-    let mut formatted = format!("{} {}", hours, message);
-    if formatted.len() > MAX_MESSAGE_LENGTH {
-      formatted.truncate(MAX_MESSAGE_LENGTH);
-      formatted.push_str("...");
+/// Flags that need someone at the terminal, which batch mode doesn't have
+const BATCH_CONFLICTS: [&str; 7] = ["select", "interactive", "fuzzy", "graph", "preview", "edit", "wizard"];
+
+impl Cli {
+  /// Modes that rewrite through a rebase plan and don't choose a message by strategy
+  fn uses_plan(&self) -> bool {
+    self.autosquash
+      || self.by_author
+      || self.window.is_some()
+      || !self.paths.is_empty()
+      || matches!(self.amount, Some(Target::Range(_)))
+  }
+
+  /// Whether something other than the result goes to stdout
+  fn prints_stdout(&self) -> bool {
+    self.porcelain || self.json || self.format_email || self.pr_description
+  }
+
+  /// The first flag given that only makes sense with someone answering prompts
+  fn needs_prompts(&self) -> Option<&'static str> {
+    [("select", self.select), ("interactive", self.interactive), ("graph", self.graph), ("preview", self.preview)]
+      .into_iter()
+      .find_map(|(flag, given)| given.then_some(flag))
+  }
+
+  /// Settings given on the command line, which override any config file
+  fn config(&self) -> Config {
+    Config {
+      strategy: self.strategy,
+      sign: match (self.sign, self.no_sign) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None
+      },
+      message_cmd: self.message_cmd.clone(),
+      max_subject_length: self.max_subject_length,
+      co_authors: self.co_authors.then_some(true),
+      wizard: self.wizard.then_some(true),
+      conventional: self.conventional.then_some(true),
+      gitmoji: self.gitmoji.then_some(true),
+      lint_cmd: self.lint_cmd.clone(),
+      require_signoff: self.require_signoff.then_some(true),
+      wrap: self.wrap.then_some(true),
+      plain: self.plain.then_some(true),
+      date_format: self.date,
+      date_source: self.date_source,
+      color: self.color,
+      copy: self.copy,
+      backend: self.backend,
+      ..Config::default()
     }
-    Ok(formatted)
   }
 }
 
-trait Commitable {
-  fn commit_with_msg(&self, message: &str) -> Result<git2::Oid>;
+/// Only the subject, the first line, is held to `max_length`
+fn validate_input(input: &str, max_length: usize) -> Result<()> {
+  let length = input.lines().next().unwrap_or_default().chars().count();
+  if length > max_length {
+    bail!(t!("message-too-long", length = length, max = max_length));
+  }
+
+  Ok(())
 }
 
-impl Commitable for Repository {
-  fn commit_with_msg(&self, message: &str) -> Result<git2::Oid> {
-    let mut index = self.index().context("Failed to get index")?;
-    let oid = index.write_tree().context("Failed to write tree")?;
-    let signature = self.signature().context("Failed to get signature")?;
-    let tree = self.find_tree(oid).context("Failed to find tree")?;
-    let parent = self.head().ok().and_then(|head| head.peel_to_commit().ok());
-    let parents = parent.iter().collect::<Vec<&Commit>>();
+/// How custom messages are asked for
+struct MessagePrompt<'a> {
+  max_length:  usize,
+  /// Walk through the parts of a Conventional Commits message instead of a single input
+  wizard:      bool,
+  /// `commit.template`, which is edited in the editor like `git commit` does
+  template:    Option<String>,
+  /// Write every message in the editor, including ones picked from the commits
+  edit:        bool,
+  /// The editor git would use, possibly with arguments
+  editor:      String,
+  /// Commented list of the squashed commits shown in the editor
+  comments:    EditorComments<'a>,
+  /// Messages typed before, recalled with the arrow keys
+  history:     MessageHistory,
+  /// Extra messages offered in the menu, e.g. the pull request title
+  suggestions: Vec<Suggestion>
+}
 
-    self
-      .commit(Some("HEAD"), &signature, &signature, &message, &tree, parents.as_slice())
-      .context("Could not commit")
+impl MessagePrompt<'_> {
+  /// Opens a message that was picked rather than written in the editor when `--edit` is given
+  fn review(&self, message: String) -> Result<String> {
+    match self.edit {
+      true => editor::compose(&self.editor, &message, self.comments.text()?, self.max_length),
+      false => Ok(message)
+    }
   }
 }
 
-trait HoursAgo {
-  fn hours_ago(&self) -> String;
+fn prompt_for_commit_message(prompt: &MessagePrompt) -> Result<String> {
+  if prompt.wizard {
+    return conventional::wizard(prompt.max_length);
+  }
+
+  if let Some(template) = &prompt.template {
+    let message = editor::compose(&prompt.editor, template, prompt.comments.text()?, prompt.max_length)?;
+    if message == editor::strip_comments(template) {
+      bail!(SquashError::Aborted(t!("template-not-edited")));
+    }
+    return Ok(message);
+  }
+
+  if prompt.edit {
+    return editor::compose(&prompt.editor, "", prompt.comments.text()?, prompt.max_length);
+  }
+
+  if prompt::is_plain() {
+    return prompt::text(&t!("message-prompt-plain"), None, |input| {
+      validate_input(input, prompt.max_length).map_err(|error| error.to_string())
+    })
+    .context("Failed to get commit message");
+  }
+
+  let mut history = prompt.history.clone();
+  prompt::counted_text(&t!("message-prompt"), prompt.max_length, &mut history, &clipboard::Paste, |input| {
+    validate_input(input, prompt.max_length).map_err(|error| error.to_string())
+  })
+  .context("Failed to get commit message")
 }
 
-impl HoursAgo for Time {
-  fn hours_ago(&self) -> String {
-    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
-    let hours = (now - self.seconds()) / SECONDS_IN_HOUR;
-    format!("{: <8}", format!("{} h", hours))
+fn select_commit_message(repo: &Repository, walk: &Walk, prompt: &MessagePrompt, fuzzy: bool, dates: Dates) -> Result<String> {
+  if !fuzzy && walk.amount > PAGE_SIZE {
+    return select_commit_message_paginated(repo, walk.amount, prompt, dates);
+  }
+  let squashed = walk.get()?;
+
+  let mut items = vec![t!("custom-message")];
+  items.extend(prompt.suggestions.iter().map(|suggestion| suggestion.label.clone()));
+  let suggested = prompt.suggestions.len();
+
+  // Only the labels are kept, the selected commit is looked up again
+  let messages = format::format_all(repo, &squashed.commits, dates).context("Failed to format commits")?;
+
+  items.extend_from_slice(&messages);
+
+  let selection = match fuzzy {
+    true => prompt::fuzzy_select(&t!("select-message-fuzzy"), &items, 0),
+    false => prompt::select(&t!("select-message"), &items, 0)
+  }
+  .context("Failed to set selection")?;
+
+  match selection {
+    0 => prompt_for_commit_message(prompt),
+    n if n <= suggested => prompt.review(prompt.suggestions[n - 1].message.clone()),
+    n if n - suggested <= messages.len() => {
+      let commit = repo.find_commit(squashed.commits[n - suggested - 1]).context("Failed to find commit")?;
+      prompt.review(commit_message(Some(&commit))?)
+    },
+    _ => bail!(t!("invalid-selection"))
   }
 }
 
-#[derive(Parser, Debug)]
-#[clap(author, version, about)]
-struct Cli {
-  #[clap()]
-  amount: usize
+/// Shows `PAGE_SIZE` commits at a time, reading further from the revwalk
+/// only when the user pages forward
+fn select_commit_message_paginated(repo: &Repository, amount: usize, prompt: &MessagePrompt, dates: Dates) -> Result<String> {
+  let mut pages = Pages::new(iter_topological_commits(repo, amount)?, amount, PAGE_SIZE);
+  let mut page = 0;
+  let mailmap = repo.mailmap().context("Failed to load mailmap")?;
+
+  loop {
+    let (labels, messages) = pages
+      .page(page)?
+      .iter()
+      .map(|commit| Ok((commit.format(repo, &mailmap, dates)?, commit_message(Some(commit))?)))
+      .collect::<Result<(Vec<String>, Vec<String>)>>()?;
+
+    let first = pages.first_position(page);
+    let title = t!(
+      "select-message-page",
+      first = first,
+      last = first + labels.len().saturating_sub(1),
+      total = pages.total(),
+      page = page + 1,
+      pages = pages.page_count()
+    );
+
+    let mut items = vec![t!("custom-message")];
+    items.extend(prompt.suggestions.iter().map(|suggestion| suggestion.label.clone()));
+    let suggested = prompt.suggestions.len();
+    items.extend(labels);
+    let has_next = pages.has_next(page);
+    if has_next {
+      items.push(t!("next-page"));
+    }
+    if page > 0 {
+      items.push(t!("previous-page"));
+    }
+
+    let selection = prompt::select(&title, &items, 0).context("Failed to set selection")?;
+
+    match selection.saturating_sub(suggested) {
+      _ if selection == 0 => return prompt_for_commit_message(prompt),
+      _ if selection <= suggested => return prompt.review(prompt.suggestions[selection - 1].message.clone()),
+      n if n <= messages.len() => return prompt.review(messages[n - 1].clone()),
+      n if n == messages.len() + 1 && has_next => page += 1,
+      _ => page -= 1
+    }
+  }
+}
+
+/// Runs the configured message command and asks whether to use its suggestion
+fn generated_commit_message(repo: &Repository, squashed: &Squashed, command: &str, prompts: bool) -> Result<Option<String>> {
+  let base = squashed.base_commit(repo)?;
+  let message = generate::generate(repo, base.as_ref(), &squashed.load(repo)?, command)?;
+  if !prompts {
+    return Ok(Some(message));
+  }
+
+  eprintln!("{}\n\n{}\n", t!("suggested-message"), message);
+  let accepted = prompt::confirm(&t!("use-suggestion"), true).context("Failed to confirm message")?;
+
+  Ok(accepted.then_some(message))
+}
+
+/// Lets the user check commits to fold into their predecessor and replays
+/// the branch with those commits folded away
+fn fold_selected_commits(
+  repo: &Repository, squashed: &Squashed, config: &Config, subject: &Subject, tags: &[tags::Tag]
+) -> Result<usize> {
+  let dates = config.dates();
+  let items = format::format_all(repo, &squashed.commits, dates).context("Failed to format commits")?;
+
+  let selected = prompt::multi_select(&t!("select-fold"), &items).context("Failed to get selection")?;
+
+  let Some(&oldest) = selected.iter().max() else {
+    bail!(SquashError::Aborted(t!("no-commits-selected")));
+  };
+
+  let predecessor = repo
+    .find_commit(squashed.commits[oldest])
+    .context("Failed to find commit")?
+    .parent(0)
+    .context("The root commit has no predecessor to fold into")?;
+  let onto = predecessor.parent_id(0).context("Folding into the root commit is not supported")?;
+  let head = *squashed.commits.first().context("Failed to get HEAD")?;
+
+  let mut plan = Plan::from_range(repo, onto, head)?;
+  for step in &mut plan.steps {
+    if selected.iter().any(|&n| squashed.commits[n] == step.commit) {
+      step.action = Action::Fixup;
+    }
+  }
+
+  run_plan(repo, config, subject, tags, &plan, &format!("squash: folded {} commits", selected.len()))?;
+  Ok(selected.len())
+}
+
+/// Lets the user move commits around until they replay without conflicts
+fn reorder_interactively(repo: &Repository, plan: &mut Plan, dates: Dates) -> Result<()> {
+  let original = plan.clone();
+  let ids = original.steps.iter().map(|step| step.commit).collect::<Vec<_>>();
+  let items = format::format_all(repo, &ids, dates).context("Failed to format commits")?;
+
+  loop {
+    let order = prompt::sort(&t!("reorder-commits"), &items).context("Failed to get order")?;
+
+    *plan = original.clone();
+    plan.reorder(&order);
+
+    // Replaying in memory reveals conflicts without touching the branch
+    match plan.execute(repo) {
+      Ok(_) => return Ok(()),
+      Err(error) => eprintln!("{}", t!("try-another-order", error = format!("{:#}", error)))
+    }
+  }
+}
+
+/// Squashes the commits among the last `amount` that touch `paths` into the
+/// oldest of them, replaying the others on top, and returns how many were squashed
+fn squash_paths(
+  repo: &Repository, config: &Config, subject: &Subject, tags: &[tags::Tag], paths: &[String], message: Option<&str>,
+  prompt: &MessagePrompt
+) -> Result<usize> {
+  let amount = subject.count;
+  let mut plan = plan_range(repo, amount)?;
+  let mut touching = Vec::new();
+  for step in &plan.steps {
+    let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+    if diff::touches(repo, &commit, paths)? {
+      touching.push(commit);
+    }
+  }
+
+  if touching.len() < 2 {
+    bail!(SquashError::NothingToSquash(t!("paths-too-few", count = touching.len(), paths = paths.join(" "))));
+  }
+
+  let message = prompt.review(message.map_or_else(|| combined_message(&touching), str::to_string))?;
+  check_message(repo, config, subject, &normalize_message(&message))?;
+  let ids = touching.iter().map(Commit::id).collect::<Vec<_>>();
+  let reflog = format!("squash: squashed {} commits touching {}", touching.len(), paths.join(" "));
+  let finish = Finish {
+    tags: tags.iter().map(|tag| tag.name.clone()).collect(),
+    ..Finish::new(repo, config, subject.branch, touching.len(), &message)
+  };
+  let finished = squash_in_plan(repo, &mut plan, &ids, message.clone(), &reflog, &finish)?;
+  report_moved_tags(&finished.tags);
+  Ok(touching.len())
+}
+
+/// Asks what to do with each commit in the range, oldest first
+fn plan_interactively(repo: &Repository, squashed: &Squashed, prompt: &MessagePrompt, dates: Dates) -> Result<Plan> {
+  let head = repo
+    .head()
+    .context("Failed to get HEAD")?
+    .peel_to_commit()
+    .context("Failed to resolve HEAD")?;
+  let onto = squashed.base.with_context(|| t!("root-needs-plain-squash"))?;
+  let mut plan = Plan::from_range(repo, onto, head.id())?;
+  reorder_interactively(repo, &mut plan, dates)?;
+  let mailmap = repo.mailmap().context("Failed to load mailmap")?;
+
+  for (n, step) in plan.steps.iter_mut().enumerate() {
+    let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+
+    // The oldest commit has nothing to be folded into
+    let actions = Action::ALL.iter().filter(|action| n > 0 || !action.folds()).collect::<Vec<_>>();
+    let names = actions.iter().map(|action| action.name().to_string()).collect::<Vec<_>>();
+
+    let selection = prompt::select(&commit.format(repo, &mailmap, dates)?, &names, 0).context("Failed to get action")?;
+
+    step.action = *actions[selection];
+    if step.action == Action::Reword {
+      step.message = Some(prompt_for_commit_message(prompt)?);
+    }
+  }
+
+  Ok(plan)
+}
+
+/// Prints the topology of the range and asks whether to go on. Flattening
+/// merges loses their structure, so ranges containing any are always shown.
+fn confirm_topology(repo: &Repository, squashed: &Squashed, always: bool, prompts: bool) -> Result<bool> {
+  if !always && !squashed.has_merges(repo)? {
+    return Ok(true);
+  }
+
+  if !prompts {
+    bail!(SquashError::Refused(t!("merges-need-confirmation", count = squashed.len())));
+  }
+
+  for line in graph::render(&squashed.load(repo)?)? {
+    eprintln!("{}", line);
+  }
+
+  prompt::confirm(&t!("confirm-topology", count = squashed.len()), true).context("Failed to get confirmation")
 }
 
-fn iter_topological_commits(repo: &Repository, amount: usize) -> Result<impl Iterator<Item = Result<Commit, git2::Error>>> {
-  let mut revwalk = repo.revwalk().context("Failed to get revwalk")?;
-  revwalk.set_sorting(Sort::TOPOLOGICAL).context("Failed to set sorting")?;
-  revwalk.push_head().context("Failed to push HEAD")?;
+/// Lets the user look at the content of the squash commit, the diff from
+/// the new parent to `HEAD`, as often as they like before deciding
+fn preview_squash(repo: &Repository, squashed: &Squashed) -> Result<bool> {
+  let base = squashed.base_commit(repo)?;
+  let head = repo
+    .head()
+    .context("Failed to get HEAD")?
+    .peel_to_commit()
+    .context("Failed to resolve HEAD")?;
 
-  Ok(
-    revwalk
-      .take(amount)
-      .map(|oid_result| oid_result.and_then(|oid| repo.find_commit(oid).map_err(Into::into)))
+  loop {
+    let actions = [t!("action-squash"), t!("action-preview"), t!("action-abort")];
+    let selection =
+      prompt::select(&t!("confirm-squash", count = squashed.len()), &actions, 0).context("Failed to get selection")?;
+
+    match selection {
+      0 => return Ok(true),
+      1 => diff::show(repo, &diff::colorize(&diff::patch(repo, base.as_ref(), &head)?))?,
+      _ => return Ok(false)
+    }
+  }
+}
+
+/// Warns that squashing drops the signatures in the range and decides
+/// whether the squash commit gets signed, offering it to users with a
+/// signing key who haven't made up their mind in the config
+fn confirm_signing(repo: &Repository, squashed: &Squashed, config: &Config, prompts: bool) -> Result<bool> {
+  let signed = squashed.commits.iter().filter(|oid| sign::is_signed(repo, **oid)).count();
+  if signed == 0 {
+    return Ok(config.sign());
+  }
+
+  match signed {
+    1 => eprintln!("{}", t!("signature-discarded", count = signed)),
+    _ => eprintln!("{}", t!("signatures-discarded", count = signed))
+  }
+  if !prompts || config.sign.is_some() || !sign::key_configured(repo) {
+    return Ok(config.sign());
+  }
+
+  prompt::confirm(&t!("confirm-sign"), true).context("Failed to get confirmation")
+}
+
+/// Offers to squash the work-in-progress commits at the tip of the branch
+/// into the commit they build on, staying within the unpushed commits
+fn confirm_wip(repo: &Repository, tracking: Option<&upstream::Tracking>, limit: Option<usize>) -> Result<Option<usize>> {
+  let scan = [tracking.map(|tracking| tracking.ahead), limit]
+    .into_iter()
+    .flatten()
+    .fold(WIP_SCAN_LIMIT, usize::min);
+  let Some(noise) = wip::detect(repo, scan)? else {
+    return Ok(None);
+  };
+
+  let question = match &noise.target {
+    Some(target) => t!("confirm-wip-into", count = noise.count, target = target),
+    None => t!("confirm-wip", count = noise.count)
+  };
+  let accepted = prompt::confirm(&question, true).context("Failed to get confirmation")?;
+
+  Ok(accepted.then(|| noise.amount()))
+}
+
+/// Proposes the number of commits ahead of upstream, which is what people
+/// want to squash most of the time, and lets the user adjust it. Without
+/// `prompts` the proposal is taken as is.
+fn prompt_for_amount(tracking: Option<&upstream::Tracking>, limit: Option<usize>, prompts: bool) -> Result<usize> {
+  let tracking = tracking.with_context(|| t!("no-upstream"))?;
+  if tracking.ahead == 0 {
+    bail!(SquashError::NothingToSquash(t!(
+      "not-ahead",
+      branch = tracking.branch,
+      upstream = tracking.upstream
+    )));
+  }
+
+  let limit = limit.unwrap_or(usize::MAX);
+  if !prompts {
+    return Ok(tracking.ahead.min(limit));
+  }
+
+  let amount = prompt::text(
+    &t!("amount-prompt", ahead = tracking.ahead, upstream = tracking.upstream),
+    Some(&tracking.ahead.min(limit).to_string()),
+    |amount| {
+      match amount.parse::<usize>() {
+        Ok(0) | Err(_) => Err(t!("amount-at-least-one")),
+        Ok(n) if n > limit => Err(t!("shallow-available", limit = limit)),
+        Ok(_) => Ok(())
+      }
+    }
   )
+  .context("Failed to get amount")?;
+
+  Ok(amount.parse()?)
+}
+
+/// `amount` if the history has that many commits to squash. Otherwise says
+/// how many there are and offers to squash them all, pick another number or
+/// abort, or fails without `prompts`.
+fn fit_history(repo: &Repository, amount: usize, limit: Option<usize>, prompts: bool) -> Result<usize> {
+  let available = match limit {
+    Some(limit) => limit,
+    None => iter_topological_commits(repo, amount)?.count()
+  };
+  if amount <= available {
+    return Ok(amount);
+  }
+
+  let error = match limit {
+    Some(limit) => {
+      SquashError::ShallowClone {
+        limit,
+        requested: amount
+      }
+    },
+    None => {
+      SquashError::NotEnoughCommits {
+        requested: amount,
+        available
+      }
+    },
+  };
+  if !prompts || available == 0 {
+    bail!(error);
+  }
+
+  let choices = [t!("shortfall-all", count = available), t!("shortfall-other"), t!("shortfall-abort")];
+  match prompt::select(&error.to_string(), &choices, 0)? {
+    0 => Ok(available),
+    1 => {
+      let amount = prompt::text(&t!("shortfall-prompt", available = available), None, |amount| {
+        match amount.parse::<usize>() {
+          Ok(0) | Err(_) => Err(t!("amount-at-least-one")),
+          Ok(n) if n > available => Err(t!("too-many-commits", count = n, available = available)),
+          Ok(_) => Ok(())
+        }
+      })
+      .context("Failed to get amount")?;
+      Ok(amount.parse()?)
+    },
+    _ => bail!(SquashError::Aborted(t!("nothing-squashed")))
+  }
 }
 
-fn find_old_commit(repo: &Repository, amount: usize) -> Result<git2::Object> {
-  iter_topological_commits(repo, amount + 1)?
-    .last()
-    .context("Failed to get last commit")
-    .and_then(|commit| Ok(commit.map(|c| c.into_object())?))
+/// Tags the squash commit for `--tag`, signing when asked to or when
+/// `tag.gpgSign` is set
+fn create_tag(cli: &Cli, repo: &Repository, name: &str, commit: Oid, message: &str) -> Result<()> {
+  let sign = cli.sign_tag || tags::sign_by_default(repo);
+  tags::create(repo, name, commit, message, sign)?;
+  eprintln!("{}", t!("tagged", name = name, oid = &commit.to_string()[..7]));
+  Ok(())
 }
 
-fn git_soft_reset(repo: &Repository, amount: usize, message: &String) -> Result<git2::Oid> {
-  let obj = find_old_commit(repo, amount).context("Failed to find old commit")?;
-  repo.reset(&obj, ResetType::Soft, None).context("Failed to reset")?;
-  repo.commit_with_msg(&message).context("Failed to commit")
+/// Adds the squashed commits (newest first) to the `--changelog` file
+fn write_changelog(path: &Path, commits: &[Commit]) -> Result<()> {
+  let count = changelog::append(path, &changelog::sections(commits))?;
+  eprintln!("{}", t!("changelog-written", count = count, path = path.display()));
+  Ok(())
 }
 
-fn commits(repo: &Repository, amount: usize) -> Result<Vec<Commit>> {
-  Ok(iter_topological_commits(repo, amount)?.filter_map(Result::ok).collect::<Vec<Commit>>())
+/// Prints the squash commit as a patch email for `--format-email`
+fn print_email(repo: &Repository, commit: Oid, squashed: &[Commit]) -> Result<()> {
+  let commit = repo.find_commit(commit).context("Failed to find squash commit")?;
+  print!("{}", email::format(repo, &commit, squashed)?);
+  Ok(())
 }
 
-fn validate_input(input: &String) -> Result<()> {
-  if input.len() > MAX_MESSAGE_LENGTH {
-    bail!("Message is too long, max is {}", MAX_MESSAGE_LENGTH);
+/// Cherry-picks the squash commit onto each `--also-onto` branch
+fn pick_onto(cli: &Cli, repo: &Repository, commit: Oid) -> Result<()> {
+  for branch in &cli.also_onto {
+    let picked = branches::cherry_pick_onto(repo, commit, branch)?;
+    eprintln!("{}", t!("cherry-picked", branch = branch, oid = &picked.to_string()[..7]));
+  }
+  Ok(())
+}
+
+/// When the squashed commits (newest first) were written by more than one
+/// person, asks whether the squash commit is authored by me, whoever wrote
+/// most of them or the author of the oldest one
+fn choose_author(repo: &Repository, range: &[Oid]) -> Result<()> {
+  let oldest_first = range.iter().rev().copied().collect::<Vec<_>>();
+  let authors = identity::authors(repo, &oldest_first)?;
+  if authors.len() < 2 {
+    return Ok(());
+  }
+
+  let me = Identity::of(&identity::author(repo)?);
+  // Ties go to whoever started
+  let (majority, count) = authors.iter().rev().max_by_key(|(_, count)| *count).context("No authors")?;
+  let mut choices = vec![(t!("author-me", identity = &me), me.clone())];
+  choices.push((t!("author-majority", identity = majority, count = count, total = range.len()), majority.clone()));
+  choices.push((t!("author-oldest", identity = &authors[0].0), authors[0].0.clone()));
+  let mut seen = Vec::new();
+  choices.retain(|(_, author)| {
+    let new = !seen.contains(author);
+    seen.push(author.clone());
+    new
+  });
+  if choices.len() < 2 {
+    return Ok(());
   }
 
+  let items = choices.iter().map(|(item, _)| item.clone()).collect::<Vec<_>>();
+  let choice = prompt::select(&t!("author-prompt", count = authors.len()), &items, 0)?;
+  let author = &choices[choice].1;
+  identity::set_author((*author != me).then(|| author.clone()));
   Ok(())
 }
 
-fn prompt_for_commit_message() -> Result<String> {
-  Input::<String>::with_theme(&ColorfulTheme::default())
-    .with_prompt("Message")
-    .validate_with(validate_input)
-    .interact()
-    .context("Failed to get commit message")
+/// Offers to prefix the subject with the ticket key found in the branch name
+fn prefix_ticket(message: String, pattern: &str, branch: Option<&str>, prompts: bool) -> Result<String> {
+  let Some(key) = branch.map(|branch| ticket::from_branch(pattern, branch)).transpose()?.flatten() else {
+    return Ok(message);
+  };
+
+  if ticket::in_subject(&message, &key) {
+    return Ok(message);
+  }
+
+  if !prompts {
+    return Ok(ticket::prefix(&message, &key));
+  }
+
+  let accepted = prompt::confirm(&t!("confirm-ticket", key = key), true).context("Failed to get confirmation")?;
+
+  Ok(if accepted { ticket::prefix(&message, &key) } else { message })
+}
+
+/// Prefixes `message` with the ticket key and gitmoji the config asks for
+fn decorate_message(message: String, config: &Config, branch: Option<&str>, prompts: bool) -> Result<String> {
+  // Pasted or edited on Windows, messages may come with `\r\n`
+  let message = normalize_message(&message);
+
+  let message = match &config.ticket_pattern {
+    Some(pattern) => prefix_ticket(message, pattern, branch, prompts)?,
+    None => message
+  };
+  Ok(match gitmoji::pick_for(&message, config.gitmoji() && prompts)? {
+    Some(emoji) => gitmoji::prefix(&message, emoji),
+    None => message
+  })
+}
+
+fn main() -> ExitCode {
+  i18n::set(i18n::Locale::from_env());
+
+  // Usage errors exit with 1 rather than clap's 2, which means nothing to squash
+  let cli = match Cli::try_parse() {
+    Ok(cli) => cli,
+    Err(error) => {
+      let _ = error.print();
+      return match error.use_stderr() {
+        true => ExitStatus::Failure.into(),
+        false => ExitCode::SUCCESS
+      };
+    }
+  };
+
+  match run(&cli) {
+    Ok(message) => {
+      if actions::github() && !cli.prints_stdout() && !message.0.is_empty() {
+        actions::annotate(Level::Notice, &message.0);
+      }
+      message.report()
+    },
+    Err(error) => {
+      let status = ExitStatus::of(&error);
+      if actions::github() {
+        annotate_failure(&error, status);
+      }
+      match status {
+        // Outcomes rather than failures, so no `Error:` in front
+        ExitStatus::NothingToSquash | ExitStatus::Aborted => eprintln!("{}", error),
+        _ => eprintln!("Error: {:?}", error)
+      }
+      status.into()
+    }
+  }
+}
+
+/// Shows why the squash didn't happen on the workflow run, with a separate
+/// annotation for each violated policy rule
+fn annotate_failure(error: &anyhow::Error, status: ExitStatus) {
+  if let Some(violations) = error.chain().find_map(|cause| cause.downcast_ref::<Violations>()) {
+    for violation in &violations.0 {
+      actions::annotate(Level::Error, &violation.to_string());
+    }
+    return;
+  }
+
+  match status {
+    ExitStatus::NothingToSquash | ExitStatus::Aborted => actions::annotate(Level::Notice, &error.to_string()),
+    _ => actions::annotate(Level::Error, &format!("{:#}", error))
+  }
+}
+
+/// Tells later workflow steps what the squash made of the branch
+fn set_outputs(new_head: Oid, count: usize) -> Result<()> {
+  actions::set_output("new_sha", &new_head.to_string())?;
+  actions::set_output("squashed_count", &count.to_string())
 }
 
-fn main() -> Result<Message> {
-  // Show cursor on exit whenever ctrl-c is pressed
+fn run(cli: &Cli) -> Result<Message> {
+  // Undo a half-finished squash and show cursor on exit whenever ctrl-c is pressed
   ctrlc::set_handler(move || {
-    println!("Terminating...");
+    eprintln!("{}", t!("terminating"));
+    match transaction::restore_interrupted() {
+      Ok(Some(oid)) => eprintln!("{}", t!("restored-head", oid = oid)),
+      Ok(None) => {},
+      Err(error) => eprintln!("{}", t!("restore-failed", error = format!("{:#}", error)))
+    }
+    lock::release_held();
     let stream = console::Term::stdout();
     let _value = stream.show_cursor().ok();
-    std::process::exit(0);
+    std::process::exit(ExitStatus::Aborted as i32);
   })?;
 
-  let repo = Repository::open_ext(".", git2::RepositoryOpenFlags::empty(), Vec::<&Path>::new()).context("Failed to open repo")?;
-  let mut items = vec!["➜ [Enter] Custom commit message".to_string()];
-  let cli: Cli = Cli::parse();
+  if !cli.repos.is_empty() || cli.manifest.is_some() {
+    return squash_batch(cli);
+  }
 
-  let messages: Vec<String> = commits(&repo, cli.amount)?
-    .iter()
-    .map(|c| c.format())
-    .collect::<Result<Vec<String>>>()
-    .context("Failed to format commits")?;
+  if let Some(Command::Install {
+    alias,
+    dir,
+    dry_run
+  }) = &cli.command
+  {
+    return install(*alias, dir.as_deref(), *dry_run);
+  }
 
-  items.extend_from_slice(&messages);
+  // Honours GIT_DIR, GIT_WORK_TREE and GIT_CEILING_DIRECTORIES like git itself
+  let repo = Repository::open_from_env().context("Failed to open repo")?;
+  let message = match &cli.command {
+    Some(command) => run_command(command, &repo)?,
+    None => squash(cli, &repo, true)?
+  };
 
-  let selection = Select::with_theme(&ColorfulTheme::default())
-    .with_prompt("Select a commit message")
-    .items(&items)
-    .default(0)
-    .interact()
-    .context("Failed to set selection")?;
-
-  let message = match selection {
-    0 => prompt_for_commit_message(),
-    n if n <= messages.len() => {
-      commits(&repo, cli.amount)?
-        .get(n - 1)
-        .context("Failed to get commit")?
-        .message()
-        .map(|s| s.to_string())
-        .context("Failed to get commit message")
+  if cli.porcelain {
+    println!("{}", repo.head().context("Failed to get HEAD")?.target().context("Failed to resolve HEAD")?);
+  }
+
+  Ok(message)
+}
+
+fn run_command(command: &Command, repo: &Repository) -> Result<Message> {
+  match command {
+    Command::Continue {
+      json,
+      verbose
+    } => {
+      let _lock = OperationLock::acquire(repo)?;
+      let finished = rebase::resume(repo)?;
+      report_moved_tags(&finished.tags);
+      report_warnings(&finished.warnings);
+      if *verbose {
+        Verbose.notify(&Event::CommitsRewritten(finished.rewritten.clone()));
+      }
+      report_rewritten(*json, finished.old_head, finished.new_head, &finished.rewritten)?;
+      Ok(Message(t!("continued", oid = finished.new_head)))
+    },
+    Command::Abort => {
+      let _lock = OperationLock::acquire(repo)?;
+      let head = rebase::abort(repo)?;
+      Ok(Message(t!("aborted", oid = head)))
+    },
+    Command::Install {
+      ..
+    } => unreachable!("install runs outside a repository"),
+    Command::InstallHook {
+      hook: GitHook::PrePush,
+      force
+    } => {
+      let exe = std::env::current_exe().context("Failed to find this binary")?;
+      let path = prepush::install(repo, &exe, *force)?;
+      Ok(Message(t!("hook-installed", hook = "pre-push", path = path.display())))
     },
-    _ => bail!("Invalid selection")
-  }?;
+    Command::PrePush {
+      ..
+    } => pre_push(repo),
+    Command::Analyze {
+      amount
+    } => analyze(repo, *amount),
+    Command::Doctor => doctor(repo)
+  }
+}
+
+/// Reports each check, failing when a squash wouldn't go through
+fn doctor(repo: &Repository) -> Result<Message> {
+  let config = Config::load(repo)?;
+  config.color().apply();
 
-  git_soft_reset(&repo, cli.amount, &message)?;
+  let checks = doctor::run(repo, &config)?;
+  for check in &checks {
+    let status = match check.status {
+      Status::Pass => console::style(t!("doctor-pass")).green(),
+      Status::Warn => console::style(t!("doctor-warn")).yellow(),
+      Status::Fail => console::style(t!("doctor-fail")).red()
+    };
+    eprintln!("{:>7}  {}: {}", status.for_stderr().bold(), check.name, check.detail);
+  }
 
-  Ok(Message(format!("Squashed {} commits", cli.amount)))
+  let failed = checks.iter().filter(|check| check.status == Status::Fail).count();
+  if failed > 0 {
+    bail!(SquashError::Refused(t!("doctor-failed", count = failed)));
+  }
+  Ok(Message(String::new()))
 }
 
-#[cfg(test)]
-mod tests {
-  use std::fs::File;
-  use std::io::Write;
-  use super::*;
-  use git2::{IndexAddOption, Time};
-  use log::{info, LevelFilter};
-  use tempdir::TempDir;
+/// Lists the suggested groupings with the command squashing each
+fn analyze(repo: &Repository, amount: Option<usize>) -> Result<Message> {
+  let amount = match amount {
+    Some(amount) => amount,
+    None => upstream::tracking(repo)?.map_or(analyze::DEFAULT_AMOUNT, |tracking| tracking.ahead)
+  };
 
-  #[test]
-  fn test_2_hours_ago() {
-    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
-    let two_hours_ago = now - (SECONDS_IN_HOUR * 2);
-    let hours = Time::new(two_hours_ago, 0).hours_ago();
-    assert_eq!(hours.trim(), "2 h");
+  let suggestions = analyze::analyze(repo, amount)?;
+  if suggestions.is_empty() {
+    return Ok(Message(t!("analyze-nothing", count = amount)));
   }
 
-  #[test]
-  fn test_0_hours_ago() {
-    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_secs() as i64;
-    let time = Time::new(now, 0);
-    let hours = time.hours_ago();
-    assert_eq!(hours.trim(), "0 h");
+  let mut report = Vec::new();
+  for suggestion in &suggestions {
+    report.push(match suggestion.reason {
+      Reason::Fixups => t!("analyze-fixups"),
+      Reason::Burst => t!("analyze-burst", minutes = analyze::BURST_GAP / 60),
+      Reason::Similar => t!("analyze-similar")
+    });
+    for oid in &suggestion.commits {
+      let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+      report.push(format!("  {} {}", &oid.to_string()[..7], encoding::summary(&commit)));
+    }
+    report.push(format!("  $ {}\n", suggestion.command));
   }
 
-  #[test]
-  fn test_format_commit() {
-    let repo = Repository::init("temp_test_repo").unwrap();
-    let commit_id = repo.commit_with_msg("This is a test commit".as_ref()).unwrap();
-    let commit = repo.find_commit(commit_id).unwrap();
-    let formatted = commit.format().unwrap();
-    assert!(formatted.contains("This is a test commit"));
-    std::fs::remove_dir_all("temp_test_repo").unwrap();
+  Ok(Message(report.join("\n").trim_end().to_string()))
+}
+
+/// Lists the work-in-progress commits about to be pushed, refusing the push
+/// over them when squash.prePush is `block`
+fn pre_push(repo: &Repository) -> Result<Message> {
+  let mode = Config::load(repo)?.pre_push();
+  if mode == PrePush::Off {
+    return Ok(Message(String::new()));
   }
 
-  #[test]
-  fn test_get_commits() {
-    let repo = Repository::init("temp_test_repo2").unwrap();
-    let commit_id = repo.commit_with_msg("This is a test commit".as_ref()).unwrap();
-    let commit = repo.find_commit(commit_id).unwrap();
-    let commits = commits(&repo, 1).unwrap();
-    assert_eq!(commits.len(), 1);
-    assert_eq!(commits[0].id(), commit.id());
+  let flagged = prepush::check(repo, std::io::stdin().lock())?;
+  if flagged.is_empty() {
+    return Ok(Message(String::new()));
+  }
 
-    // Cleanup
-    std::fs::remove_dir_all("temp_test_repo2").unwrap();
+  let mut report = t!("prepush-flagged");
+  for commit in &flagged {
+    report.push_str(&format!("\n  {} {}", &commit.oid.to_string()[..7], commit.subject));
   }
+  eprintln!("{}\n{}", report, t!("prepush-suggest"));
 
-  #[test]
-  fn test_commit_to_string() {
-    let repo = Repository::init("temp_test_repo3").unwrap();
-    let commit_id = repo.commit_with_msg("This is a test commit".as_ref()).unwrap();
-    let commit = repo.find_commit(commit_id).unwrap();
-    let commit_string = commit.summary().unwrap_or_default().to_string();
-    assert_eq!(commit_string, "This is a test commit");
+  match mode {
+    PrePush::Block => bail!(SquashError::Refused(t!("prepush-blocked"))),
+    _ => Ok(Message(String::new()))
+  }
+}
+
+/// Makes `git squash` run this binary, refusing to shadow or replace
+/// another `git-squash` or alias
+fn install(alias: bool, dir: Option<&Path>, dry_run: bool) -> Result<Message> {
+  let exe = std::env::current_exe().context("Failed to find this binary")?;
+  let path = std::env::var_os("PATH").unwrap_or_default();
 
-    // Cleanup
-    std::fs::remove_dir_all("temp_test_repo3").unwrap();
+  let dir = match dir {
+    Some(dir) => dir.to_path_buf(),
+    None => install::default_dir()?
+  };
+  let step = match alias {
+    true => install::alias(&exe, &git2::Config::open_default().context("Failed to open git config")?, &path)?,
+    false => install::link(&exe, &dir, &path)?
+  };
+
+  if !dry_run {
+    step.apply(&install::global_config()?)?;
+  }
+  if matches!(step, install::Step::Link { .. }) && !install::on_path(&dir, &path) {
+    eprintln!("{}", t!("warning", error = t!("install-not-on-path", dir = dir.display())));
   }
 
-  // Create a test case using TempDir
-  #[test]
-  fn test_find_old_commit() -> Result<()> {
-    let dir = TempDir::new("temp_test_repo4").unwrap();
-    let repo = Repository::init(dir.path()).unwrap();
-
-    for n in 0..10 {
-      let name = format!("{}.txt", n);
-      let file_path = dir.path().join(name.clone());
-      let mut file = File::create(file_path).context("Failed to create file")?;
-      let content = format!("{}", n);
-      file.write_all(content.as_bytes()).context("Failed to write file")?;
-      let message = format!("Commit {}", n);
-      let mut index = repo.index().context("Failed to get index")?;
-      index.add_all([name], IndexAddOption::DEFAULT, None).context("Failed to add file")?;
-      repo.commit_with_msg(message.as_ref()).context("Failed to commit")?;
+  Ok(Message(step.describe(dry_run)))
+}
+
+/// The commented list of commits shown in the editor, followed by the diff
+/// of the squash below a scissors line when `verbose`. Looking up every
+/// commit and diffing them waits until an editor actually opens, which
+/// most squashes never do.
+/// The last commits, walked the first time something needs all of them, so
+/// the message selector opens without waiting on the whole range
+struct Walk<'a> {
+  repo:     &'a Repository,
+  backend:  Backends,
+  amount:   usize,
+  squashed: OnceCell<Squashed>
+}
+
+impl<'a> Walk<'a> {
+  fn new(repo: &'a Repository, backend: Backends, amount: usize) -> Self {
+    Self {
+      repo,
+      backend,
+      amount,
+      squashed: OnceCell::new()
     }
+  }
 
-    let old_tree = repo.head().unwrap().peel_to_tree().unwrap();
-    let new_commit = "New commit".to_string();
-    git_soft_reset(&repo, 5, &new_commit).context("Failed to squash commits")?;
-    let all_commits = commits(&repo, 10).unwrap();
-    assert_eq!(all_commits.len(), 6);
-
-    assert!(all_commits[0].message().unwrap().contains("New commit"));
-    assert!(all_commits[1].message().unwrap().contains("4"));
-    assert!(all_commits[2].message().unwrap().contains("3"));
-    assert!(all_commits[3].message().unwrap().contains("2"));
-    assert!(all_commits[4].message().unwrap().contains("1"));
-    assert!(all_commits[5].message().unwrap().contains("0"));
-
-    /* Check that all files exists */
-    for n in 0..10 {
-      let name = format!("{}.txt", n);
-      let file_path = dir.path().join(name.clone());
-      assert!(file_path.exists());
+  fn get(&self) -> Result<&Squashed> {
+    if let Some(squashed) = self.squashed.get() {
+      return Ok(squashed);
     }
+    let squashed = Squashed::walk(self.repo, &self.backend, self.amount)?;
+    Ok(self.squashed.get_or_init(|| squashed))
+  }
+}
 
-    let new_tree = repo.head().unwrap().peel_to_tree().unwrap();
-    let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+struct EditorComments<'a> {
+  repo:    &'a Repository,
+  walk:    &'a Walk<'a>,
+  range:   Option<&'a CommitRange>,
+  verbose: bool,
+  text:    OnceCell<String>
+}
 
-    env_logger::builder()
-      .filter_level(LevelFilter::Debug)
-      .format_target(false)
-      .format_timestamp(None)
-      .init();
+impl<'a> EditorComments<'a> {
+  fn new(repo: &'a Repository, walk: &'a Walk<'a>, range: Option<&'a CommitRange>, verbose: bool) -> Self {
+    Self {
+      repo,
+      walk,
+      range,
+      verbose,
+      text: OnceCell::new()
+    }
+  }
 
-    diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
-      let old = delta.old_file().path().unwrap();
-      let new = delta.new_file().path().unwrap();
-      info!("{} {}", old.display(), new.display());
-      info!("{}", String::from_utf8_lossy(line.content()));
-      true
-    })?;
+  fn text(&self) -> Result<&str> {
+    if let Some(text) = self.text.get() {
+      return Ok(text);
+    }
+    let text = self.build()?;
+    Ok(self.text.get_or_init(|| text))
+  }
 
-    Ok(())
+  fn build(&self) -> Result<String> {
+    let repo = self.repo;
+    let (summary, base, head) = match self.range {
+      Some(range) => {
+        let commits = range.commits.iter().rev().copied().collect::<Vec<_>>();
+        (editor::summary(repo, &commits)?, Some(range.from), commits.first().copied())
+      },
+      None => {
+        let squashed = self.walk.get()?;
+        (editor::summary(repo, &squashed.commits)?, squashed.base, squashed.commits.first().copied())
+      }
+    };
+    let Some(head) = head.filter(|_| self.verbose) else {
+      return Ok(summary);
+    };
+
+    let base = base.map(|base| repo.find_commit(base)).transpose().context("Failed to find base")?;
+    let head = repo.find_commit(head).context("Failed to find commit")?;
+    let patch = diff::patch(repo, base.as_ref(), &head)?;
+    Ok(format!("{}{}", summary, editor::scissors(&patch)))
   }
+}
 
-  #[test]
-  fn test_commit_message_validation() {
-    let long_message = "a".repeat(MAX_MESSAGE_LENGTH + 1);
-    assert!(validate_input(&long_message).is_err());
+/// What `--json` prints once the history is rewritten
+#[derive(Serialize)]
+struct Report {
+  old_head:  String,
+  new_head:  String,
+  rewritten: Vec<Rewrite>
+}
+
+#[derive(Serialize)]
+struct Rewrite {
+  old: String,
+  new: String
+}
+
+/// Prints what each replaced commit became as JSON for `--json`, so
+/// tooling can re-link references to the old commits
+fn report_rewritten(json: bool, old_head: Oid, new_head: Oid, rewritten: &[(Oid, Oid)]) -> Result<()> {
+  if !json {
+    return Ok(());
   }
 
-  #[test]
-  fn test_commit_enumeration() -> Result<()> {
-    let dir = TempDir::new("temp_test_repo_commit_enumeration").unwrap();
-    let repo = Repository::init(dir.path()).unwrap();
-    for n in 0..3 {
-      let name = format!("{}.txt", n);
-      let file_path = dir.path().join(name.clone());
-      let mut file = File::create(file_path).context("Failed to create file")?;
-      let content = format!("{}", n);
-      file.write_all(content.as_bytes()).context("Failed to write file")?;
-      let message = format!("Commit {}", n);
-      let mut index = repo.index().context("Failed to get index")?;
-      index.add_all([name], IndexAddOption::DEFAULT, None).context("Failed to add file")?;
-      repo.commit_with_msg(message.as_ref()).context("Failed to commit")?;
+  let report = Report {
+    old_head:  old_head.to_string(),
+    new_head:  new_head.to_string(),
+    rewritten: rewritten
+      .iter()
+      .map(|(old, new)| {
+        Rewrite {
+          old: old.to_string(), new: new.to_string()
+        }
+      })
+      .collect()
+  };
+  println!("{}", serde_json::to_string(&report).context("Failed to serialize report")?);
+  Ok(())
+}
+
+/// Applies the same squash to every repository given with `--repo` or
+/// `--manifest`, reporting each one and failing if any of them did
+fn squash_batch(cli: &Cli) -> Result<Message> {
+  let mut paths = cli.repos.clone();
+  if let Some(manifest) = &cli.manifest {
+    paths.extend(batch::read_manifest(manifest)?);
+  }
+
+  let failed = batch::run(&paths, |repo| squash(cli, repo, false).map(|message| message.0));
+  if failed > 0 {
+    bail!(t!("batch-summary-failed", failed = failed, total = paths.len()));
+  }
+
+  Ok(Message(t!("batch-summary", total = paths.len())))
+}
+
+/// Whether someone can answer prompts, which dialoguer draws on stderr and
+/// reads from stdin
+fn attended() -> bool {
+  std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Checks every message `plan` creates, runs it, moves `tags` along and
+/// records it in the audit log. With several new commits, the log gets the
+/// reflog message.
+fn run_plan(
+  repo: &Repository, config: &Config, subject: &Subject, tags: &[tags::Tag], plan: &Plan, reflog: &str
+) -> Result<Finished> {
+  let messages = plan.messages(repo)?;
+  for message in &messages {
+    check_message(repo, config, subject, message)?;
+  }
+
+  let message = match messages.as_slice() {
+    [message] => message,
+    _ => reflog
+  };
+  let finish = Finish {
+    tags: tags.iter().map(|tag| tag.name.clone()).collect(),
+    ..Finish::new(repo, config, subject.branch, subject.count, message)
+  };
+  let finished = rebase::run(repo, plan, reflog, &finish)?;
+  report_moved_tags(&finished.tags);
+  Ok(finished)
+}
+
+fn report_moved_tags(names: &[String]) {
+  if !names.is_empty() {
+    eprintln!("{}", t!("moved-tags", tags = names.join(", ")));
+  }
+}
+
+/// Prints what went wrong after the squash was made, which doesn't fail it
+fn report_warnings(warnings: &[String]) {
+  for warning in warnings {
+    eprintln!("{}", t!("warning", error = warning));
+  }
+}
+
+/// Pushes the squashed branch for `--push`
+fn push(repo: &Repository, config: &Config, destination: &upstream::Destination, observer: &mut dyn Observer) -> Result<()> {
+  config
+    .backend()
+    .push(repo, &destination.remote, &destination.refspec)
+    .with_context(|| t!("push-failed", destination = destination))?;
+  observer.notify(&Event::PushFinished(destination.clone()));
+  Ok(())
+}
+
+/// Squashes the last commits of `repo` as configured. Without `prompts`, or
+/// a terminal to show them on, nothing is asked: the message must come from
+/// `-m`, the strategy or `message_cmd`, and questions are answered by the config.
+fn squash(cli: &Cli, repo: &Repository, prompts: bool) -> Result<Message> {
+  ensure_worktree(&repo)?;
+  let config = Config::load(&repo)?.merge(cli.config());
+  if let Some(locale) = config.locale()? {
+    i18n::set(locale);
+  }
+  prompt::set_plain(config.plain());
+  config.color().apply();
+  // Plain prompts read answers line by line, which works from a pipe too,
+  // while on CI nobody is there to answer unless asked for plain prompts
+  let prompts = prompts && (config.plain() || (attended() && !actions::ci()));
+  if let Some(flag) = cli.needs_prompts().filter(|_| !prompts) {
+    bail!(t!("needs-terminal", flag = flag));
+  }
+  if !prompts
+    && !cli.uses_plan()
+    && cli.message.is_none()
+    && !cli.explain
+    && config.message_cmd.is_none()
+    && matches!(config.strategy(), Strategy::Select | Strategy::Custom)
+  {
+    bail!(t!("needs-message-strategy"));
+  }
+
+  let _lock = OperationLock::acquire(&repo)?;
+  rebase::ensure_idle(&repo)?;
+  let tracking = upstream::tracking(&repo)?;
+  let destination = match cli.push {
+    true => Some(upstream::destination(&repo)?.with_context(|| t!("push-no-upstream"))?),
+    false => None
+  };
+  let limit = shallow_limit(&repo)?;
+  let commit_range = match &cli.amount {
+    Some(Target::Range(spec)) => {
+      if cli.select || cli.interactive || cli.autosquash || cli.by_author || cli.window.is_some() || !cli.paths.is_empty() {
+        bail!(t!("range-unsupported"));
+      }
+      if cli.explain {
+        bail!(t!("explain-range-unsupported"));
+      }
+      // Asked before the message is, even though the squash refuses it again
+      if config.sign() {
+        bail!(SquashError::Refused(t!("range-sign-unsupported")));
+      }
+      Some(range::resolve(&repo, spec)?)
+    },
+    _ => None
+  };
+  let amount = match &cli.amount {
+    Some(Target::Last(amount)) => *amount,
+    Some(Target::Range(_)) => commit_range.as_ref().map_or(0, |range| range.rewritten),
+    None if cli.autosquash || cli.by_author || cli.window.is_some() => {
+      tracking.as_ref().with_context(|| t!("no-upstream"))?.ahead
+    },
+    None => {
+      let wip = match prompts {
+        true => confirm_wip(&repo, tracking.as_ref(), limit)?,
+        false => None
+      };
+      match wip {
+        Some(amount) => amount,
+        None => prompt_for_amount(tracking.as_ref(), limit, prompts)?
+      }
+    }
+  };
+
+  let amount = match commit_range.is_some() {
+    true => amount,
+    false => fit_history(&repo, amount, limit, prompts)?
+  };
+  // A push that isn't forced only goes through as a fast-forward
+  if let Some(tracking) = tracking.as_ref().filter(|_| cli.push) {
+    if tracking.behind > 0 {
+      bail!(SquashError::Refused(t!("push-behind", upstream = tracking.upstream, count = tracking.behind)));
+    }
+    if amount > tracking.ahead {
+      bail!(SquashError::Refused(t!("push-would-force", upstream = tracking.upstream, ahead = tracking.ahead)));
+    }
+  }
+
+  let head = repo.head().ok();
+  let branch = head.as_ref().and_then(|head| head.shorthand());
+  if let Some(branch) = branch.filter(|branch| config.is_protected(branch)) {
+    bail!(SquashError::ProtectedBranch {
+      branch: branch.to_string()
+    });
+  }
+
+  let subject = Subject {
+    branch,
+    count: amount,
+    message: None
+  };
+  config.policy.enforce(&subject)?;
+
+  let mut observer: Box<dyn Observer> = match cli.verbose {
+    true => Box::new(Verbose),
+    false => Box::new(Silent)
+  };
+  if let Some(name) = &cli.tag {
+    tags::ensure_available(&repo, name)?;
+  }
+  for branch in &cli.also_onto {
+    branches::ensure_pickable(&repo, branch)?;
+  }
+
+  // Squashing the last commits picks a message before the range is scanned,
+  // so the selector opens without walking it all
+  let walk = Walk::new(&repo, config.backend(), amount);
+  let last = commit_range.is_none()
+    && !cli.explain
+    && !cli.select
+    && !cli.by_author
+    && cli.window.is_none()
+    && !cli.autosquash
+    && !cli.interactive
+    && cli.paths.is_empty();
+  let tags = match last {
+    true => Vec::new(),
+    false => scan_range(&repo, walk.get()?, cli.retag, observer.as_mut())?
+  };
+
+  if cli.explain {
+    let squashed = walk.get()?;
+    let range = &squashed.commits;
+    let reflog = reflog::message(config.reflog_message(), &range.iter().rev().copied().collect::<Vec<_>>());
+    let steps = explain::steps(&repo, squashed, &config, &reflog)?;
+    let mut report = steps
+      .iter()
+      .enumerate()
+      .map(|(n, step)| format!("{}. {}", n + 1, step))
+      .collect::<Vec<_>>();
+    report.push(t!("explain-unchanged"));
+    return Ok(Message(report.join("\n")));
+  }
+
+  if cli.select {
+    let folded = fold_selected_commits(&repo, walk.get()?, &config, &subject, &tags)?;
+    return Ok(Message(t!("folded", count = folded)));
+  }
+
+  if cli.by_author {
+    let mut plan = plan_range(&repo, amount)?;
+    return match plan.group_by_author(&repo)? {
+      0 => bail!(SquashError::NothingToSquash(t!("nothing-to-group"))),
+      folded => {
+        run_plan(&repo, &config, &subject, &tags, &plan, &format!("squash: grouped {} commits by author", folded))?;
+        Ok(Message(t!("grouped-by-author", count = folded)))
+      }
+    };
+  }
+
+  if let Some(window) = cli.window {
+    let mut plan = plan_range(&repo, amount)?;
+    return match plan.group_by_window(&repo, window, config.dates())? {
+      0 => bail!(SquashError::NothingToSquash(t!("nothing-in-window", window = window))),
+      folded => {
+        run_plan(
+          &repo,
+          &config,
+          &subject,
+          &tags,
+          &plan,
+          &format!("squash: grouped {} commits within {}", folded, window)
+        )?;
+        Ok(Message(t!("grouped-by-window", count = folded, window = window)))
+      }
+    };
+  }
+
+  if cli.autosquash {
+    let mut plan = plan_range(&repo, amount)?;
+    return match plan.autosquash(&repo)? {
+      0 => bail!(SquashError::NothingToSquash(t!("nothing-to-autosquash"))),
+      folded => {
+        run_plan(&repo, &config, &subject, &tags, &plan, &format!("squash: autosquashed {} commits", folded))?;
+        Ok(Message(t!("autosquashed", count = folded)))
+      }
+    };
+  }
+
+  let prompt = MessagePrompt {
+    max_length:  config.max_subject_length(),
+    wizard:      config.wizard(),
+    template:    editor::template(&repo)?,
+    edit:        cli.edit,
+    editor:      editor::command(&repo),
+    comments:    EditorComments::new(&repo, &walk, commit_range.as_ref(), cli.verbose_message || editor::verbose(&repo)),
+    history:     MessageHistory::load(&repo, config.history_size())?,
+    suggestions: match config.strategy() == Strategy::Select && config.suggest_pr() {
+      true => forge::suggestions(&repo, branch),
+      false => Vec::new()
+    }
+  };
+  if let Some(commit_range) = &commit_range {
+    let commits = commit_range
+      .commits
+      .iter()
+      .map(|oid| repo.find_commit(*oid).context("Failed to find commit"))
+      .collect::<Result<Vec<_>>>()?;
+    let message = prompt.review(cli.message.clone().unwrap_or_else(|| combined_message(&commits)))?;
+    let message = decorate_message(message, &config, branch, prompts)?;
+    let options = SquashOptions::new(cli.amount.clone().context("No range given")?)
+      .message(MessageStrategy::Custom(message))
+      .retag(cli.retag)
+      .allow_empty(cli.allow_empty)
+      .backend(config.backend())
+      .config(config.clone());
+    let outcome = squash_reported(&repo, &options, observer.as_mut())?;
+    return finish(cli, &repo, &config, &outcome, destination.as_ref(), observer.as_mut());
+  }
+
+  if !cli.paths.is_empty() {
+    let squashed = squash_paths(&repo, &config, &subject, &tags, &cli.paths, cli.message.as_deref(), &prompt)?;
+    return Ok(Message(t!("squashed-paths", count = squashed, total = amount)));
+  }
+
+  if cli.interactive {
+    let plan = plan_interactively(&repo, walk.get()?, &prompt, config.dates())?;
+    let finished = run_plan(&repo, &config, &subject, &tags, &plan, &format!("squash: rewrote {} commits", plan.steps.len()))?;
+    observer.notify(&Event::CommitsRewritten(finished.rewritten.clone()));
+    report_rewritten(cli.json, finished.old_head, finished.new_head, &finished.rewritten)?;
+    return Ok(Message(t!("rewrote", count = plan.steps.len())));
+  }
+
+  if let Some(tracking) = &tracking {
+    eprintln!("{}", tracking);
+  }
+
+  let provided = match (&cli.message, &config.message_cmd) {
+    (Some(message), _) => Some(message.clone()),
+    (None, Some(command)) => generated_commit_message(&repo, walk.get()?, command, prompts)?,
+    (None, None) => None
+  };
+
+  let message = match provided {
+    Some(message) => prompt.review(message)?,
+    None => {
+      match config.strategy() {
+        Strategy::Select => select_commit_message(&repo, &walk, &prompt, cli.fuzzy, config.dates())?,
+        Strategy::Custom => prompt_for_commit_message(&prompt)?,
+        Strategy::Newest => prompt.review(commit_message(walk.get()?.newest(&repo)?.as_ref())?)?,
+        Strategy::Oldest => prompt.review(commit_message(walk.get()?.oldest(&repo)?.as_ref())?)?
+      }
+    },
+  };
+  let message = decorate_message(message, &config, branch, prompts)?;
+
+  let squashed = walk.get()?;
+  scan_range(&repo, squashed, cli.retag, observer.as_mut())?;
+  // Checked before the squash starts, even though the squash checks it again
+  if !cli.allow_empty && squash_is_empty(&repo, squashed)? {
+    bail!(SquashError::Refused(t!("empty-squash", count = amount)));
+  }
+  if !confirm_topology(&repo, squashed, cli.graph, prompts)? {
+    bail!(SquashError::Aborted(t!("nothing-squashed")));
+  }
+  if prompts {
+    choose_author(&repo, &squashed.commits)?;
+  }
+
+  if cli.preview && !preview_squash(&repo, squashed)? {
+    bail!(SquashError::Aborted(t!("nothing-squashed")));
+  }
+
+  let sign = confirm_signing(&repo, squashed, &config, prompts)?;
+
+  // The squash wraps the message, adds trailers and checks it against the
+  // message rules and the policy
+  let options = SquashOptions::new(Target::Last(amount))
+    .message(MessageStrategy::Custom(message))
+    .sign(sign)
+    .retag(cli.retag)
+    .allow_empty(cli.allow_empty)
+    .merges(true)
+    .backend(config.backend())
+    .config(config.clone());
+  let outcome = squash_reported(&repo, &options, observer.as_mut())?;
+  finish(cli, &repo, &config, &outcome, destination.as_ref(), observer.as_mut())
+}
+
+/// Reports the commits about to be squashed, refuses tags pointing into them
+/// unless they may be moved and warns about other branches containing them
+fn scan_range(repo: &Repository, squashed: &Squashed, retag: bool, observer: &mut dyn Observer) -> Result<Vec<tags::Tag>> {
+  let range = &squashed.commits;
+  observer.notify(&Event::CommitsEnumerated(range.iter().rev().copied().collect()));
+  let tags = tags::pointing_into(repo, range)?;
+  tags::ensure_rewritable(&tags, retag)?;
+
+  let diverging = branches::containing(repo, range)?;
+  if !diverging.is_empty() {
+    eprintln!("{}", t!("diverging-branches", branches = diverging.join(", ")));
+  }
+  Ok(tags)
+}
+
+/// Squashes as `options` describe, passing every step to `observer` but the
+/// commits being enumerated, which were reported as they were walked
+fn squash_reported(repo: &Repository, options: &SquashOptions, observer: &mut dyn Observer) -> Result<SquashOutcome> {
+  let mut walked = |event: &Event| {
+    if !matches!(event, Event::CommitsEnumerated(_)) {
+      observer.notify(event);
+    }
+  };
+  Ok(squash_with(repo, options, &mut walked)?)
+}
+
+/// Reports the squash and does what was asked for once it's made, the same
+/// for the last commits as for a range
+fn finish(
+  cli: &Cli, repo: &Repository, config: &Config, outcome: &SquashOutcome, destination: Option<&upstream::Destination>,
+  observer: &mut dyn Observer
+) -> Result<Message> {
+  let new_head = outcome.new_head.context("Failed to squash")?;
+  let squash = outcome.commit().context("Failed to squash")?;
+  let count = outcome.squashed.len();
+  let message = &outcome.message;
+
+  report_moved_tags(&outcome.tags);
+  report_warnings(&outcome.warnings);
+  if let Some(name) = &cli.tag {
+    create_tag(cli, repo, name, squash, message)?;
+  }
+  pick_onto(cli, repo, squash)?;
+
+  // What --changelog, --format-email and --pr-description list, newest first
+  let originals = match cli.changelog.is_some() || cli.format_email || cli.pr_description {
+    true => {
+      outcome
+        .squashed
+        .iter()
+        .rev()
+        .map(|oid| repo.find_commit(*oid).context("Failed to find commit"))
+        .collect::<Result<Vec<_>>>()?
+    },
+    false => Vec::new()
+  };
+  if let Some(path) = &cli.changelog {
+    write_changelog(path, &originals)?;
+  }
+  if cli.format_email {
+    print_email(repo, squash, &originals)?;
+  }
+
+  report_rewritten(cli.json, outcome.old_head, new_head, &outcome.rewritten)?;
+  set_outputs(new_head, count)?;
+
+  let newest_first = outcome.squashed.iter().rev().copied().collect::<Vec<_>>();
+  let copied = notes::copy(repo, &newest_first, squash)?;
+  // Commits replayed above a range keep their own notes
+  for (old, new) in outcome.rewritten.iter().filter(|(old, _)| !outcome.squashed.contains(old)) {
+    notes::copy(repo, &[*old], *new)?;
+  }
+  if copied > 0 {
+    eprintln!("{}", t!("copied-notes", count = copied));
+  }
+
+  let copied = match config.copy {
+    Some(CopyTarget::Sha) => Some(squash.to_string()),
+    Some(CopyTarget::Message) => Some(message.clone()),
+    None => None
+  };
+  if let Some(text) = copied {
+    match clipboard::copy(&text) {
+      Ok(()) => eprintln!("{}", t!("copied-to-clipboard")),
+      Err(error) => eprintln!("{}", t!("warning", error = format!("{:#}", error)))
     }
-    let commits_list = commits(&repo, 3)?;
-    assert_eq!(commits_list.len(), 3);
-    Ok(())
+  }
+
+  if cli.pr_description {
+    println!("{}", description::render(&originals));
+  }
+
+  if let Some(destination) = destination {
+    push(repo, config, destination, observer)?;
+  }
+
+  if repo.head_detached().unwrap_or(false) {
+    return Ok(Message(t!("squashed-detached", count = count, oid = new_head)));
+  }
+
+  Ok(Message(t!("squashed", count = count)))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const MAX_MESSAGE_LENGTH: usize = 80;
+
+  #[test]
+  fn test_commit_message_validation() {
+    let long_message = "a".repeat(MAX_MESSAGE_LENGTH + 1);
+    assert!(validate_input(&long_message, MAX_MESSAGE_LENGTH).is_err());
+    assert!(validate_input(&format!("Fix it\n\n{}", long_message), MAX_MESSAGE_LENGTH).is_ok());
+    assert!(validate_input(&"å".repeat(MAX_MESSAGE_LENGTH), MAX_MESSAGE_LENGTH).is_ok());
   }
 }
@@ -0,0 +1,81 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use crate::identity;
+
+/// Copies the notes of the squashed `commits` (newest first) onto `new_head`,
+/// concatenating them oldest first per notes ref. Returns how many notes
+/// were carried over.
+pub fn copy(repo: &Repository, commits: &[Oid], new_head: Oid) -> Result<usize> {
+  let signature = identity::committer(repo)?;
+  let mut copied = 0;
+
+  for notes_ref in notes_refs(repo)? {
+    let notes = commits
+      .iter()
+      .rev()
+      .filter_map(|oid| repo.find_note(Some(&notes_ref), *oid).ok())
+      .filter_map(|note| note.message().map(|message| message.trim_end().to_string()))
+      .collect::<Vec<String>>();
+
+    if notes.is_empty() {
+      continue;
+    }
+
+    repo
+      .note(&signature, &signature, Some(&notes_ref), new_head, &notes.join("\n\n"), true)
+      .with_context(|| format!("Failed to write note to {}", notes_ref))?;
+    copied += notes.len();
+  }
+
+  Ok(copied)
+}
+
+/// `core.notesRef` (or `refs/notes/commits`) plus any `notes.rewriteRef`
+/// patterns, the refs `git rebase` copies notes along
+fn notes_refs(repo: &Repository) -> Result<Vec<String>> {
+  let config = repo.config().context("Failed to get config")?;
+  let mut refs = vec![config.get_string("core.notesRef").unwrap_or_else(|_| "refs/notes/commits".to_string())];
+
+  let mut patterns = Vec::new();
+  if let Ok(entries) = config.multivar("notes.rewriteRef", None) {
+    entries
+      .for_each(|entry| patterns.extend(entry.value().map(String::from)))
+      .context("Failed to read notes.rewriteRef")?;
+  }
+
+  for pattern in patterns {
+    for reference in repo.references_glob(&pattern).context("Failed to list notes refs")? {
+      let reference = reference.context("Failed to read notes ref")?;
+      if let Some(name) = reference.name().filter(|name| !refs.iter().any(|known| known == name)) {
+        refs.push(name.to_string());
+      }
+    }
+  }
+
+  Ok(refs)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_concatenates_notes() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let middle = head.parent(0)?;
+    let base = middle.parent(0)?;
+    let signature = repo.signature()?;
+
+    repo.note(&signature, &signature, None, middle.id(), "Reviewed-by: A", false)?;
+    repo.note(&signature, &signature, None, head.id(), "Reviewed-by: B", false)?;
+    repo.note(&signature, &signature, Some("refs/notes/review"), head.id(), "LGTM", false)?;
+    repo.config()?.set_str("notes.rewriteRef", "refs/notes/*")?;
+
+    assert_eq!(copy(&repo, &[head.id(), middle.id()], base.id())?, 3);
+    assert_eq!(repo.find_note(None, base.id())?.message(), Some("Reviewed-by: A\n\nReviewed-by: B"));
+    assert_eq!(repo.find_note(Some("refs/notes/review"), base.id())?.message(), Some("LGTM"));
+    Ok(())
+  }
+}
@@ -0,0 +1,101 @@
+use anyhow::{Context, Result};
+use git2::Commit;
+
+/// Commits pulled from a revwalk on demand, one page at a time, so large
+/// ranges don't have to be loaded before the first page can be shown
+pub struct Pages<'repo, I> {
+  source:    I,
+  loaded:    Vec<Commit<'repo>>,
+  total:     usize,
+  page_size: usize
+}
+
+impl<'repo, I> Pages<'repo, I>
+where I: Iterator<Item = Result<Commit<'repo>, git2::Error>>
+{
+  /// `total` is the number of commits the walk is expected to yield
+  pub fn new(source: I, total: usize, page_size: usize) -> Self {
+    Self {
+      source,
+      loaded: Vec::new(),
+      total,
+      page_size
+    }
+  }
+
+  /// The commits on `page`, pulling them from the walk if necessary
+  pub fn page(&mut self, page: usize) -> Result<&[Commit<'repo>]> {
+    let end = (page + 1) * self.page_size;
+    while self.loaded.len() < end {
+      match self.source.next() {
+        Some(commit) => self.loaded.push(commit.context("Failed to load commit")?),
+        None => {
+          // The walk ran out early (e.g. short history), so this is the real total
+          self.total = self.loaded.len();
+          break;
+        }
+      }
+    }
+
+    let start = (page * self.page_size).min(self.loaded.len());
+    Ok(&self.loaded[start..end.min(self.loaded.len())])
+  }
+
+  pub fn page_count(&self) -> usize {
+    self.total.div_ceil(self.page_size).max(1)
+  }
+
+  pub fn has_next(&self, page: usize) -> bool {
+    page + 1 < self.page_count()
+  }
+
+  /// Position of the first commit on `page`, counted from 1
+  pub fn first_position(&self, page: usize) -> usize {
+    page * self.page_size + 1
+  }
+
+  pub fn total(&self) -> usize {
+    self.total
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_loads_pages_lazily() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(10)?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    let source = revwalk.map(|oid| oid.and_then(|oid| repo.find_commit(oid)));
+    let mut pages = Pages::new(source, 10, 3);
+
+    assert_eq!(pages.page(0)?.len(), 3);
+    assert_eq!(pages.loaded.len(), 3);
+    assert_eq!(pages.page_count(), 4);
+    assert!(pages.has_next(2));
+
+    let last = pages.page(3)?;
+    assert_eq!(last.len(), 1);
+    assert_eq!(last[0].message(), Some("Commit 0"));
+    assert!(!pages.has_next(3));
+    Ok(())
+  }
+
+  #[test]
+  fn test_shrinks_total_when_walk_ends_early() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(4)?;
+    let mut revwalk = repo.revwalk()?;
+    revwalk.push_head()?;
+    let source = revwalk.map(|oid| oid.and_then(|oid| repo.find_commit(oid)));
+    let mut pages = Pages::new(source, 100, 3);
+
+    assert_eq!(pages.page_count(), 34);
+    pages.page(1)?;
+    assert_eq!(pages.total(), 4);
+    assert_eq!(pages.page_count(), 2);
+    Ok(())
+  }
+}
@@ -0,0 +1,168 @@
+use std::fmt;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use crate::config::glob_match;
+
+/// Team rules checked before any history is rewritten, configured under
+/// `[policy]` in the config files
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Policy {
+  /// Regex the final message has to match
+  pub message_pattern:    Option<String>,
+  /// Maximum number of commits that may be squashed at once
+  pub max_count:          Option<usize>,
+  /// Branch patterns that may never be squashed
+  pub forbidden_branches: Option<Vec<String>>,
+  /// Trailer keys (e.g. `Signed-off-by`) the final message has to carry
+  pub required_trailers:  Option<Vec<String>>
+}
+
+/// What is about to be squashed. `message` is `None` when checking before
+/// the message has been chosen, which skips the message rules.
+pub struct Subject<'a> {
+  pub branch:  Option<&'a str>,
+  pub count:   usize,
+  pub message: Option<&'a str>
+}
+
+#[derive(Debug, PartialEq)]
+pub struct Violation {
+  pub rule:   &'static str,
+  pub detail: String
+}
+
+impl fmt::Display for Violation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}: {}", self.rule, self.detail)
+  }
+}
+
+/// Error carrying every violated rule, so they can all be fixed in one go
+#[derive(Debug)]
+pub struct Violations(pub Vec<Violation>);
+
+impl fmt::Display for Violations {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "Squash violates {} policy rule(s):", self.0.len())?;
+    for violation in &self.0 {
+      write!(f, "\n  - {}", violation)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for Violations {}
+
+impl Policy {
+  /// Values set in `other` take precedence over the ones in `self`
+  pub fn merge(self, other: Self) -> Self {
+    Self {
+      message_pattern:    other.message_pattern.or(self.message_pattern),
+      max_count:          other.max_count.or(self.max_count),
+      forbidden_branches: other.forbidden_branches.or(self.forbidden_branches),
+      required_trailers:  other.required_trailers.or(self.required_trailers)
+    }
+  }
+
+  pub fn evaluate(&self, subject: &Subject) -> Result<Vec<Violation>> {
+    let mut violations = Vec::new();
+
+    if let Some(max) = self.max_count.filter(|max| subject.count > *max) {
+      violations.push(Violation {
+        rule:   "max_count",
+        detail: format!("squashing {} commits exceeds the maximum of {}", subject.count, max)
+      });
+    }
+
+    if let Some(branch) = subject.branch {
+      if self.forbidden_branches.iter().flatten().any(|pattern| glob_match(pattern, branch)) {
+        violations.push(Violation {
+          rule: "forbidden_branches", detail: format!("branch `{}` may not be squashed", branch)
+        });
+      }
+    }
+
+    let Some(message) = subject.message else {
+      return Ok(violations);
+    };
+
+    if let Some(pattern) = &self.message_pattern {
+      let regex = Regex::new(pattern).with_context(|| format!("Invalid policy message_pattern `{}`", pattern))?;
+      if !regex.is_match(message) {
+        violations.push(Violation {
+          rule: "message_pattern", detail: format!("message does not match `{}`", pattern)
+        });
+      }
+    }
+
+    let trailers = git2::message_trailers_strs(message).context("Failed to parse message trailers")?;
+    let present = trailers.iter().map(|(key, _)| key.to_lowercase()).collect::<Vec<_>>();
+    for required in self.required_trailers.iter().flatten() {
+      if !present.contains(&required.to_lowercase()) {
+        violations
+          .push(Violation {
+            rule: "required_trailers", detail: format!("message is missing a `{}` trailer", required)
+          });
+      }
+    }
+
+    Ok(violations)
+  }
+
+  /// Fails with every violated rule listed
+  pub fn enforce(&self, subject: &Subject) -> Result<()> {
+    let violations = self.evaluate(subject)?;
+    if violations.is_empty() {
+      return Ok(());
+    }
+
+    Err(Violations(violations).into())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn policy() -> Policy {
+    Policy {
+      message_pattern:    Some("^(feat|fix): ".to_string()),
+      max_count:          Some(5),
+      forbidden_branches: Some(vec!["release/*".to_string()]),
+      required_trailers:  Some(vec!["Signed-off-by".to_string()])
+    }
+  }
+
+  #[test]
+  fn test_collects_all_violations() -> Result<()> {
+    let subject = Subject {
+      branch: Some("release/1.0"), count: 8, message: Some("wip")
+    };
+
+    let rules = policy().evaluate(&subject)?.into_iter().map(|v| v.rule).collect::<Vec<_>>();
+    assert_eq!(rules, ["max_count", "forbidden_branches", "message_pattern", "required_trailers"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_passes_compliant_squash() -> Result<()> {
+    let subject =
+      Subject {
+        branch: Some("feature/x"), count: 3, message: Some("feat: add parser\n\nSigned-off-by: A <a@example.com>\n")
+      };
+
+    policy().enforce(&subject)
+  }
+
+  #[test]
+  fn test_skips_message_rules_without_message() -> Result<()> {
+    let subject = Subject {
+      branch: None, count: 1, message: None
+    };
+
+    assert!(policy().evaluate(&subject)?.is_empty());
+    Ok(())
+  }
+}
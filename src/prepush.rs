@@ -0,0 +1,161 @@
+use std::io::BufRead;
+use std::path::{Path, PathBuf};
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use git2::{Oid, Repository, Sort};
+use serde::Deserialize;
+use crate::i18n::t;
+use crate::{encoding, hooks, wip, SquashError};
+
+/// First line after the shebang of the hooks this installs, so they can be
+/// told apart from ones written by hand
+const MARKER: &str = "# Installed by git-squash";
+
+/// What the pre-push hook does about work-in-progress commits
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum PrePush {
+  /// List them and suggest squashing, but push anyway
+  #[default]
+  Warn,
+  /// Refuse the push
+  Block,
+  /// Push without looking
+  Off
+}
+
+/// An outgoing commit whose subject looks like work in progress
+#[derive(Debug, PartialEq)]
+pub struct Flagged {
+  pub oid:     Oid,
+  pub subject: String
+}
+
+/// The hook script, which hands the push over to `exe`
+pub fn script(exe: &Path) -> String {
+  format!("#!/bin/sh\n{}\nexec \"{}\" pre-push \"$@\"\n", MARKER, exe.display())
+}
+
+/// Writes the pre-push hook into the hooks directory, refusing to replace
+/// one that git-squash didn't install unless `force`
+pub fn install(repo: &Repository, exe: &Path, force: bool) -> Result<PathBuf> {
+  let dir = hooks::directory(repo);
+  let path = dir.join("pre-push");
+  if let Ok(existing) = std::fs::read_to_string(&path) {
+    if !force && !existing.lines().any(|line| line == MARKER) {
+      bail!(SquashError::Refused(t!("hook-exists", path = path.display())));
+    }
+  }
+
+  std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+  std::fs::write(&path, script(exe)).with_context(|| format!("Failed to write {}", path.display()))?;
+  make_executable(&path).with_context(|| format!("Failed to make {} executable", path.display()))?;
+  Ok(path)
+}
+
+/// The work-in-progress commits among the ones pushed, given the lines git
+/// passes the hook on stdin: `<local ref> <local sha> <remote ref> <remote sha>`
+pub fn check(repo: &Repository, updates: impl BufRead) -> Result<Vec<Flagged>> {
+  let mut flagged = Vec::new();
+  for line in updates.lines() {
+    let line = line.context("Failed to read the refs being pushed")?;
+    let fields = line.split_whitespace().collect::<Vec<_>>();
+    let [_, local, _, remote] = fields[..] else {
+      continue;
+    };
+
+    let local = Oid::from_str(local).with_context(|| format!("Invalid object name `{}`", local))?;
+    let remote = Oid::from_str(remote).with_context(|| format!("Invalid object name `{}`", remote))?;
+    // Deleting a remote branch pushes nothing
+    if local.is_zero() {
+      continue;
+    }
+
+    for commit in outgoing(repo, local, remote)? {
+      if !flagged.iter().any(|flagged: &Flagged| flagged.oid == commit.oid) {
+        flagged.push(commit);
+      }
+    }
+  }
+
+  Ok(flagged)
+}
+
+/// The flagged commits reachable from `local` but not from `remote`, or for a
+/// new branch (or one whose tip we don't have) from any remote-tracking branch
+fn outgoing(repo: &Repository, local: Oid, remote: Oid) -> Result<Vec<Flagged>> {
+  let mut revwalk = repo.revwalk().context("Failed to get revwalk")?;
+  revwalk.set_sorting(Sort::TOPOLOGICAL).context("Failed to set sorting")?;
+  revwalk.push(local).context("Failed to push local commit")?;
+  match !remote.is_zero() && repo.find_commit(remote).is_ok() {
+    true => revwalk.hide(remote).context("Failed to hide remote commit")?,
+    false => revwalk.hide_glob("refs/remotes/*").context("Failed to hide remote-tracking branches")?
+  }
+
+  let mut flagged = Vec::new();
+  for oid in revwalk {
+    let oid = oid.context("Failed to get commit")?;
+    let commit = repo.find_commit(oid).context("Failed to find commit")?;
+    let subject = encoding::summary(&commit);
+    if wip::is_noise(&subject) {
+      flagged.push(Flagged {
+        oid,
+        subject
+      });
+    }
+  }
+
+  Ok(flagged)
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> std::io::Result<()> {
+  use std::os::unix::fs::PermissionsExt;
+  std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755))
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> std::io::Result<()> {
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_check() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let pushed = repo.head()?.target().unwrap();
+    commit_file(&repo, "a.txt", "a", "wip")?;
+    let fixup = commit_file(&repo, "b.txt", "b", "fixup! Add b")?;
+    let head = commit_file(&repo, "c.txt", "c", "Add c")?;
+
+    let updates = format!("refs/heads/main {} refs/heads/main {}\n", head, pushed);
+    let flagged = check(&repo, Cursor::new(updates))?;
+    assert_eq!(flagged.iter().map(|flagged| flagged.subject.as_str()).collect::<Vec<_>>(), ["fixup! Add b", "wip"]);
+    assert_eq!(flagged[0].oid, fixup);
+
+    let deletion = format!("(delete) {} refs/heads/main {}\n", Oid::zero(), pushed);
+    assert!(check(&repo, Cursor::new(deletion))?.is_empty());
+    Ok(())
+  }
+
+  #[cfg(unix)]
+  #[test]
+  fn test_install() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let exe = Path::new("/usr/bin/git-squash");
+
+    let path = install(&repo, exe, false)?;
+    assert_eq!(std::fs::read_to_string(&path)?, script(exe));
+    // Reinstalling over our own hook is fine, a hand-written one needs --force
+    install(&repo, exe, false)?;
+    std::fs::write(&path, "#!/bin/sh\nmake lint\n")?;
+    assert!(install(&repo, exe, false).is_err());
+    install(&repo, exe, true)?;
+    Ok(())
+  }
+}
@@ -0,0 +1,343 @@
+use anyhow::{bail, Result};
+use console::{measure_text_width, strip_ansi_codes, style, Key, Term};
+use dialoguer::theme::{ColorfulTheme, SimpleTheme, Theme};
+use dialoguer::{Completion, Confirm, FuzzySelect, History, Input, MultiSelect, Select, Sort};
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::color;
+use crate::i18n::t;
+
+static PLAIN: AtomicBool = AtomicBool::new(false);
+
+/// Asks every question from here on as a numbered list or a line read from
+/// stdin, without arrow keys or ANSI control sequences, for screen readers
+pub fn set_plain(plain: bool) {
+  PLAIN.store(plain, Ordering::Relaxed);
+}
+
+pub fn is_plain() -> bool {
+  PLAIN.load(Ordering::Relaxed)
+}
+
+/// The look of the menus, without colors when they are turned off
+pub fn theme() -> Box<dyn Theme> {
+  match color::enabled() {
+    true => Box::new(ColorfulTheme::default()),
+    false => Box::new(SimpleTheme)
+  }
+}
+
+/// The index of the item picked among `items`
+pub fn select(prompt: &str, items: &[String], default: usize) -> Result<usize> {
+  if is_plain() {
+    return choose(&mut io::stdin().lock(), &mut io::stderr(), prompt, items, default);
+  }
+
+  Ok(
+    Select::with_theme(theme().as_ref())
+      .with_prompt(prompt)
+      .items(items)
+      .default(default)
+      .interact()?
+  )
+}
+
+/// Like `select`, filtering the items by typing
+pub fn fuzzy_select(prompt: &str, items: &[String], default: usize) -> Result<usize> {
+  if is_plain() {
+    return choose(&mut io::stdin().lock(), &mut io::stderr(), prompt, items, default);
+  }
+
+  Ok(
+    FuzzySelect::with_theme(theme().as_ref())
+      .with_prompt(prompt)
+      .items(items)
+      .default(default)
+      .interact()?
+  )
+}
+
+/// The indices of the items checked among `items`
+pub fn multi_select(prompt: &str, items: &[String]) -> Result<Vec<usize>> {
+  if is_plain() {
+    return choose_many(&mut io::stdin().lock(), &mut io::stderr(), prompt, items);
+  }
+
+  Ok(MultiSelect::with_theme(theme().as_ref()).with_prompt(prompt).items(items).interact()?)
+}
+
+/// `items` rearranged, as indices into the original order
+pub fn sort(prompt: &str, items: &[String]) -> Result<Vec<usize>> {
+  if is_plain() {
+    return order(&mut io::stdin().lock(), &mut io::stderr(), prompt, items);
+  }
+
+  Ok(Sort::with_theme(theme().as_ref()).with_prompt(prompt).items(items).interact()?)
+}
+
+pub fn confirm(prompt: &str, default: bool) -> Result<bool> {
+  if is_plain() {
+    return yes_or_no(&mut io::stdin().lock(), &mut io::stderr(), prompt, default);
+  }
+
+  Ok(Confirm::with_theme(theme().as_ref()).with_prompt(prompt).default(default).interact()?)
+}
+
+/// A line of text accepted by `validate`, or `default` when left empty
+pub fn text(prompt: &str, default: Option<&str>, validate: impl Fn(&str) -> std::result::Result<(), String>) -> Result<String> {
+  if is_plain() {
+    return line(&mut io::stdin().lock(), &mut io::stderr(), prompt, default, validate);
+  }
+
+  let theme = theme();
+  let mut input = Input::<String>::with_theme(theme.as_ref()).with_prompt(prompt).allow_empty(true);
+  if let Some(default) = default {
+    input = input.default(default.to_string());
+  }
+  Ok(input.validate_with(|text: &String| validate(text)).interact_text()?)
+}
+
+/// A line of text with its length shown against `max` while it is typed,
+/// like `Message [23/72]: …`. Up and down recall `history`, Tab asks
+/// `completion`, and Enter accepts the line once `validate` does.
+pub fn counted_text(
+  prompt: &str, max: usize, history: &mut impl History<String>, completion: &impl Completion,
+  validate: impl Fn(&str) -> std::result::Result<(), String>
+) -> Result<String> {
+  let term = Term::stderr();
+  let mut text = Vec::<char>::new();
+  let mut cursor = 0;
+  // The history entry shown, if any
+  let mut recalled: Option<usize> = None;
+
+  loop {
+    render(&term, prompt, max, &text, cursor)?;
+    match term.read_key()? {
+      Key::Enter => {
+        let line = text.iter().collect::<String>();
+        term.write_line("")?;
+        match validate(&line) {
+          Ok(()) => {
+            history.write(&line);
+            return Ok(line);
+          },
+          Err(error) => term.write_line(&style(error).red().for_stderr().to_string())?
+        }
+      },
+      Key::Char(c) if !c.is_control() => {
+        text.insert(cursor, c);
+        cursor += 1;
+      },
+      Key::Backspace if cursor > 0 => {
+        cursor -= 1;
+        text.remove(cursor);
+      },
+      Key::Del if cursor < text.len() => {
+        text.remove(cursor);
+      },
+      Key::ArrowLeft if cursor > 0 => cursor -= 1,
+      Key::ArrowRight if cursor < text.len() => cursor += 1,
+      Key::Home => cursor = 0,
+      Key::End => cursor = text.len(),
+      Key::ArrowUp => {
+        let pos = recalled.map_or(0, |pos| pos + 1);
+        if let Some(entry) = history.read(pos) {
+          recalled = Some(pos);
+          text = entry.chars().collect();
+          cursor = text.len();
+        }
+      },
+      Key::ArrowDown => {
+        recalled = recalled.and_then(|pos| pos.checked_sub(1));
+        text = recalled.and_then(|pos| history.read(pos)).unwrap_or_default().chars().collect();
+        cursor = text.len();
+      },
+      Key::Tab => {
+        if let Some(completed) = completion.get(&text.iter().collect::<String>()) {
+          text = completed.chars().collect();
+          cursor = text.len();
+        }
+      },
+      _ => {}
+    }
+  }
+}
+
+fn render(term: &Term, prompt: &str, max: usize, text: &[char], cursor: usize) -> io::Result<()> {
+  term.clear_line()?;
+  term.write_str(&format!("{} {}: {}", prompt, counter(text.len(), max), text.iter().collect::<String>()))?;
+  term.move_cursor_left(measure_text_width(&text[cursor..].iter().collect::<String>()))
+}
+
+/// `[length/max]`, in red once over the limit
+fn counter(length: usize, max: usize) -> String {
+  let counter = format!("[{}/{}]", length, max);
+  match length > max {
+    true => style(counter).red().for_stderr().to_string(),
+    false => style(counter).dim().for_stderr().to_string()
+  }
+}
+
+fn list(output: &mut impl Write, prompt: &str, items: &[String]) -> Result<()> {
+  writeln!(output, "{}", strip_ansi_codes(prompt))?;
+  for (n, item) in items.iter().enumerate() {
+    // Column padding only gets read out as silence
+    let item = strip_ansi_codes(item).split_whitespace().collect::<Vec<_>>().join(" ");
+    writeln!(output, "{:>3}. {}", n + 1, item)?;
+  }
+  Ok(())
+}
+
+/// Asks `question` and reads the answer, failing once stdin is closed
+fn ask(input: &mut impl BufRead, output: &mut impl Write, question: &str) -> Result<String> {
+  write!(output, "{}: ", strip_ansi_codes(question))?;
+  output.flush()?;
+
+  let mut answer = String::new();
+  if input.read_line(&mut answer)? == 0 {
+    bail!(t!("plain-no-answer"));
+  }
+  Ok(answer.trim().to_string())
+}
+
+/// Parses a 1-based item number
+fn number(answer: &str, count: usize) -> Option<usize> {
+  answer.parse::<usize>().ok().filter(|n| (1..=count).contains(n)).map(|n| n - 1)
+}
+
+fn choose(input: &mut impl BufRead, output: &mut impl Write, prompt: &str, items: &[String], default: usize) -> Result<usize> {
+  list(output, prompt, items)?;
+  loop {
+    let answer = ask(input, output, &t!("plain-choose", count = items.len(), default = default + 1))?;
+    if answer.is_empty() {
+      return Ok(default);
+    }
+    match number(&answer, items.len()) {
+      Some(n) => return Ok(n),
+      None => writeln!(output, "{}", t!("plain-invalid", count = items.len()))?
+    }
+  }
+}
+
+fn choose_many(input: &mut impl BufRead, output: &mut impl Write, prompt: &str, items: &[String]) -> Result<Vec<usize>> {
+  list(output, prompt, items)?;
+  loop {
+    let answer = ask(input, output, &t!("plain-choose-many"))?;
+    let chosen = answer
+      .split([' ', ','])
+      .filter(|n| !n.is_empty())
+      .map(|n| number(n, items.len()))
+      .collect::<Option<Vec<_>>>();
+    match chosen {
+      Some(mut chosen) => {
+        chosen.sort_unstable();
+        chosen.dedup();
+        return Ok(chosen);
+      },
+      None => writeln!(output, "{}", t!("plain-invalid", count = items.len()))?
+    }
+  }
+}
+
+fn order(input: &mut impl BufRead, output: &mut impl Write, prompt: &str, items: &[String]) -> Result<Vec<usize>> {
+  list(output, prompt, items)?;
+  loop {
+    let answer = ask(input, output, &t!("plain-order"))?;
+    if answer.is_empty() {
+      return Ok((0..items.len()).collect());
+    }
+
+    let order = answer
+      .split([' ', ','])
+      .filter(|n| !n.is_empty())
+      .map(|n| number(n, items.len()))
+      .collect::<Option<Vec<_>>>();
+    match order {
+      Some(order) if order.len() == items.len() && (0..items.len()).all(|n| order.contains(&n)) => return Ok(order),
+      _ => writeln!(output, "{}", t!("plain-invalid-order", count = items.len()))?
+    }
+  }
+}
+
+fn yes_or_no(input: &mut impl BufRead, output: &mut impl Write, prompt: &str, default: bool) -> Result<bool> {
+  let hint = if default { "Y/n" } else { "y/N" };
+  loop {
+    match ask(input, output, &format!("{} [{}]", prompt, hint))?.to_lowercase().as_str() {
+      "" => return Ok(default),
+      "y" | "yes" => return Ok(true),
+      "n" | "no" => return Ok(false),
+      _ => writeln!(output, "{}", t!("plain-yes-no"))?
+    }
+  }
+}
+
+fn line(
+  input: &mut impl BufRead, output: &mut impl Write, prompt: &str, default: Option<&str>,
+  validate: impl Fn(&str) -> std::result::Result<(), String>
+) -> Result<String> {
+  let question = match default {
+    Some(default) => format!("{} [{}]", prompt, default),
+    None => prompt.to_string()
+  };
+
+  loop {
+    let answer = match ask(input, output, &question)? {
+      answer if answer.is_empty() => default.unwrap_or_default().to_string(),
+      answer => answer
+    };
+    match validate(&answer) {
+      Ok(()) => return Ok(answer),
+      Err(error) => writeln!(output, "{}", error)?
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::Cursor;
+
+  fn items() -> Vec<String> {
+    vec!["Custom".to_string(), "\u{1b}[1mFix parser\u{1b}[0m".to_string(), "Add lists".to_string()]
+  }
+
+  #[test]
+  fn test_choose() -> Result<()> {
+    let mut output = Vec::new();
+    let selection = choose(&mut Cursor::new("7\nabc\n2\n"), &mut output, "Pick one", &items(), 0)?;
+    assert_eq!(selection, 1);
+
+    let output = String::from_utf8(output)?;
+    assert!(output.starts_with("Pick one\n  1. Custom\n  2. Fix parser\n  3. Add lists\n"));
+    assert!(!output.contains('\u{1b}'));
+
+    assert_eq!(choose(&mut Cursor::new("\n"), &mut Vec::new(), "Pick one", &items(), 2)?, 2);
+    assert!(choose(&mut Cursor::new(""), &mut Vec::new(), "Pick one", &items(), 0).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_choose_many_and_order() -> Result<()> {
+    assert_eq!(choose_many(&mut Cursor::new("3 1,3\n"), &mut Vec::new(), "Pick", &items())?, vec![0, 2]);
+    assert_eq!(choose_many(&mut Cursor::new("\n"), &mut Vec::new(), "Pick", &items())?, Vec::<usize>::new());
+    assert_eq!(order(&mut Cursor::new("1 2\n3 1 2\n"), &mut Vec::new(), "Order", &items())?, vec![2, 0, 1]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_yes_or_no_and_line() -> Result<()> {
+    assert!(!yes_or_no(&mut Cursor::new("maybe\nN\n"), &mut Vec::new(), "Sign?", true)?);
+    assert!(yes_or_no(&mut Cursor::new("\n"), &mut Vec::new(), "Sign?", true)?);
+
+    let not_empty = |text: &str| if text.is_empty() { Err("Empty".to_string()) } else { Ok(()) };
+    assert_eq!(line(&mut Cursor::new("\nFix it\n"), &mut Vec::new(), "Message", None, not_empty)?, "Fix it");
+    assert_eq!(line(&mut Cursor::new("\n"), &mut Vec::new(), "Amount", Some("3"), not_empty)?, "3");
+    Ok(())
+  }
+
+  #[test]
+  fn test_counter() {
+    assert_eq!(strip_ansi_codes(&counter(23, 72)), "[23/72]");
+    assert_eq!(strip_ansi_codes(&counter(80, 72)), "[80/72]");
+  }
+}
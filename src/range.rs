@@ -0,0 +1,107 @@
+use anyhow::{bail, Context, Result};
+use git2::{Oid, Repository, RevparseMode};
+use crate::i18n::t;
+use crate::rebase::Plan;
+use crate::SquashError;
+
+/// What to squash, as given on the command line
+#[derive(Clone, Debug, PartialEq)]
+pub enum Target {
+  /// The last N commits
+  Last(usize),
+  /// A range like `abc123..def456`, which may end below `HEAD`
+  Range(String)
+}
+
+impl Target {
+  pub fn parse(value: &str) -> Result<Self, String> {
+    if value.contains("..") {
+      return Ok(Target::Range(value.to_string()));
+    }
+
+    match value.parse::<usize>() {
+      Ok(amount) => Ok(Target::Last(amount)),
+      Err(_) => Err("expected a number of commits or a range like abc123..def456".to_string())
+    }
+  }
+}
+
+/// Commits of a `from..to` range on the current branch
+#[derive(Debug)]
+pub struct CommitRange {
+  pub from:      Oid,
+  /// The commits in the range, oldest first
+  pub commits:   Vec<Oid>,
+  /// Commits from the start of the range up to `HEAD`, which all get rewritten
+  pub rewritten: usize
+}
+
+/// Resolves `spec` and checks that it is an unbroken run of at least two
+/// commits in the history of `HEAD`
+pub fn resolve(repo: &Repository, spec: &str) -> Result<CommitRange> {
+  let revspec = repo.revparse(spec).with_context(|| format!("Failed to resolve range {}", spec))?;
+  let (Some(from), Some(to)) = (revspec.from(), revspec.to()) else {
+    bail!(t!("range-invalid", range = spec));
+  };
+  if !revspec.mode().contains(RevparseMode::RANGE) || revspec.mode().contains(RevparseMode::MERGE_BASE) {
+    bail!(t!("range-invalid", range = spec));
+  }
+
+  let from = from.peel_to_commit().context("Failed to resolve start of range")?.id();
+  let to = to.peel_to_commit().context("Failed to resolve end of range")?.id();
+  let head = repo
+    .head()
+    .context("Failed to get HEAD")?
+    .peel_to_commit()
+    .context("Failed to resolve HEAD")?
+    .id();
+  if to != head && !repo.graph_descendant_of(head, to).context("Failed to compare with HEAD")? {
+    bail!(t!("range-not-on-branch", range = spec));
+  }
+
+  let commits = Plan::from_range(repo, from, to)?.steps.iter().map(|step| step.commit).collect::<Vec<_>>();
+  let rewritten = Plan::from_range(repo, from, head)?.steps;
+  if !rewritten.iter().map(|step| step.commit).take(commits.len()).eq(commits.iter().copied()) {
+    bail!(t!("range-not-linear", range = spec));
+  }
+  if commits.len() < 2 {
+    bail!(SquashError::NothingToSquash(t!("range-too-small", range = spec, count = commits.len())));
+  }
+
+  Ok(CommitRange {
+    from,
+    commits,
+    rewritten: rewritten.len()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_parse_target() {
+    assert_eq!(Target::parse("3"), Ok(Target::Last(3)));
+    assert_eq!(Target::parse("HEAD~4..HEAD~1"), Ok(Target::Range("HEAD~4..HEAD~1".to_string())));
+    assert!(Target::parse("three").is_err());
+  }
+
+  #[test]
+  fn test_resolve_range() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(6)?;
+    let range = resolve(&repo, "HEAD~4..HEAD~1")?;
+
+    let subjects = range
+      .commits
+      .iter()
+      .map(|oid| Ok(repo.find_commit(*oid)?.summary().unwrap_or_default().to_string()))
+      .collect::<Result<Vec<_>>>()?;
+    assert_eq!(subjects, ["Commit 2", "Commit 3", "Commit 4"]);
+    assert_eq!(range.rewritten, 4);
+
+    assert!(resolve(&repo, "HEAD~1..HEAD~4").is_err());
+    assert!(resolve(&repo, "HEAD~2..HEAD~1").is_err());
+    Ok(())
+  }
+}
@@ -0,0 +1,893 @@
+use anyhow::{bail, Context, Result};
+use git2::build::CheckoutBuilder;
+use git2::{Commit, Oid, Repository, RepositoryState, ResetType, Signature, Sort, StatusOptions, Tree};
+use serde::{Deserialize, Serialize};
+use crate::dates::{Dates, Window};
+use crate::encoding;
+use crate::error::SquashError;
+use crate::finish::{Finish, Finished};
+use crate::i18n::t;
+use crate::identity;
+use crate::state::{self, State};
+use crate::transaction::Transaction;
+
+/// What to do with a commit when replaying a plan
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Action {
+  /// Keep the commit as is
+  Pick,
+  /// Keep the commit with a new message
+  Reword,
+  /// Fold into the previous commit, keeping both messages
+  Squash,
+  /// Fold into the previous commit, keeping only its message
+  Fixup
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Step {
+  pub action:  Action,
+  #[serde(with = "state::oid")]
+  pub commit:  Oid,
+  /// Replaces the message the step would otherwise produce
+  pub message: Option<String>
+}
+
+impl Action {
+  pub const ALL: [Action; 4] = [Action::Pick, Action::Reword, Action::Squash, Action::Fixup];
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      Action::Pick => "pick",
+      Action::Reword => "reword",
+      Action::Squash => "squash",
+      Action::Fixup => "fixup"
+    }
+  }
+
+  /// Whether the commit ends up merged into the previous one
+  pub fn folds(&self) -> bool {
+    matches!(self, Action::Squash | Action::Fixup)
+  }
+}
+
+impl Step {
+  pub fn new(action: Action, commit: Oid) -> Self {
+    Self {
+      action,
+      commit,
+      message: None
+    }
+  }
+}
+
+/// A sequence of steps replayed on top of `onto`, oldest commit first, in
+/// the spirit of a `git rebase -i` todo list
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Plan {
+  #[serde(with = "state::oid")]
+  pub onto:  Oid,
+  pub steps: Vec<Step>
+}
+
+impl Plan {
+  /// Plan picking every commit in `onto..head`, oldest first
+  pub fn from_range(repo: &Repository, onto: Oid, head: Oid) -> Result<Self> {
+    let mut revwalk = repo.revwalk().context("Failed to get revwalk")?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE).context("Failed to set sorting")?;
+    revwalk.push(head).context("Failed to push head")?;
+    revwalk.hide(onto).context("Failed to hide onto")?;
+
+    let steps = revwalk
+      .map(|oid| oid.map(|oid| Step::new(Action::Pick, oid)))
+      .collect::<Result<Vec<Step>, git2::Error>>()
+      .context("Failed to walk range")?;
+
+    Ok(Self {
+      onto,
+      steps
+    })
+  }
+
+  /// Moves every `fixup! <subject>` and `squash! <subject>` commit right
+  /// after the commit it names and folds it in, like `git rebase --autosquash`.
+  /// Targets are matched by subject, then by SHA prefix, then by subject
+  /// prefix. Returns the number of commits that will be folded.
+  pub fn autosquash(&mut self, repo: &Repository) -> Result<usize> {
+    let mut subjects = Vec::new();
+    for step in &self.steps {
+      let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+      subjects.push(encoding::summary(&commit));
+    }
+
+    let mut targets = vec![None; self.steps.len()];
+    for (n, subject) in subjects.iter().enumerate() {
+      let Some((action, wanted)) = autosquash_target(subject) else {
+        continue;
+      };
+
+      let earlier = &subjects[..n];
+      let target = earlier
+        .iter()
+        .position(|subject| subject == wanted)
+        .or_else(|| self.steps[..n].iter().position(|step| is_sha_prefix(wanted, step.commit)))
+        .or_else(|| earlier.iter().position(|subject| subject.starts_with(wanted)));
+      targets[n] = target.map(|target| (action, target));
+    }
+
+    // Each fold goes after its target and the folds placed there before it
+    let mut order = Vec::new();
+    for n in 0..self.steps.len() {
+      if targets[n].is_some() {
+        continue;
+      }
+
+      let mut group = vec![n];
+      let mut i = 0;
+      while i < group.len() {
+        let root = group[i];
+        group.extend((0..self.steps.len()).filter(|&m| targets[m].is_some_and(|(_, target)| target == root)));
+        i += 1;
+      }
+      group[1..].sort_unstable();
+      order.extend(group);
+    }
+
+    let folded = targets.iter().flatten().count();
+    self.reorder(&order);
+    for (step, n) in self.steps.iter_mut().zip(&order) {
+      if let Some((action, _)) = targets[*n] {
+        step.action = action;
+      }
+    }
+
+    Ok(folded)
+  }
+
+  /// Squashes each run of consecutive commits by the same author (after
+  /// `.mailmap`) into the first commit of the run, which keeps its author.
+  /// Returns the number of commits that will be folded.
+  pub fn group_by_author(&mut self, repo: &Repository) -> Result<usize> {
+    let mailmap = repo.mailmap().context("Failed to load mailmap")?;
+    let mut previous = None;
+    let mut folded = 0;
+
+    for step in &mut self.steps {
+      let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+      let author = commit.author_with_mailmap(&mailmap).context("Failed to resolve author")?;
+      let author = (author.name_bytes().to_vec(), author.email_bytes().to_ascii_lowercase());
+
+      if previous.as_ref() == Some(&author) {
+        step.action = Action::Squash;
+        folded += 1;
+      }
+      previous = Some(author);
+    }
+
+    Ok(folded)
+  }
+
+  /// Squashes each burst of commits made less than `window` after the one
+  /// before, going by the date `dates` picks, into the first commit of the
+  /// burst. Returns the number of commits that will be folded.
+  pub fn group_by_window(&mut self, repo: &Repository, window: Window, dates: Dates) -> Result<usize> {
+    let mut previous: Option<i64> = None;
+    let mut folded = 0;
+
+    for step in &mut self.steps {
+      let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+      let time = dates.time(&commit).seconds();
+      if previous.is_some_and(|previous| time - previous < window.seconds()) {
+        step.action = Action::Squash;
+        folded += 1;
+      }
+      previous = Some(time);
+    }
+
+    Ok(folded)
+  }
+
+  /// Moves the `commits` next to the oldest of them and squashes them into
+  /// it, leaving the other steps in their order
+  pub fn gather(&mut self, commits: &[Oid]) {
+    let (gathered, rest): (Vec<usize>, Vec<usize>) =
+      (0..self.steps.len()).partition(|&n| commits.contains(&self.steps[n].commit));
+    let Some(&first) = gathered.first() else {
+      return;
+    };
+
+    let mut order = rest.iter().copied().filter(|&n| n < first).collect::<Vec<_>>();
+    let start = order.len();
+    order.extend(&gathered);
+    order.extend(rest.iter().copied().filter(|&n| n > first));
+    self.reorder(&order);
+
+    for step in &mut self.steps[start + 1..start + gathered.len()] {
+      step.action = Action::Squash;
+    }
+  }
+
+  /// Rearranges the steps, where `order[n]` is the index of the step that
+  /// should end up at position `n`
+  pub fn reorder(&mut self, order: &[usize]) {
+    self.steps = order.iter().map(|&n| self.steps[n].clone()).collect();
+  }
+
+  /// Replays the plan without touching any ref, the index or the working
+  /// directory, returning the new tip
+  pub fn execute(&self, repo: &Repository) -> Result<Oid> {
+    self.validate()?;
+
+    match self.replay(repo, 0, self.onto)? {
+      Replay::Done(tip) => Ok(tip),
+      Replay::Conflict {
+        step,
+        paths,
+        ..
+      } => bail!(t!("replay-conflicts", commit = short(self.steps[step].commit), paths = paths.join(", ")))
+    }
+  }
+
+  fn validate(&self) -> Result<()> {
+    if let Some(step) = self.steps.first().filter(|step| step.action.folds()) {
+      bail!(t!("fold-first-step", commit = short(step.commit)));
+    }
+
+    Ok(())
+  }
+
+  /// The message of each commit the plan creates by folding or rewording,
+  /// oldest first, as replaying it would write them. Picked commits keep
+  /// the message they had and are left out.
+  pub fn messages(&self, repo: &Repository) -> Result<Vec<String>> {
+    let mut messages: Vec<(String, bool)> = Vec::new();
+    for step in &self.steps {
+      let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+      match messages.last_mut().filter(|_| step.action.folds()) {
+        Some((tip, created)) => {
+          *tip = folded_message(step, &message(&commit), tip);
+          *created = true;
+        },
+        None => {
+          let created = step.action == Action::Reword || step.message.is_some();
+          messages.push((step.message.clone().unwrap_or_else(|| message(&commit)), created));
+        }
+      }
+    }
+
+    Ok(messages.into_iter().filter(|(_, created)| *created).map(|(message, _)| message).collect())
+  }
+
+  /// Pairs each commit of the plan with the commit it became in the history
+  /// `new_head` ends, oldest first. Squashed and fixed up commits map to the
+  /// commit they were folded into.
+  pub fn rewritten(&self, repo: &Repository, new_head: Oid) -> Result<Vec<(Oid, Oid)>> {
+    let mut replayed = Vec::new();
+    let mut oid = new_head;
+    while oid != self.onto {
+      replayed.push(oid);
+      oid = repo
+        .find_commit(oid)
+        .context("Failed to find commit")?
+        .parent_id(0)
+        .context("Failed to find the start of the rewritten history")?;
+    }
+
+    let mut replayed = replayed.into_iter().rev();
+    let mut current = None;
+    let mut pairs = Vec::with_capacity(self.steps.len());
+    for step in &self.steps {
+      if current.is_none() || matches!(step.action, Action::Pick | Action::Reword) {
+        current = replayed.next();
+      }
+      pairs.push((step.commit, current.context("Rewritten history is shorter than the plan")?));
+    }
+
+    Ok(pairs)
+  }
+
+  /// Replays the steps from `start` on top of `tip` in memory, stopping at
+  /// the first step that conflicts
+  fn replay(&self, repo: &Repository, start: usize, tip: Oid) -> Result<Replay> {
+    let committer = identity::committer(repo)?;
+    let mut tip = repo.find_commit(tip).context("Failed to find onto commit")?;
+
+    for (n, step) in self.steps.iter().enumerate().skip(start) {
+      let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+      if commit.parent_count() > 1 {
+        bail!(t!("replay-merge", commit = short(commit.id())));
+      }
+
+      // Nothing changes underneath an untouched pick, so keep the original commit
+      if step.action == Action::Pick && step.message.is_none() && commit.parent_id(0).ok() == Some(tip.id()) {
+        tip = commit;
+        continue;
+      }
+
+      let mut index = repo.cherrypick_commit(&commit, &tip, 0, None).context("Failed to cherry-pick")?;
+      if index.has_conflicts() {
+        let paths = index
+          .conflicts()
+          .context("Failed to read conflicts")?
+          .filter_map(Result::ok)
+          .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+          .map(|entry| String::from_utf8_lossy(&entry.path).into_owned())
+          .collect::<Vec<String>>();
+        return Ok(Replay::Conflict {
+          step: n,
+          tip: tip.id(),
+          paths
+        });
+      }
+
+      let tree_id = index.write_tree_to(repo).context("Failed to write tree")?;
+      let tree = repo.find_tree(tree_id).context("Failed to find tree")?;
+      let oid = commit_step(repo, step, &commit, &tip, &tree, &committer)?;
+      tip = repo.find_commit(oid).context("Failed to find new commit")?;
+    }
+
+    Ok(Replay::Done(tip.id()))
+  }
+}
+
+/// Where replaying a plan ended up
+enum Replay {
+  /// Every step was replayed, giving this tip
+  Done(Oid),
+  /// Step `step` conflicts in `paths` when applied on `tip`
+  Conflict { step: usize, tip: Oid, paths: Vec<String> }
+}
+
+/// Creates the commit `step` makes from `commit`, with `tree` on top of `tip`
+fn commit_step(repo: &Repository, step: &Step, commit: &Commit, tip: &Commit, tree: &Tree, committer: &Signature) -> Result<Oid> {
+  match step.action {
+    Action::Pick | Action::Reword => {
+      let original = message(commit);
+      let message = step.message.as_deref().unwrap_or(&original);
+      repo.commit(None, &commit.author(), committer, message, tree, &[tip])
+    },
+    Action::Squash | Action::Fixup => {
+      let message = folded_message(step, &message(commit), &message(tip));
+      let parents = tip.parents().collect::<Vec<Commit>>();
+      repo.commit(None, &tip.author(), committer, &message, tree, &parents.iter().collect::<Vec<&Commit>>())
+    }
+  }
+  .context("Failed to create commit")
+}
+
+/// The message of `tip` once the commit with `folded` is folded into it by `step`
+fn folded_message(step: &Step, folded: &str, tip: &str) -> String {
+  if let Some(message) = &step.message {
+    return message.clone();
+  }
+
+  match (step.action, squashed_body(folded)) {
+    (Action::Squash, "") | (Action::Fixup, _) => tip.to_string(),
+    (_, body) => format!("{}\n\n{}", tip.trim_end(), body)
+  }
+}
+
+/// Executes `plan`, moves `HEAD` and the working directory to the result
+/// and does what `finish` asks for. Requires a clean working directory since
+/// the checkout replaces it. On a conflict the working directory is left at
+/// the conflicting step with the plan and `finish` saved, to be resumed with
+/// `resume` or given up with `abort`.
+pub fn run(repo: &Repository, plan: &Plan, reflog: &str, finish: &Finish) -> Result<Finished> {
+  ensure_clean(repo)?;
+  plan.validate()?;
+
+  let new_head = match plan.replay(repo, 0, plan.onto)? {
+    Replay::Done(new_head) => new_head,
+    Replay::Conflict {
+      step,
+      tip,
+      paths
+    } => {
+      let head = repo.head().context("Failed to get HEAD")?;
+      let state = State {
+        head_name: head.is_branch().then(|| head.name().map(str::to_string)).flatten(),
+        orig_head: head.peel_to_commit().context("Failed to resolve HEAD")?.id(),
+        plan:      plan.clone(),
+        next:      step,
+        reflog:    reflog.to_string(),
+        finish:    finish.clone()
+      };
+      return Err(stop(repo, &state, tip, &paths));
+    }
+  };
+
+  let transaction = Transaction::begin(repo)?;
+  let old_head = transaction.original();
+  let commit = repo.find_commit(new_head).context("Failed to find new HEAD")?;
+  repo
+    .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+    .context("Failed to check out result")?;
+  crate::sign::update_head(repo, new_head, reflog)?;
+
+  let rewritten = plan.rewritten(repo, new_head)?.into_iter().filter(|(old, new)| old != new).collect();
+  finish.complete(repo, transaction, old_head, new_head, rewritten)
+}
+
+/// Commits the resolved conflict of a stopped squash, replays the rest of
+/// its plan and finishes it like `run` would have
+pub fn resume(repo: &Repository) -> Result<Finished> {
+  let mut state = State::load(repo)?.with_context(|| t!("nothing-to-continue"))?;
+  let mut index = repo.index().context("Failed to get index")?;
+  if index.has_conflicts() {
+    bail!(t!("conflicts-unresolved"));
+  }
+
+  let tip = repo
+    .head()
+    .context("Failed to get HEAD")?
+    .peel_to_commit()
+    .context("Failed to resolve HEAD")?;
+  let step = &state.plan.steps[state.next];
+  let commit = repo.find_commit(step.commit).context("Failed to find commit")?;
+  let tree = repo
+    .find_tree(index.write_tree().context("Failed to write tree")?)
+    .context("Failed to find tree")?;
+  let committer = identity::committer(repo)?;
+  let resolved = commit_step(repo, step, &commit, &tip, &tree, &committer)?;
+  repo.set_head_detached(resolved).context("Failed to update HEAD")?;
+
+  let new_head = match state.plan.replay(repo, state.next + 1, resolved)? {
+    Replay::Done(new_head) => new_head,
+    Replay::Conflict {
+      step,
+      tip,
+      paths
+    } => {
+      state.next = step;
+      return Err(stop(repo, &state, tip, &paths));
+    }
+  };
+
+  let commit = repo.find_commit(new_head).context("Failed to find new HEAD")?;
+  repo
+    .checkout_tree(commit.as_object(), Some(CheckoutBuilder::new().safe()))
+    .context("Failed to check out result")?;
+  // Back where the squash started, so a failure below rolls back to there
+  match &state.head_name {
+    Some(name) => repo.set_head(name).context("Failed to update HEAD")?,
+    None => repo.set_head_detached(state.orig_head).context("Failed to update HEAD")?
+  }
+  let transaction = Transaction::begin(repo)?;
+  crate::sign::update_head(repo, new_head, &state.reflog)?;
+  State::remove(repo)?;
+
+  let rewritten = state
+    .plan
+    .rewritten(repo, new_head)?
+    .into_iter()
+    .filter(|(old, new)| old != new)
+    .collect();
+  state.finish.complete(repo, transaction, state.orig_head, new_head, rewritten)
+}
+
+/// Gives up a stopped squash, putting the branch, index and working
+/// directory back the way they were before it started
+pub fn abort(repo: &Repository) -> Result<Oid> {
+  let state = State::load(repo)?.with_context(|| t!("nothing-to-abort"))?;
+  match &state.head_name {
+    Some(name) => repo.set_head(name).context("Failed to restore HEAD")?,
+    None => repo.set_head_detached(state.orig_head).context("Failed to restore HEAD")?
+  }
+
+  let orig_head = repo.find_commit(state.orig_head).context("Failed to find original HEAD")?;
+  repo
+    .reset(orig_head.as_object(), ResetType::Hard, None)
+    .context("Failed to restore working directory")?;
+  repo.cleanup_state().context("Failed to clean up")?;
+  State::remove(repo)?;
+
+  Ok(state.orig_head)
+}
+
+/// Leaves the working directory at the conflicting step: `HEAD` is detached
+/// at `tip` and the step's changes are applied with conflict markers
+fn stop(repo: &Repository, state: &State, tip: Oid, paths: &[String]) -> anyhow::Error {
+  let stopped = (|| -> Result<()> {
+    let tip = repo.find_commit(tip).context("Failed to find commit")?;
+    repo
+      .checkout_tree(tip.as_object(), Some(CheckoutBuilder::new().safe()))
+      .context("Failed to check out conflicting step")?;
+    repo.set_head_detached(tip.id()).context("Failed to update HEAD")?;
+
+    let commit = repo.find_commit(state.plan.steps[state.next].commit).context("Failed to find commit")?;
+    repo.cherrypick(&commit, None).context("Failed to apply conflicting step")?;
+    // Only git-squash knows how to go on from here, not `git cherry-pick --continue`
+    repo.cleanup_state().context("Failed to clean up")?;
+    state.save(repo)
+  })();
+
+  let commit = short(state.plan.steps[state.next].commit);
+  match stopped {
+    Ok(()) => {
+      let conflicts = t!("replay-conflicts", commit = commit, paths = paths.join(", "));
+      anyhow::anyhow!("{}\n{}", conflicts, t!("replay-stopped"))
+    },
+    Err(error) => error.context(t!("replay-conflicts", commit = commit, paths = paths.join(", ")))
+  }
+}
+
+/// Fails when tracked files have staged or unstaged changes
+pub fn ensure_clean(repo: &Repository) -> Result<()> {
+  let mut options = StatusOptions::new();
+  options.include_untracked(false).include_ignored(false);
+
+  let statuses = repo.statuses(Some(&mut options)).context("Failed to get status")?;
+  if !statuses.is_empty() {
+    bail!(SquashError::DirtyWorktree);
+  }
+
+  Ok(())
+}
+
+/// Fails while git is in the middle of another operation, whose state a
+/// squash would otherwise get mixed up with
+pub fn ensure_idle(repo: &Repository) -> Result<()> {
+  if State::path(repo).exists() {
+    bail!(SquashError::Stopped);
+  }
+
+  let operation = match repo.state() {
+    RepositoryState::Clean => return Ok(()),
+    RepositoryState::Merge => "merge",
+    RepositoryState::Revert | RepositoryState::RevertSequence => "revert",
+    RepositoryState::CherryPick | RepositoryState::CherryPickSequence => "cherry-pick",
+    RepositoryState::Bisect => "bisect",
+    RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => "rebase",
+    RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => "git am"
+  };
+
+  bail!(SquashError::InProgress {
+    operation: operation.to_string()
+  });
+}
+
+/// The action and target subject of a `fixup! ` or `squash! ` commit,
+/// looking through repeated prefixes like `fixup! fixup! Add x`
+fn autosquash_target(subject: &str) -> Option<(Action, &str)> {
+  let (action, mut target) = match subject {
+    subject if subject.starts_with("fixup! ") => (Action::Fixup, &subject[7..]),
+    subject if subject.starts_with("squash! ") => (Action::Squash, &subject[8..]),
+    _ => return None
+  };
+
+  while let Some(rest) = target.strip_prefix("fixup! ").or_else(|| target.strip_prefix("squash! ")) {
+    target = rest;
+  }
+
+  Some((action, target.trim()))
+}
+
+fn is_sha_prefix(text: &str, oid: Oid) -> bool {
+  text.len() >= 4 && text.chars().all(|c| c.is_ascii_hexdigit()) && oid.to_string().starts_with(&text.to_lowercase())
+}
+
+/// The message a squashed commit adds, leaving out its `squash! ` subject
+/// line, which only names the commit it belongs to
+fn squashed_body(message: &str) -> &str {
+  match message.starts_with("squash! ") {
+    true => message.split_once('\n').map(|(_, body)| body.trim()).unwrap_or_default(),
+    false => message
+  }
+}
+
+fn message(commit: &Commit) -> String {
+  encoding::message(commit)
+}
+
+fn short(oid: Oid) -> String {
+  oid.to_string()[..7].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::dates::DateSource;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  /// Commits reachable from HEAD, oldest first
+  fn history(repo: &Repository) -> Result<Vec<Commit<'_>>> {
+    let mut revwalk = repo.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE)?;
+    revwalk.push_head()?;
+    Ok(revwalk.map(|oid| repo.find_commit(oid.unwrap()).unwrap()).collect())
+  }
+
+  #[test]
+  fn test_fixup_folds_into_predecessor() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(4)?;
+    let original = history(&repo)?;
+    let old_tree = repo.head()?.peel_to_tree()?.id();
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[3].id())?;
+    assert_eq!(plan.steps.len(), 3);
+    plan.steps[1].action = Action::Fixup;
+
+    run(&repo, &plan, "test: fixup", &Finish::default())?;
+    let rewritten = history(&repo)?;
+
+    assert_eq!(rewritten.len(), 3);
+    assert_eq!(rewritten[0].id(), original[0].id());
+    assert_eq!(rewritten[1].message(), Some("Commit 1"));
+    assert!(rewritten[1].tree()?.get_name("2.txt").is_some());
+    assert_eq!(rewritten[2].message(), Some("Commit 3"));
+    assert_eq!(repo.head()?.peel_to_tree()?.id(), old_tree);
+    Ok(())
+  }
+
+  #[test]
+  fn test_execute_leaves_head_alone() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let original = history(&repo)?;
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[2].id())?;
+    plan.steps[1].action = Action::Fixup;
+    let tip = repo.find_commit(plan.execute(&repo)?)?;
+
+    assert_eq!(tip.message(), Some("Commit 1"));
+    assert_eq!(tip.parent_id(0)?, original[0].id());
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), original[2].id(), "execute must not move HEAD");
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_and_reword() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(4)?;
+    let original = history(&repo)?;
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[3].id())?;
+    plan.steps[1].action = Action::Squash;
+    plan.steps[2].action = Action::Reword;
+    plan.steps[2].message = Some("Renamed".to_string());
+
+    let new_head = run(&repo, &plan, "test: squash", &Finish::default())?.new_head;
+    let rewritten = history(&repo)?;
+
+    assert_eq!(rewritten.len(), 3);
+    assert_eq!(rewritten[1].message(), Some("Commit 1\n\nCommit 2"));
+    assert_eq!(rewritten[2].message(), Some("Renamed"));
+    assert_eq!(plan.rewritten(&repo, new_head)?, [
+      (original[1].id(), rewritten[1].id()),
+      (original[2].id(), rewritten[1].id()),
+      (original[3].id(), rewritten[2].id())
+    ]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_reorder_then_squash() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(4)?;
+    let original = history(&repo)?;
+    let old_tree = repo.head()?.peel_to_tree()?.id();
+
+    // Move "Commit 3" before "Commit 2" and fold it into "Commit 1"
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[3].id())?;
+    plan.reorder(&[0, 2, 1]);
+    plan.steps[1].action = Action::Fixup;
+
+    run(&repo, &plan, "test: reorder", &Finish::default())?;
+    let rewritten = history(&repo)?;
+
+    assert_eq!(rewritten.len(), 3);
+    assert!(rewritten[1].tree()?.get_name("3.txt").is_some());
+    assert!(rewritten[1].tree()?.get_name("2.txt").is_none());
+    assert_eq!(rewritten[2].message(), Some("Commit 2"));
+    assert_eq!(repo.head()?.peel_to_tree()?.id(), old_tree);
+    Ok(())
+  }
+
+  #[test]
+  fn test_conflicting_reorder_is_detected() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "file.txt", "one", "One")?;
+    commit_file(&repo, "file.txt", "two", "Two")?;
+    commit_file(&repo, "file.txt", "three", "Three")?;
+    let original = history(&repo)?;
+    let head = original[3].id();
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), head)?;
+    plan.reorder(&[0, 2, 1]);
+
+    let error = run(&repo, &plan, "test: conflict", &Finish::default()).unwrap_err();
+    assert!(error.to_string().contains("file.txt"));
+    assert!(ensure_idle(&repo).is_err());
+
+    assert_eq!(abort(&repo)?, head);
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), head);
+    assert!(repo.head()?.is_branch());
+    assert_eq!(std::fs::read_to_string(repo.workdir().unwrap().join("file.txt"))?, "three");
+    ensure_idle(&repo)?;
+    Ok(())
+  }
+
+  #[test]
+  fn test_continue_after_resolving() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "file.txt", "one", "One")?;
+    commit_file(&repo, "file.txt", "two", "Two")?;
+    commit_file(&repo, "other.txt", "x", "Other")?;
+    let original = history(&repo)?;
+    repo.tag_lightweight("v1", original[3].as_object(), false)?;
+    let finish = Finish {
+      tags: vec!["v1".to_string()],
+      audit_log: Some(repo.path().join("audit.jsonl")),
+      count: 3,
+      message: "test: conflict".to_string(),
+      ..Finish::default()
+    };
+
+    // Moving "Two" before "One" conflicts, as "One" created the file
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[3].id())?;
+    plan.reorder(&[1, 0, 2]);
+    plan.steps[1].action = Action::Fixup;
+    assert!(run(&repo, &plan, "test: conflict", &finish).is_err());
+    assert!(repo.index()?.has_conflicts());
+    assert!(resume(&repo).is_err());
+
+    // Folding "One" into the resolved "Two" conflicts again
+    let workdir = repo.workdir().unwrap();
+    let resolve = || -> Result<()> {
+      std::fs::write(workdir.join("file.txt"), "resolved")?;
+      let mut index = repo.index()?;
+      index.add_path(std::path::Path::new("file.txt"))?;
+      index.write()?;
+      Ok(())
+    };
+    resolve()?;
+    assert!(resume(&repo).is_err());
+    resolve()?;
+
+    let finished = resume(&repo)?;
+    assert!(!State::path(&repo).exists());
+    assert!(repo.head()?.is_branch());
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), finished.new_head);
+    assert_eq!(finished.old_head, original[3].id());
+    assert_eq!(finished.rewritten.len(), 3);
+
+    // The squash ends as if it never stopped
+    assert_eq!(finished.tags, ["v1"]);
+    assert_eq!(repo.refname_to_id("refs/tags/v1")?, finished.new_head);
+    let log = std::fs::read_to_string(repo.path().join("audit.jsonl"))?;
+    assert!(log.contains(&format!("\"new_head\":\"{}\"", finished.new_head)));
+
+    let rewritten = history(&repo)?;
+    let messages = rewritten.iter().map(|commit| commit.message().unwrap()).collect::<Vec<_>>();
+    assert_eq!(messages, ["Commit 0", "Two", "Other"]);
+    assert_eq!(std::fs::read_to_string(workdir.join("file.txt"))?, "resolved");
+    assert!(workdir.join("other.txt").exists());
+    Ok(())
+  }
+
+  #[test]
+  fn test_ensure_idle_detects_merge() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    ensure_idle(&repo)?;
+
+    let head = repo.head()?.peel_to_commit()?;
+    std::fs::write(repo.path().join("MERGE_HEAD"), format!("{}\n", head.id()))?;
+    let error = ensure_idle(&repo).unwrap_err();
+    assert_eq!(error.to_string(), "A merge is in progress, finish or abort it first");
+    Ok(())
+  }
+
+  #[test]
+  fn test_autosquash() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let parser = commit_file(&repo, "parser.rs", "a", "Add parser")?;
+    commit_file(&repo, "lexer.rs", "a", "Add lexer")?;
+    commit_file(&repo, "parser.rs", "b", "fixup! Add parser")?;
+    commit_file(&repo, "lexer.rs", "b", "squash! Add lexer\n\nHandle tabs")?;
+    commit_file(&repo, "parser.rs", "c", &format!("fixup! {}", &parser.to_string()[..7]))?;
+    commit_file(&repo, "other.rs", "a", "fixup! Not in range")?;
+    let original = history(&repo)?;
+    let old_tree = repo.head()?.peel_to_tree()?.id();
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[6].id())?;
+    assert_eq!(plan.autosquash(&repo)?, 3);
+    run(&repo, &plan, "test: autosquash", &Finish::default())?;
+    let rewritten = history(&repo)?;
+
+    let messages = rewritten.iter().map(|commit| commit.message().unwrap()).collect::<Vec<_>>();
+    assert_eq!(messages, ["Commit 0", "Add parser", "Add lexer\n\nHandle tabs", "fixup! Not in range"]);
+    assert_eq!(
+      rewritten[1]
+        .tree()?
+        .get_name("parser.rs")
+        .unwrap()
+        .to_object(&repo)?
+        .peel_to_blob()?
+        .content(),
+      b"c"
+    );
+    assert_eq!(repo.head()?.peel_to_tree()?.id(), old_tree);
+    Ok(())
+  }
+
+  #[test]
+  fn test_group_by_author() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    for (author, file) in [("Ada", "a"), ("Ada", "b"), ("Linus", "c"), ("Ada", "d"), ("Ada", "e"), ("Ada", "f")] {
+      repo.config()?.set_str("user.name", author)?;
+      commit_file(&repo, file, file, &format!("Write {}", file))?;
+    }
+    let original = history(&repo)?;
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[6].id())?;
+    assert_eq!(plan.group_by_author(&repo)?, 3);
+    run(&repo, &plan, "test: group by author", &Finish::default())?;
+    let rewritten = history(&repo)?;
+
+    let authors = rewritten[1..]
+      .iter()
+      .map(|commit| commit.author().name().unwrap().to_string())
+      .collect::<Vec<_>>();
+    assert_eq!(authors, ["Ada", "Linus", "Ada"]);
+    assert_eq!(rewritten[3].message(), Some("Write d\n\nWrite e\n\nWrite f"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_group_by_window() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    for (minutes, file) in [(0, "a"), (10, "b"), (60, "c"), (61, "d")] {
+      commit_file(&repo, file, file, &format!("Write {}", file))?;
+      let author = Signature::new("Ada", "ada@example.com", &git2::Time::new(1_700_000_000 + minutes * 60, 0))?;
+      repo
+        .head()?
+        .peel_to_commit()?
+        .amend(Some("HEAD"), Some(&author), None, None, None, None)?;
+    }
+    let original = history(&repo)?;
+
+    // The commits were all made just now
+    let committed = Dates::default();
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[4].id())?;
+    assert_eq!(plan.group_by_window(&repo, Window(30 * 60), committed)?, 3);
+
+    let authored = Dates {
+      source: DateSource::Author,
+      ..Dates::default()
+    };
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[4].id())?;
+    assert_eq!(plan.group_by_window(&repo, Window(30 * 60), authored)?, 2);
+    assert_eq!(plan.messages(&repo)?, ["Write a\n\nWrite b", "Write c\n\nWrite d"]);
+    run(&repo, &plan, "test: group by window", &Finish::default())?;
+    let rewritten = history(&repo)?;
+
+    assert_eq!(rewritten.len(), 3);
+    assert_eq!(rewritten[1].message(), Some("Write a\n\nWrite b"));
+    assert_eq!(rewritten[2].message(), Some("Write c\n\nWrite d"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_gather() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(6)?;
+    let original = history(&repo)?;
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[5].id())?;
+    plan.gather(&[original[2].id(), original[4].id()]);
+    run(&repo, &plan, "test: gather", &Finish::default())?;
+    let rewritten = history(&repo)?;
+
+    let messages = rewritten.iter().map(|commit| commit.message().unwrap()).collect::<Vec<_>>();
+    assert_eq!(messages, ["Commit 0", "Commit 1", "Commit 2\n\nCommit 4", "Commit 3", "Commit 5"]);
+    Ok(())
+  }
+
+  #[test]
+  fn test_rejects_leading_fold() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let original = history(&repo)?;
+
+    let mut plan = Plan::from_range(&repo, original[0].id(), original[2].id())?;
+    plan.steps[0].action = Action::Fixup;
+    assert!(plan.execute(&repo).is_err());
+    Ok(())
+  }
+}
@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use crate::identity;
+
+pub const DEFAULT_MESSAGE: &str = "squash: collapsed {count} commits ({from}..{to})";
+
+/// The reflog entry for squashing `commits` (oldest first), filled in from
+/// `template` with `{count}`, `{from}` and `{to}`
+pub fn message(template: &str, commits: &[Oid]) -> String {
+  let short = |oid: Option<&Oid>| oid.map(|oid| oid.to_string()[..7].to_string()).unwrap_or_default();
+  template
+    .replace("{count}", &commits.len().to_string())
+    .replace("{from}", &short(commits.first()))
+    .replace("{to}", &short(commits.last()))
+}
+
+/// How long the reflogs of `HEAD` and the branch it points to were before a
+/// squash, so the entries written on the way can be replaced by one
+pub struct Mark(Vec<(String, usize)>);
+
+impl Mark {
+  pub fn capture(repo: &Repository) -> Result<Self> {
+    let head = repo.find_reference("HEAD").context("Failed to find HEAD")?;
+    let mut names = vec!["HEAD".to_string()];
+    names.extend(head.symbolic_target().map(str::to_string));
+
+    names
+      .into_iter()
+      .map(|name| {
+        let reflog = repo.reflog(&name).with_context(|| format!("Failed to read the reflog of {}", name))?;
+        Ok((name, reflog.len()))
+      })
+      .collect::<Result<_>>()
+      .map(Self)
+  }
+
+  /// Replaces the entries written since the mark, like the soft reset and
+  /// the commit, with a single `message` entry moving to `new_head`. Reflogs
+  /// git didn't write to, e.g. with `core.logAllRefUpdates` off, stay as they are.
+  pub fn collapse(&self, repo: &Repository, new_head: Oid, message: &str) -> Result<()> {
+    let signature = identity::committer(repo)?;
+    for (name, len) in &self.0 {
+      let mut reflog = repo.reflog(name).with_context(|| format!("Failed to read the reflog of {}", name))?;
+      if reflog.len() <= *len {
+        continue;
+      }
+
+      // The newest entry comes first
+      while reflog.len() > *len {
+        reflog.remove(0, false).context("Failed to remove reflog entry")?;
+      }
+      reflog
+        .append(new_head, &signature, Some(message))
+        .context("Failed to append reflog entry")?;
+      reflog.write().with_context(|| format!("Failed to write the reflog of {}", name))?;
+    }
+
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::squash::Commitable;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_message() {
+    let commits = [Oid::from_str("a1b2c3d4").unwrap(), Oid::from_str("d4e5f6a7").unwrap()];
+    assert_eq!(message(DEFAULT_MESSAGE, &commits), "squash: collapsed 2 commits (a1b2c3d..d4e5f6a)");
+    assert_eq!(message("squash {count}", &[]), "squash 0");
+  }
+
+  #[test]
+  fn test_collapse() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let old_head = repo.head()?.peel_to_commit()?;
+    let mark = Mark::capture(&repo)?;
+
+    let base = old_head.parent(0)?.parent(0)?;
+    repo.reset(base.as_object(), git2::ResetType::Soft, None)?;
+    let new_head = repo.commit_with_msg("Squashed")?;
+    mark.collapse(&repo, new_head, "squash: collapsed 2 commits")?;
+
+    for name in ["HEAD", repo.head()?.name().unwrap()] {
+      let reflog = repo.reflog(name)?;
+      assert_eq!(reflog.len(), 4, "{}", name);
+      let entry = reflog.get(0).unwrap();
+      assert_eq!(entry.message(), Some("squash: collapsed 2 commits"));
+      assert_eq!((entry.id_old(), entry.id_new()), (old_head.id(), new_head));
+    }
+    Ok(())
+  }
+}
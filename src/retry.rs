@@ -0,0 +1,88 @@
+use std::path::PathBuf;
+use std::thread::sleep;
+use std::time::Duration;
+use anyhow::{anyhow, Result};
+use git2::{ErrorCode, Repository};
+
+const LOCK_RETRIES: u32 = 5;
+const LOCK_BACKOFF: Duration = Duration::from_millis(50);
+
+/// Runs `operation`, retrying with exponential backoff while another process
+/// (an IDE, a background `git status`) holds one of the repository's lock files
+pub fn retry_on_lock<T>(repo: &Repository, mut operation: impl FnMut() -> Result<T, git2::Error>) -> Result<T> {
+  let mut delay = LOCK_BACKOFF;
+
+  for _ in 0..LOCK_RETRIES {
+    match operation() {
+      Err(error) if error.code() == ErrorCode::Locked => {
+        sleep(delay);
+        delay *= 2;
+      },
+      result => return Ok(result?)
+    }
+  }
+
+  operation().map_err(|error| {
+    if error.code() != ErrorCode::Locked {
+      return error.into();
+    }
+
+    match held_lock(repo) {
+      Some(path) => {
+        anyhow!(error).context(format!(
+          "{} is held by another git process; wait for it to finish or remove the file if it is stale",
+          path.display()
+        ))
+      },
+      None => anyhow!(error).context("A lock file is held by another git process")
+    }
+  })
+}
+
+/// The first lock file that currently exists among the ones a squash writes
+fn held_lock(repo: &Repository) -> Option<PathBuf> {
+  let git_dir = repo.path();
+  let mut candidates = vec![git_dir.join("index.lock"), git_dir.join("HEAD.lock")];
+  if let Some(name) = repo.head().ok().and_then(|head| head.name().map(str::to_string)) {
+    candidates.push(git_dir.join(format!("{}.lock", name)));
+  }
+
+  candidates.into_iter().find(|path| path.exists())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_retries_until_lock_is_released() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let lock = repo.path().join("index.lock");
+    std::fs::write(&lock, "")?;
+
+    let releaser = {
+      let lock = lock.clone();
+      std::thread::spawn(move || {
+        sleep(Duration::from_millis(120));
+        std::fs::remove_file(lock).unwrap();
+      })
+    };
+
+    let mut index = repo.index()?;
+    retry_on_lock(&repo, || index.write())?;
+    releaser.join().unwrap();
+    Ok(())
+  }
+
+  #[test]
+  fn test_names_lock_file_when_retries_run_out() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    std::fs::write(repo.path().join("index.lock"), "")?;
+
+    let mut index = repo.index()?;
+    let error = retry_on_lock(&repo, || index.write()).unwrap_err();
+    assert!(format!("{:#}", error).contains("index.lock"));
+    Ok(())
+  }
+}
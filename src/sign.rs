@@ -0,0 +1,213 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use anyhow::{bail, Context, Result};
+use git2::{Commit, Config, ObjectType, Oid, Repository, Signature, Tree};
+use crate::retry::retry_on_lock;
+use crate::i18n::t;
+use crate::identity;
+
+/// Creates a signed commit of the index on top of `HEAD` and moves `HEAD` to
+/// it, signing with the program and key configured for `git commit -S`
+pub fn commit_signed(repo: &Repository, message: &str) -> Result<Oid> {
+  let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+  let oid = create_signed(repo, message, &parent.iter().collect::<Vec<&Commit>>())?;
+
+  let summary = message.lines().next().unwrap_or_default();
+  update_head(repo, oid, &format!("commit: {}", summary))?;
+  Ok(oid)
+}
+
+/// Creates a signed commit of the index with the given parents, without
+/// updating any ref
+pub fn create_signed(repo: &Repository, message: &str, parents: &[&Commit]) -> Result<Oid> {
+  let mut index = repo.index().context("Failed to get index")?;
+  let oid = index.write_tree().context("Failed to write tree")?;
+  let tree = repo.find_tree(oid).context("Failed to find tree")?;
+  let author = identity::author(repo)?;
+  create_signed_with(repo, &author, message, &tree, parents)
+}
+
+/// Creates a signed commit of `tree` by `author`, committed by the current
+/// committer, without updating any ref
+pub fn create_signed_with(repo: &Repository, author: &Signature, message: &str, tree: &Tree, parents: &[&Commit]) -> Result<Oid> {
+  let committer = identity::committer(repo)?;
+  let buffer = repo
+    .commit_create_buffer(author, &committer, message, tree, parents)
+    .context("Failed to create commit buffer")?;
+  let content = buffer.as_str().context("Commit buffer is not valid UTF-8")?;
+  let gpgsig = sign_buffer(repo, &committer, content)?;
+  repo.commit_signed(content, &gpgsig, None).context("Failed to create signed commit")
+}
+
+/// Creates an annotated tag object for `target`, signed like `git tag -s`,
+/// without creating the ref
+pub fn create_signed_tag(repo: &Repository, name: &str, target: Oid, message: &str) -> Result<Oid> {
+  let tagger = identity::committer(repo)?;
+  let mut buffer = format!("object {}\ntype commit\ntag {}\ntagger {}\n\n{}", target, name, raw_signature(&tagger), message);
+  if !buffer.ends_with('\n') {
+    buffer.push('\n');
+  }
+
+  // Unlike commits, tags carry their signature at the end of the message
+  let signature = sign_buffer(repo, &tagger, &buffer)?;
+  buffer.push_str(&signature);
+  let odb = repo.odb().context("Failed to open object database")?;
+  odb.write(ObjectType::Tag, buffer.as_bytes()).context("Failed to write signed tag")
+}
+
+/// Points `HEAD` (or the branch it refers to) at `oid`
+pub fn update_head(repo: &Repository, oid: Oid, reflog: &str) -> Result<()> {
+  let head = repo.find_reference("HEAD").context("Failed to find HEAD")?;
+
+  match head.symbolic_target() {
+    Some(name) => retry_on_lock(repo, || repo.reference(name, oid, true, reflog).map(|_| ())),
+    None => retry_on_lock(repo, || repo.set_head_detached(oid))
+  }
+  .context("Failed to update HEAD")
+}
+
+/// Whether the commit carries a gpg, x509 or ssh signature
+pub fn is_signed(repo: &Repository, oid: Oid) -> bool {
+  repo.extract_signature(&oid, None).is_ok()
+}
+
+/// Whether `user.signingkey` is set, i.e. the user signs commits at least sometimes
+pub fn key_configured(repo: &Repository) -> bool {
+  repo.config().map(|config| config.get_string("user.signingkey").is_ok()).unwrap_or(false)
+}
+
+/// The program `git commit -S` signs with, as `gpg.format` chooses it
+pub fn program_for(repo: &Repository) -> Result<String> {
+  let config = repo.config().context("Failed to get config")?;
+  let format = config.get_string("gpg.format").unwrap_or_else(|_| "openpgp".to_string());
+  Ok(match format.as_str() {
+    "ssh" => program(&config, &["gpg.ssh.program"], "ssh-keygen"),
+    "x509" => program(&config, &["gpg.x509.program"], "gpgsm"),
+    "openpgp" => program(&config, &["gpg.openpgp.program", "gpg.program"], "gpg"),
+    other => bail!(t!("unsupported-gpg-format", format = other))
+  })
+}
+
+fn sign_buffer(repo: &Repository, committer: &Signature, buffer: &str) -> Result<String> {
+  let config = repo.config().context("Failed to get config")?;
+  let format = config.get_string("gpg.format").unwrap_or_else(|_| "openpgp".to_string());
+  let key = config.get_string("user.signingkey").ok();
+
+  match format.as_str() {
+    "ssh" => sign_ssh(repo, &config, key, buffer),
+    "x509" => {
+      let key = key.unwrap_or_else(|| committer_ident(committer));
+      sign_gpg(&program(&config, &["gpg.x509.program"], "gpgsm"), &key, buffer)
+    },
+    "openpgp" => {
+      let key = key.unwrap_or_else(|| committer_ident(committer));
+      sign_gpg(&program(&config, &["gpg.openpgp.program", "gpg.program"], "gpg"), &key, buffer)
+    },
+    other => bail!(t!("unsupported-gpg-format", format = other))
+  }
+}
+
+fn program(config: &Config, keys: &[&str], default: &str) -> String {
+  keys
+    .iter()
+    .find_map(|key| config.get_string(key).ok())
+    .unwrap_or_else(|| default.to_string())
+}
+
+fn committer_ident(committer: &Signature) -> String {
+  format!("{} <{}>", committer.name().unwrap_or_default(), committer.email().unwrap_or_default())
+}
+
+/// `Name <email> <seconds> <offset>`, as in commit and tag headers
+fn raw_signature(signature: &Signature) -> String {
+  let when = signature.when();
+  let offset = when.offset_minutes();
+  format!(
+    "{} {} {}{:02}{:02}",
+    committer_ident(signature),
+    when.seconds(),
+    if offset < 0 { '-' } else { '+' },
+    offset.abs() / 60,
+    offset.abs() % 60
+  )
+}
+
+fn sign_gpg(program: &str, key: &str, buffer: &str) -> Result<String> {
+  let output = run_signer(Command::new(program).args(["--status-fd=2", "-bsau", key]), buffer)
+    .with_context(|| format!("Failed to run {}", program))?;
+  let status = String::from_utf8_lossy(&output.stderr);
+
+  if !output.status.success() || !status.contains("[GNUPG:] SIG_CREATED ") {
+    bail!(t!("sign-failed", program = program, output = status.trim()));
+  }
+
+  String::from_utf8(output.stdout).context("Signature is not valid UTF-8")
+}
+
+fn sign_ssh(repo: &Repository, config: &Config, key: Option<String>, buffer: &str) -> Result<String> {
+  let program = program(config, &["gpg.ssh.program"], "ssh-keygen");
+  let key = key.with_context(|| t!("ssh-signing-key-missing"))?;
+  let literal = key.strip_prefix("key::").or_else(|| key.starts_with("ssh-").then_some(key.as_str()));
+
+  // A literal public key has to be written out so ssh-keygen can find it in the agent
+  let key_file = match literal {
+    Some(public_key) => {
+      let path = repo.path().join("squash-signing-key.pub");
+      std::fs::write(&path, public_key).context("Failed to write signing key")?;
+      path
+    },
+    None => key.clone().into()
+  };
+
+  let mut command = Command::new(&program);
+  command.args(["-Y", "sign", "-n", "git", "-f"]).arg(&key_file);
+  if literal.is_some() {
+    command.arg("-U");
+  }
+
+  let output = run_signer(&mut command, buffer);
+  if literal.is_some() {
+    let _ = std::fs::remove_file(&key_file);
+  }
+
+  let output = output.with_context(|| format!("Failed to run {}", program))?;
+  if !output.status.success() {
+    bail!(t!("sign-failed", program = program, output = String::from_utf8_lossy(&output.stderr).trim()));
+  }
+
+  String::from_utf8(output.stdout).context("Signature is not valid UTF-8")
+}
+
+fn run_signer(command: &mut Command, buffer: &str) -> std::io::Result<std::process::Output> {
+  let mut child = command.stdin(Stdio::piped()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+  child.stdin.take().expect("stdin is piped").write_all(buffer.as_bytes())?;
+  child.wait_with_output()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[cfg(unix)]
+  #[test]
+  fn test_commit_signed_with_fake_gpg() -> Result<()> {
+    let (dir, repo) = repo_with_commits(1)?;
+    let program = dir.path().join("fake-gpg");
+    std::fs::write(
+      &program,
+      "#!/bin/sh\ncat >/dev/null\necho '[GNUPG:] SIG_CREATED D' >&2\necho '-----BEGIN PGP SIGNATURE-----'\n"
+    )?;
+    std::fs::set_permissions(&program, std::os::unix::fs::PermissionsExt::from_mode(0o755))?;
+    repo.config()?.set_str("gpg.program", program.to_str().unwrap())?;
+
+    let oid = commit_signed(&repo, "Signed commit")?;
+    let (signature, _) = repo.extract_signature(&oid, None)?;
+
+    assert!(signature.as_str().unwrap().contains("BEGIN PGP SIGNATURE"));
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), oid);
+    assert!(is_signed(&repo, oid));
+    assert!(!is_signed(&repo, repo.find_commit(oid)?.parent_id(0)?));
+    Ok(())
+  }
+}
@@ -0,0 +1,1104 @@
+use anyhow::{bail, Context, Result};
+use git2::{Commit, ErrorCode, Oid, Repository, Sort, StashApplyOptions};
+use crate::backend::{Backends, GitBackend, Libgit2};
+use crate::config::Config;
+use crate::conventional;
+use crate::dates::{Dates, Window};
+use crate::encoding;
+use crate::error::SquashError;
+use crate::events::{Event, Observer, Silent};
+use crate::finish::{Finish, Finished};
+use crate::lock::OperationLock;
+use crate::policy::Subject;
+use crate::hooks::{self, Hook, HookEnv};
+use crate::i18n::t;
+use crate::identity;
+use crate::integrity;
+use crate::issues;
+use crate::lint;
+use crate::range::{self, CommitRange, Target};
+use crate::rebase::{self, Plan};
+use crate::reflog;
+use crate::retry::retry_on_lock;
+use crate::sign;
+use crate::state::State;
+use crate::tags;
+use crate::trailers;
+use crate::transaction::Transaction;
+use crate::wrap;
+
+pub trait Commitable {
+  fn commit_with_msg(&self, message: &str) -> Result<git2::Oid>;
+}
+
+impl Commitable for Repository {
+  fn commit_with_msg(&self, message: &str) -> Result<git2::Oid> {
+    let mut index = self.index().context("Failed to get index")?;
+    let oid = index.write_tree().context("Failed to write tree")?;
+    let author = identity::author(self)?;
+    let committer = identity::committer(self)?;
+    let tree = self.find_tree(oid).context("Failed to find tree")?;
+    let parent = self.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let parents = parent.iter().collect::<Vec<&Commit>>();
+
+    retry_on_lock(self, || self.commit(Some("HEAD"), &author, &committer, message, &tree, parents.as_slice()))
+      .context("Could not commit")
+  }
+}
+
+pub fn iter_topological_commits(
+  repo: &Repository, amount: usize
+) -> Result<impl Iterator<Item = Result<Commit<'_>, git2::Error>>> {
+  let mut revwalk = repo.revwalk().context("Failed to get revwalk")?;
+  revwalk.set_sorting(Sort::TOPOLOGICAL).context("Failed to set sorting")?;
+  revwalk.push_head().context("Failed to push HEAD")?;
+
+  Ok(revwalk.take(amount).map(|oid_result| oid_result.and_then(|oid| repo.find_commit(oid))))
+}
+
+/// The last commits and the commit they get squashed onto, found with a
+/// single walk and shared by everything that looks at the range
+#[derive(Clone, Debug, PartialEq)]
+pub struct Squashed {
+  /// Newest first
+  pub commits: Vec<Oid>,
+  /// `None` when the commits make up the entire history and the squash becomes the new root
+  pub base:    Option<Oid>
+}
+
+impl Squashed {
+  pub fn walk(repo: &Repository, backend: &dyn GitBackend, amount: usize) -> Result<Self> {
+    let mut commits = backend.walk(repo, amount.saturating_add(1))?;
+    let base = match commits.len() {
+      n if n > amount => commits.pop(),
+      n if n == amount && n > 0 && repo.find_commit(commits[n - 1]).is_ok_and(|root| root.parent_count() == 0) => None,
+      n => {
+        bail!(SquashError::NotEnoughCommits {
+          requested: amount, available: n
+        })
+      }
+    };
+
+    Ok(Self {
+      commits,
+      base
+    })
+  }
+
+  pub fn len(&self) -> usize {
+    self.commits.len()
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.commits.is_empty()
+  }
+
+  /// Looks up the commits, newest first
+  pub fn load<'repo>(&self, repo: &'repo Repository) -> Result<Vec<Commit<'repo>>> {
+    self.iter(repo).collect::<Result<_, _>>().context("Failed to find commit")
+  }
+
+  /// Looks up the commits one at a time, newest first, as the iterator is advanced
+  pub fn iter<'a, 'repo: 'a>(&'a self, repo: &'repo Repository) -> impl Iterator<Item = Result<Commit<'repo>, git2::Error>> + 'a {
+    self.commits.iter().map(move |oid| repo.find_commit(*oid))
+  }
+
+  /// The most recent commit, `HEAD` at the time of the walk
+  pub fn newest<'repo>(&self, repo: &'repo Repository) -> Result<Option<Commit<'repo>>> {
+    self
+      .commits
+      .first()
+      .map(|oid| repo.find_commit(*oid))
+      .transpose()
+      .context("Failed to find commit")
+  }
+
+  pub fn oldest<'repo>(&self, repo: &'repo Repository) -> Result<Option<Commit<'repo>>> {
+    self
+      .commits
+      .last()
+      .map(|oid| repo.find_commit(*oid))
+      .transpose()
+      .context("Failed to find commit")
+  }
+
+  /// Whether any of the commits is a merge, looking them up one at a time
+  pub fn has_merges(&self, repo: &Repository) -> Result<bool> {
+    for commit in self.iter(repo) {
+      if commit.context("Failed to find commit")?.parent_count() > 1 {
+        return Ok(true);
+      }
+    }
+
+    Ok(false)
+  }
+
+  pub fn base_commit<'repo>(&self, repo: &'repo Repository) -> Result<Option<Commit<'repo>>> {
+    self.base.map(|base| repo.find_commit(base)).transpose().context("Failed to find base")
+  }
+}
+
+/// The commit the last `amount` commits get squashed onto, or `None` when
+/// they make up the entire history and the squash becomes the new root
+pub fn find_base(repo: &Repository, amount: usize) -> Result<Option<Commit<'_>>> {
+  Squashed::walk(repo, &Libgit2, amount)?.base_commit(repo)
+}
+
+/// Like `find_base`, for operations that can't rewrite the root commit
+pub fn find_old_commit(repo: &Repository, amount: usize) -> Result<git2::Object<'_>> {
+  let base = find_base(repo, amount)?.with_context(|| t!("root-needs-plain-squash"))?;
+  Ok(base.into_object())
+}
+
+/// Squashes the `squashed` commits into one with `message`, restoring
+/// `HEAD` if anything goes wrong. Squashing down to the root always goes
+/// through libgit2, as there is nothing for `git reset --soft` to reset onto.
+pub fn git_soft_reset(
+  repo: &Repository, squashed: &Squashed, message: &str, sign: bool, reflog: &str, backend: &dyn GitBackend,
+  observer: &mut dyn Observer
+) -> Result<git2::Oid> {
+  let message = &normalize_message(message);
+  let transaction = Transaction::begin(repo)?;
+  let snapshot = integrity::Snapshot::capture(repo)?;
+  let mark = reflog::Mark::capture(repo)?;
+
+  // Dropping the transaction on error restores the original HEAD
+  let oid = match squashed.base {
+    Some(base) => {
+      backend.reset_soft(repo, base)?;
+      observer.notify(&Event::ResetPerformed(base));
+      backend.commit(repo, message, sign)
+    },
+    None => commit_root(repo, message, sign)
+  }
+  .with_context(|| format!("Failed to commit, HEAD was restored to {}", transaction.original()))?;
+
+  snapshot
+    .verify(repo, oid)
+    .with_context(|| format!("HEAD was restored to {}", transaction.original()))?;
+  mark.collapse(repo, oid, reflog)?;
+
+  transaction.commit();
+  observer.notify(&Event::CommitCreated(oid));
+  Ok(oid)
+}
+
+/// Squashing the whole history leaves nothing to reset onto, so the index
+/// is written as a parentless commit and `HEAD` moved to it directly
+pub fn commit_root(repo: &Repository, message: &str, sign: bool) -> Result<git2::Oid> {
+  let oid = match sign {
+    true => sign::create_signed(repo, message, &[])?,
+    false => {
+      let tree = repo
+        .index()
+        .context("Failed to get index")?
+        .write_tree()
+        .context("Failed to write tree")?;
+      let tree = repo.find_tree(tree).context("Failed to find tree")?;
+      let author = identity::author(repo)?;
+      let committer = identity::committer(repo)?;
+      repo.commit(None, &author, &committer, message, &tree, &[]).context("Could not commit")?
+    }
+  };
+
+  let summary = message.lines().next().unwrap_or_default();
+  sign::update_head(repo, oid, &format!("commit (initial): {}", summary))?;
+  Ok(oid)
+}
+
+/// Whether the squash commit would have the same tree as its parent, i.e.
+/// the squashed commits plus anything staged cancel each other out
+pub fn squash_is_empty(repo: &Repository, squashed: &Squashed) -> Result<bool> {
+  let tree = repo
+    .index()
+    .context("Failed to get index")?
+    .write_tree()
+    .context("Failed to write tree")?;
+  match squashed.base_commit(repo)? {
+    Some(base) => Ok(base.tree_id() == tree),
+    None => Ok(repo.find_tree(tree).context("Failed to find tree")?.is_empty())
+  }
+}
+
+pub fn commits(repo: &Repository, amount: usize) -> Result<Vec<Commit<'_>>> {
+  Ok(iter_topological_commits(repo, amount)?.filter_map(Result::ok).collect::<Vec<Commit>>())
+}
+
+pub fn commit_message(commit: Option<&Commit>) -> Result<String> {
+  Ok(encoding::message(commit.context("Failed to get commit")?))
+}
+
+/// Folds the `fixup!` and `squash!` commits among the last `amount` into
+/// their targets, returning how many were folded
+pub fn autosquash(repo: &Repository, amount: usize) -> Result<usize> {
+  let mut plan = plan_range(repo, amount)?;
+  let folded = plan.autosquash(repo)?;
+  if folded > 0 {
+    rebase::run(repo, &plan, &format!("squash: autosquashed {} commits", folded), &Finish::default())?;
+  }
+
+  Ok(folded)
+}
+
+/// Squashes the runs of commits by the same author among the last `amount`,
+/// returning how many commits were folded
+pub fn group_by_author(repo: &Repository, amount: usize) -> Result<usize> {
+  let mut plan = plan_range(repo, amount)?;
+  let folded = plan.group_by_author(repo)?;
+  if folded > 0 {
+    rebase::run(repo, &plan, &format!("squash: grouped {} commits by author", folded), &Finish::default())?;
+  }
+
+  Ok(folded)
+}
+
+/// Squashes the bursts of commits less than `window` apart among the last
+/// `amount` by the date `dates` picks, returning how many commits were folded
+pub fn group_by_window(repo: &Repository, amount: usize, window: Window, dates: Dates) -> Result<usize> {
+  let mut plan = plan_range(repo, amount)?;
+  let folded = plan.group_by_window(repo, window, dates)?;
+  if folded > 0 {
+    rebase::run(repo, &plan, &format!("squash: grouped {} commits within {}", folded, window), &Finish::default())?;
+  }
+
+  Ok(folded)
+}
+
+/// Plan picking each of the last `amount` commits
+pub fn plan_range(repo: &Repository, amount: usize) -> Result<Plan> {
+  let head = repo
+    .head()
+    .context("Failed to get HEAD")?
+    .peel_to_commit()
+    .context("Failed to resolve HEAD")?;
+  let onto = find_old_commit(repo, amount)?.id();
+  Plan::from_range(repo, onto, head.id())
+}
+
+/// A squash soft-resets `HEAD` and commits the index, neither of which a
+/// bare repository has
+pub fn ensure_worktree(repo: &Repository) -> Result<()> {
+  if repo.is_bare() {
+    bail!(SquashError::BareRepository {
+      path: repo.path().to_path_buf()
+    });
+  }
+
+  Ok(())
+}
+
+/// How many commits can be squashed in a shallow clone, where history ends
+/// at the fetch boundary and one commit has to remain as the new parent
+pub fn shallow_limit(repo: &Repository) -> Result<Option<usize>> {
+  if !repo.is_shallow() {
+    return Ok(None);
+  }
+
+  let available = iter_topological_commits(repo, usize::MAX)?.count();
+  Ok(Some(available.saturating_sub(1)))
+}
+
+/// How `squash` picks the message of the squash commit
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum MessageStrategy {
+  /// Reuse the message of the most recent commit
+  #[default]
+  Newest,
+  /// Reuse the message of the oldest squashed commit
+  Oldest,
+  /// Join the messages of the squashed commits, oldest first
+  Combined,
+  /// Use this message
+  Custom(String)
+}
+
+/// What `squash` should do. Start from `SquashOptions::new` and adjust the
+/// rest with the builder methods.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SquashOptions {
+  pub target:      Target,
+  pub message:     MessageStrategy,
+  pub sign:        bool,
+  /// Stash uncommitted changes first and restore them afterwards, keeping them out of the squash
+  pub autostash:   bool,
+  /// Run the pre-squash and post-squash hooks
+  pub hooks:       bool,
+  /// Move tags pointing at rewritten commits along instead of refusing
+  pub retag:       bool,
+  /// Create the squash commit even if it changes nothing
+  pub allow_empty: bool,
+  /// Flatten merge commits among the squashed ones instead of refusing
+  pub merges:      bool,
+  /// Work out the squash without rewriting anything
+  pub dry_run:     bool,
+  pub backend:     Backends,
+  /// Settings to use instead of the repository's, e.g. with command line flags merged in
+  pub config:      Option<Config>
+}
+
+impl SquashOptions {
+  pub fn new(target: Target) -> Self {
+    Self {
+      target,
+      message: MessageStrategy::default(),
+      sign: false,
+      autostash: false,
+      hooks: true,
+      retag: false,
+      allow_empty: false,
+      merges: false,
+      dry_run: false,
+      backend: Backends::default(),
+      config: None
+    }
+  }
+
+  pub fn message(mut self, message: MessageStrategy) -> Self {
+    self.message = message;
+    self
+  }
+
+  pub fn sign(mut self, sign: bool) -> Self {
+    self.sign = sign;
+    self
+  }
+
+  pub fn autostash(mut self, autostash: bool) -> Self {
+    self.autostash = autostash;
+    self
+  }
+
+  pub fn hooks(mut self, hooks: bool) -> Self {
+    self.hooks = hooks;
+    self
+  }
+
+  pub fn retag(mut self, retag: bool) -> Self {
+    self.retag = retag;
+    self
+  }
+
+  pub fn allow_empty(mut self, allow_empty: bool) -> Self {
+    self.allow_empty = allow_empty;
+    self
+  }
+
+  pub fn merges(mut self, merges: bool) -> Self {
+    self.merges = merges;
+    self
+  }
+
+  pub fn dry_run(mut self, dry_run: bool) -> Self {
+    self.dry_run = dry_run;
+    self
+  }
+
+  pub fn backend(mut self, backend: Backends) -> Self {
+    self.backend = backend;
+    self
+  }
+
+  pub fn config(mut self, config: Config) -> Self {
+    self.config = Some(config);
+    self
+  }
+}
+
+/// What `squash` did, or would have done in a dry run
+#[derive(Clone, Debug, PartialEq)]
+pub struct SquashOutcome {
+  pub old_head:  Oid,
+  /// The new `HEAD`, `None` after a dry run
+  pub new_head:  Option<Oid>,
+  /// The squashed commits, oldest first
+  pub squashed:  Vec<Oid>,
+  /// Each replaced commit and the commit it became, oldest first. Commits
+  /// replayed above a range show up here too.
+  pub rewritten: Vec<(Oid, Oid)>,
+  pub message:   String,
+  /// The tags moved along with their commits
+  pub tags:      Vec<String>,
+  /// What went wrong after the squash was made, like a failing post-squash
+  /// hook, for the caller to report
+  pub warnings:  Vec<String>
+}
+
+impl SquashOutcome {
+  /// The squash commit, which is the new `HEAD` unless commits were
+  /// replayed above it. `None` after a dry run.
+  pub fn commit(&self) -> Option<Oid> {
+    let oldest = self.squashed.first()?;
+    let replaced = self.rewritten.iter().find(|(old, _)| old == oldest);
+    replaced.map(|(_, new)| *new).or(self.new_head)
+  }
+}
+
+/// Squashes as described by `options` without asking anything, for programs
+/// embedding the library. Like the CLI, it holds the repository's squash
+/// lock, respects protected branches, tags, the message rules and the
+/// policy, and writes the audit log. When a range stops at a conflict,
+/// autostashed changes stay in the stash.
+pub fn squash(repo: &Repository, options: &SquashOptions) -> Result<SquashOutcome, SquashError> {
+  squash_with(repo, options, &mut Silent)
+}
+
+/// Like `squash`, reporting each step to `observer`
+pub fn squash_with(
+  repo: &Repository, options: &SquashOptions, observer: &mut dyn Observer
+) -> Result<SquashOutcome, SquashError> {
+  ensure_worktree(repo)?;
+  let _lock = OperationLock::acquire(repo)?;
+  rebase::ensure_idle(repo)?;
+  let head = repo.head()?;
+  let old_head = options.backend.resolve(repo, "HEAD")?;
+  let config = match &options.config {
+    Some(config) => config.clone(),
+    None => Config::load(repo)?
+  };
+  let branch = head.shorthand().filter(|_| head.is_branch());
+  if let Some(branch) = branch.filter(|branch| config.is_protected(branch)) {
+    return Err(SquashError::ProtectedBranch {
+      branch: branch.to_string()
+    });
+  }
+
+  let (range, selected) = match &options.target {
+    Target::Last(amount) => {
+      if let Some(limit) = shallow_limit(repo)?.filter(|limit| amount > limit) {
+        return Err(SquashError::ShallowClone {
+          limit,
+          requested: *amount
+        });
+      }
+      (None, Squashed::walk(repo, &options.backend, *amount)?)
+    },
+    Target::Range(spec) => {
+      if options.sign {
+        return Err(SquashError::Refused(t!("range-sign-unsupported")));
+      }
+      let range = range::resolve(repo, spec)?;
+      let selected = Squashed {
+        commits: range.commits.iter().rev().copied().collect(), base: Some(range.from)
+      };
+      (Some(range), selected)
+    }
+  };
+  let squashed = selected.commits.iter().rev().copied().collect::<Vec<_>>();
+  let subject = Subject {
+    branch,
+    count: squashed.len(),
+    message: None
+  };
+  config.policy.enforce(&subject)?;
+  observer.notify(&Event::CommitsEnumerated(squashed.clone()));
+
+  // Commits replayed above a range are rewritten too
+  let rewritten = match &range {
+    Some(range) => options.backend.walk(repo, range.rewritten)?,
+    None => selected.commits.clone()
+  };
+  let tags = tags::pointing_into(repo, &rewritten)?;
+  tags::ensure_rewritable(&tags, options.retag)?;
+  if !options.merges && selected.has_merges(repo)? {
+    return Err(SquashError::Refused(t!("merges-need-confirmation", count = squashed.len())));
+  }
+  if !options.allow_empty && is_empty(repo, &selected, range.as_ref(), options.autostash)? {
+    return Err(SquashError::Refused(t!("empty-squash", count = squashed.len())));
+  }
+
+  let commits = selected.load(repo)?;
+  let message = match &options.message {
+    MessageStrategy::Newest => commit_message(commits.first())?,
+    MessageStrategy::Oldest => commit_message(commits.last())?,
+    MessageStrategy::Combined => combined_message(&commits.into_iter().rev().collect::<Vec<_>>()),
+    MessageStrategy::Custom(message) => message.clone()
+  };
+  let message = normalize_message(&message);
+  let message = match config.wrap() {
+    true => wrap::body(&message, wrap::BODY_WIDTH),
+    false => message
+  };
+  let message = issues::collect(repo, &selected.commits, &message)?;
+  let message = trailers::co_authors(repo, &selected.commits, &message, config.co_authors())?;
+  let message = trailers::change_id(repo, &selected.commits, &message)?;
+  check_message(repo, &config, &subject, &message)?;
+
+  if options.dry_run {
+    return Ok(SquashOutcome {
+      old_head,
+      new_head: None,
+      squashed,
+      rewritten: Vec::new(),
+      message,
+      tags: Vec::new(),
+      warnings: Vec::new()
+    });
+  }
+
+  let hook_env = HookEnv {
+    base: selected.base,
+    tip: selected.commits.first().copied().unwrap_or(old_head),
+    old_head,
+    new_head: None,
+    count: squashed.len(),
+    message: &message
+  };
+  if options.hooks && hooks::run(repo, &config.hooks, Hook::PreSquash, &hook_env).map_err(SquashError::HookRejected)? {
+    observer.notify(&Event::HookRan(Hook::PreSquash));
+  }
+
+  let reflog = reflog::message(config.reflog_message(), &squashed);
+  let stashed = options.autostash && stash(repo)?;
+  let finish = Finish {
+    tags: tags.into_iter().map(|tag| tag.name).collect(),
+    hooks: options.hooks.then(|| config.hooks.clone()),
+    base: hook_env.base,
+    tip: Some(hook_env.tip),
+    unstash: stashed,
+    ..Finish::new(repo, &config, branch, squashed.len(), &message)
+  };
+  let result = match &range {
+    Some(range) => {
+      squash_range(repo, range, message.clone(), &reflog, &finish).inspect(|finished| {
+        let squash = finished
+          .rewritten
+          .iter()
+          .find(|(old, _)| *old == range.commits[0])
+          .map_or(finished.new_head, |(_, new)| *new);
+        observer.notify(&Event::CommitCreated(squash));
+      })
+    },
+    None => {
+      Transaction::begin(repo).and_then(|transaction| {
+        let new_head = git_soft_reset(repo, &selected, &message, options.sign, &reflog, &options.backend, observer)?;
+        let rewritten = squashed.iter().map(|oid| (*oid, new_head)).collect();
+        finish.complete(repo, transaction, old_head, new_head, rewritten)
+      })
+    },
+  };
+  let finished = match result {
+    Ok(finished) => finished,
+    // A squash stopped at a conflict restores the stash once it's continued
+    Err(error) if stashed && !State::path(repo).exists() => {
+      return Err(
+        match unstash(repo) {
+          Ok(()) => error,
+          Err(_) => error.context(t!("autostash-kept"))
+        }
+        .into()
+      );
+    },
+    Err(error) => return Err(error.into())
+  };
+  observer.notify(&Event::CommitsRewritten(finished.rewritten.clone()));
+  if finished.hook_ran {
+    observer.notify(&Event::HookRan(Hook::PostSquash));
+  }
+
+  Ok(SquashOutcome {
+    old_head,
+    new_head: Some(finished.new_head),
+    squashed,
+    rewritten: finished.rewritten,
+    message,
+    tags: finished.tags,
+    warnings: finished.warnings
+  })
+}
+
+/// Refuses `message` for a commit a squash creates when it breaks one of
+/// the configured message rules, before any history is rewritten
+pub fn check_message(repo: &Repository, config: &Config, subject: &Subject, message: &str) -> Result<()> {
+  if config.conventional() {
+    conventional::validate(message, config.max_subject_length())?;
+  }
+
+  if let Some(command) = &config.lint_cmd {
+    lint::run(repo, command, message)?;
+  }
+
+  if config.require_signoff() {
+    trailers::ensure_signoff(repo, message)?;
+  }
+
+  config.policy.enforce(&Subject {
+    message: Some(message),
+    ..*subject
+  })
+}
+
+/// Whether squashing `selected` changes nothing. A range is squashed from
+/// its commits alone, and autostashed changes are kept out of the squash.
+fn is_empty(repo: &Repository, selected: &Squashed, range: Option<&CommitRange>, autostash: bool) -> Result<bool> {
+  let tip = match (range, autostash) {
+    (Some(range), _) => range.commits.last().copied(),
+    (None, true) => selected.commits.first().copied(),
+    (None, false) => return squash_is_empty(repo, selected)
+  };
+  let tip = repo.find_commit(tip.context("Nothing squashed")?).context("Failed to find commit")?;
+  match selected.base_commit(repo)? {
+    Some(base) => Ok(base.tree_id() == tip.tree_id()),
+    None => Ok(tip.tree().context("Failed to get commit tree")?.is_empty())
+  }
+}
+
+/// The messages of `commits` joined by blank lines, in the given order
+pub fn combined_message(commits: &[Commit]) -> String {
+  commits
+    .iter()
+    .map(|commit| encoding::message(commit).trim_end().to_string())
+    .collect::<Vec<_>>()
+    .join("\n\n")
+}
+
+/// `message` with Windows line endings turned into `\n`, trailing whitespace
+/// stripped from every line and no blank lines around it, like git's
+/// `commit.cleanup=whitespace`
+pub fn normalize_message(message: &str) -> String {
+  message
+    .replace("\r\n", "\n")
+    .replace('\r', "\n")
+    .lines()
+    .map(str::trim_end)
+    .collect::<Vec<_>>()
+    .join("\n")
+    .trim_matches('\n')
+    .to_string()
+}
+
+/// Squashes `range`, which may end below `HEAD`, into one commit with
+/// `message`, replays the commits above it on top of the result and does
+/// what `finish` asks for
+pub fn squash_range(repo: &Repository, range: &CommitRange, message: String, reflog: &str, finish: &Finish) -> Result<Finished> {
+  let head = repo
+    .head()
+    .context("Failed to get HEAD")?
+    .peel_to_commit()
+    .context("Failed to resolve HEAD")?;
+  let mut plan = Plan::from_range(repo, range.from, head.id())?;
+  squash_in_plan(repo, &mut plan, &range.commits, message, reflog, finish)
+}
+
+/// Gathers `commits` in `plan` and squashes them into the oldest with
+/// `message`, then runs the plan
+pub fn squash_in_plan(
+  repo: &Repository, plan: &mut Plan, commits: &[Oid], message: String, reflog: &str, finish: &Finish
+) -> Result<Finished> {
+  plan.gather(commits);
+  if let Some(last) = plan.steps.iter_mut().find(|step| Some(&step.commit) == commits.last()) {
+    last.message = Some(normalize_message(&message));
+  }
+
+  rebase::run(repo, plan, reflog, finish)
+}
+
+/// Stashes staged and unstaged changes to tracked files, returning whether
+/// there were any
+fn stash(repo: &Repository) -> Result<bool> {
+  // Stashing needs a mutable handle, which callers shouldn't have to give up
+  let mut handle = Repository::open(repo.path()).context("Failed to open repo")?;
+  let signature = identity::committer(repo)?;
+  match handle.stash_save(&signature, "git-squash autostash", None) {
+    Ok(_) => Ok(true),
+    Err(error) if error.code() == ErrorCode::NotFound => Ok(false),
+    Err(error) => Err(error).context("Failed to stash changes")
+  }
+}
+
+pub(crate) fn unstash(repo: &Repository) -> Result<()> {
+  let mut handle = Repository::open(repo.path()).context("Failed to open repo")?;
+  let mut options = StashApplyOptions::new();
+  options.reinstantiate_index();
+  handle.stash_pop(0, Some(&mut options)).context("Failed to restore stashed changes")
+}
+
+#[cfg(test)]
+mod tests {
+  use std::fs::File;
+  use std::io::Write;
+  use super::*;
+  use crate::backend::Libgit2;
+  use git2::IndexAddOption;
+  use log::{info, LevelFilter};
+  use tempdir::TempDir;
+
+  #[test]
+  fn test_get_commits() {
+    let repo = Repository::init("temp_test_repo2").unwrap();
+    let commit_id = repo.commit_with_msg("This is a test commit".as_ref()).unwrap();
+    let commit = repo.find_commit(commit_id).unwrap();
+    let commits = commits(&repo, 1).unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0].id(), commit.id());
+
+    // Cleanup
+    std::fs::remove_dir_all("temp_test_repo2").unwrap();
+  }
+
+  #[test]
+  fn test_commit_to_string() {
+    let repo = Repository::init("temp_test_repo3").unwrap();
+    let commit_id = repo.commit_with_msg("This is a test commit".as_ref()).unwrap();
+    let commit = repo.find_commit(commit_id).unwrap();
+    let commit_string = commit.summary().unwrap_or_default().to_string();
+    assert_eq!(commit_string, "This is a test commit");
+
+    // Cleanup
+    std::fs::remove_dir_all("temp_test_repo3").unwrap();
+  }
+
+  // Create a test case using TempDir
+  #[test]
+  fn test_find_old_commit() -> Result<()> {
+    let dir = TempDir::new("temp_test_repo4").unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+
+    for n in 0..10 {
+      let name = format!("{}.txt", n);
+      let file_path = dir.path().join(name.clone());
+      let mut file = File::create(file_path).context("Failed to create file")?;
+      let content = format!("{}", n);
+      file.write_all(content.as_bytes()).context("Failed to write file")?;
+      let message = format!("Commit {}", n);
+      let mut index = repo.index().context("Failed to get index")?;
+      index.add_all([name], IndexAddOption::DEFAULT, None).context("Failed to add file")?;
+      repo.commit_with_msg(message.as_ref()).context("Failed to commit")?;
+    }
+
+    let old_tree = repo.head().unwrap().peel_to_tree().unwrap();
+    let new_commit = "New commit".to_string();
+    git_soft_reset(&repo, &Squashed::walk(&repo, &Libgit2, 5)?, &new_commit, false, "squash", &Libgit2, &mut Silent)
+      .context("Failed to squash commits")?;
+    let all_commits = commits(&repo, 10).unwrap();
+    assert_eq!(all_commits.len(), 6);
+
+    assert!(all_commits[0].message().unwrap().contains("New commit"));
+    assert!(all_commits[1].message().unwrap().contains("4"));
+    assert!(all_commits[2].message().unwrap().contains("3"));
+    assert!(all_commits[3].message().unwrap().contains("2"));
+    assert!(all_commits[4].message().unwrap().contains("1"));
+    assert!(all_commits[5].message().unwrap().contains("0"));
+
+    /* Check that all files exists */
+    for n in 0..10 {
+      let name = format!("{}.txt", n);
+      let file_path = dir.path().join(name.clone());
+      assert!(file_path.exists());
+    }
+
+    let new_tree = repo.head().unwrap().peel_to_tree().unwrap();
+    let diff = repo.diff_tree_to_tree(Some(&old_tree), Some(&new_tree), None)?;
+
+    env_logger::builder()
+      .filter_level(LevelFilter::Debug)
+      .format_target(false)
+      .format_timestamp(None)
+      .init();
+
+    diff.print(git2::DiffFormat::Patch, |delta, _hunk, line| {
+      let old = delta.old_file().path().unwrap();
+      let new = delta.new_file().path().unwrap();
+      info!("{} {}", old.display(), new.display());
+      info!("{}", String::from_utf8_lossy(line.content()));
+      true
+    })?;
+
+    Ok(())
+  }
+
+  #[test]
+  fn test_normalize_message() {
+    let message = "Fix parser  \r\n\r\nHandle CRLF\t\r\nand lone\rreturns\r\n\r\n";
+    assert_eq!(normalize_message(message), "Fix parser\n\nHandle CRLF\nand lone\nreturns");
+    assert_eq!(normalize_message("\n\nSubject\n"), "Subject");
+  }
+
+  #[test]
+  fn test_squash_with_windows_message() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(3)?;
+    let squashed = Squashed::walk(&repo, &Libgit2, 2)?;
+    let new_head = git_soft_reset(&repo, &squashed, "Subject \r\n\r\nBody\r\n", false, "squash", &Libgit2, &mut Silent)?;
+    assert_eq!(repo.find_commit(new_head)?.message(), Some("Subject\n\nBody"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_detached_head() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(4)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let branch = repo.head()?.name().unwrap().to_string();
+    repo.set_head_detached(head.id())?;
+
+    let new_head =
+      git_soft_reset(&repo, &Squashed::walk(&repo, &Libgit2, 2)?, "Squashed", false, "squash", &Libgit2, &mut Silent)?;
+    assert!(repo.head_detached()?);
+    assert_eq!(repo.head()?.target(), Some(new_head));
+    assert_eq!(repo.find_commit(new_head)?.parent(0)?.message(), Some("Commit 1"));
+    assert_eq!(repo.find_reference(&branch)?.target(), Some(head.id()), "branch must stay put");
+    Ok(())
+  }
+
+  #[test]
+  fn test_bare_repo_is_rejected() -> Result<()> {
+    let dir = TempDir::new("bare")?;
+    let repo = Repository::init_bare(dir.path())?;
+    assert!(ensure_worktree(&repo).unwrap_err().to_string().contains("bare repository"));
+    Ok(())
+  }
+
+  #[test]
+  fn test_shallow_limit() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(5)?;
+    assert_eq!(shallow_limit(&repo)?, None);
+
+    // Pretend "Commit 2" is the fetch boundary of a shallow clone
+    let boundary = repo.revparse_single("HEAD~2")?.id();
+    std::fs::write(repo.path().join("shallow"), format!("{}\n", boundary))?;
+    let repo = Repository::open(repo.path())?;
+    assert_eq!(shallow_limit(&repo)?, Some(2));
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_down_to_root() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(3)?;
+    let old_tree = repo.head()?.peel_to_tree()?.id();
+
+    let new_head =
+      git_soft_reset(&repo, &Squashed::walk(&repo, &Libgit2, 3)?, "Everything", false, "squash", &Libgit2, &mut Silent)?;
+    let root = repo.find_commit(new_head)?;
+    assert_eq!(root.parent_count(), 0);
+    assert_eq!(root.tree_id(), old_tree);
+    assert_eq!(commits(&repo, 10)?.len(), 1);
+
+    assert!(Squashed::walk(&repo, &Libgit2, 2).is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_detects_empty_squash() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(2)?;
+    crate::testing::commit_file(&repo, "0.txt", "changed", "Change")?;
+    assert!(!squash_is_empty(&repo, &Squashed::walk(&repo, &Libgit2, 1)?)?);
+
+    crate::testing::commit_file(&repo, "0.txt", "0", "Revert change")?;
+    assert!(squash_is_empty(&repo, &Squashed::walk(&repo, &Libgit2, 2)?)?);
+    assert!(!squash_is_empty(&repo, &Squashed::walk(&repo, &Libgit2, 3)?)?);
+    Ok(())
+  }
+
+  #[test]
+  fn test_commit_enumeration() -> Result<()> {
+    let dir = TempDir::new("temp_test_repo_commit_enumeration").unwrap();
+    let repo = Repository::init(dir.path()).unwrap();
+    for n in 0..3 {
+      let name = format!("{}.txt", n);
+      let file_path = dir.path().join(name.clone());
+      let mut file = File::create(file_path).context("Failed to create file")?;
+      let content = format!("{}", n);
+      file.write_all(content.as_bytes()).context("Failed to write file")?;
+      let message = format!("Commit {}", n);
+      let mut index = repo.index().context("Failed to get index")?;
+      index.add_all([name], IndexAddOption::DEFAULT, None).context("Failed to add file")?;
+      repo.commit_with_msg(message.as_ref()).context("Failed to commit")?;
+    }
+    let commits_list = commits(&repo, 3)?;
+    assert_eq!(commits_list.len(), 3);
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_with_options() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(4)?;
+    let old_head = repo.head()?.peel_to_commit()?.id();
+
+    let options = SquashOptions::new(Target::Last(3)).message(MessageStrategy::Combined).dry_run(true);
+    let outcome = squash(&repo, &options)?;
+    assert_eq!((outcome.old_head, outcome.new_head, outcome.squashed.len()), (old_head, None, 3));
+    assert_eq!(outcome.message, "Commit 1\n\nCommit 2\n\nCommit 3");
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), old_head);
+
+    let mut events = Vec::new();
+    let outcome = squash_with(&repo, &options.dry_run(false), &mut |event: &Event| events.push(event.clone()))?;
+    let head = repo.head()?.peel_to_commit()?;
+    assert_eq!(outcome.new_head, Some(head.id()));
+    assert_eq!(events, [
+      Event::CommitsEnumerated(outcome.squashed.clone()),
+      Event::ResetPerformed(head.parent_id(0)?),
+      Event::CommitCreated(head.id()),
+      Event::CommitsRewritten(outcome.rewritten.clone())
+    ]);
+    assert_eq!(outcome.rewritten, outcome.squashed.iter().map(|oid| (*oid, head.id())).collect::<Vec<_>>());
+    assert_eq!(head.message(), Some("Commit 1\n\nCommit 2\n\nCommit 3"));
+    assert_eq!(commits(&repo, 10)?.len(), 2);
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_range_with_autostash() -> Result<()> {
+    let (dir, repo) = crate::testing::repo_with_commits(5)?;
+    std::fs::write(dir.path().join("0.txt"), "edited")?;
+
+    let options = SquashOptions::new(Target::Range("HEAD~3..HEAD~1".to_string()))
+      .message(MessageStrategy::Custom("Squashed".to_string()))
+      .autostash(true);
+    let outcome = squash(&repo, &options)?;
+    assert_eq!(outcome.squashed.len(), 2);
+
+    let subjects = commits(&repo, 10)?
+      .iter()
+      .map(|commit| commit.summary().unwrap_or_default().to_string())
+      .collect::<Vec<_>>();
+    assert_eq!(subjects, ["Commit 4", "Squashed", "Commit 1", "Commit 0"]);
+    assert_eq!(std::fs::read_to_string(dir.path().join("0.txt"))?, "edited");
+    Ok(())
+  }
+
+  #[test]
+  fn test_hooks_get_the_squashed_range() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(5)?;
+    let out = repo.path().join("range.out");
+    let config = Config {
+      hooks: crate::hooks::Hooks {
+        pre_squash:  Some(format!("echo \"$GIT_SQUASH_RANGE $GIT_SQUASH_OLD_HEAD\" > {}", out.display())),
+        post_squash: None
+      },
+      ..Config::default()
+    };
+    let from = repo.revparse_single("HEAD~3")?.id();
+    let to = repo.revparse_single("HEAD~1")?.id();
+    let head = repo.revparse_single("HEAD")?.id();
+
+    squash(&repo, &SquashOptions::new(Target::Range("HEAD~3..HEAD~1".to_string())).config(config))?;
+    assert_eq!(std::fs::read_to_string(out)?, format!("{}..{} {}\n", from, to, head));
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_errors_are_typed() -> Result<()> {
+    let (dir, repo) = crate::testing::repo_with_commits(3)?;
+    let error = squash(&repo, &SquashOptions::new(Target::Last(5))).unwrap_err();
+    assert!(matches!(error, SquashError::NotEnoughCommits {
+      requested: 5, available: 3
+    }));
+    let options = SquashOptions::new(Target::Range("HEAD~2..HEAD".to_string())).sign(true);
+    assert!(matches!(squash(&repo, &options), Err(SquashError::Refused(_))));
+
+    std::fs::write(dir.path().join("0.txt"), "edited")?;
+    let error = squash(&repo, &SquashOptions::new(Target::Range("HEAD~2..HEAD".to_string()))).unwrap_err();
+    assert!(matches!(error, SquashError::DirtyWorktree));
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_refuses_what_the_cli_refuses() -> Result<()> {
+    use crate::testing::commit_file;
+    let (_dir, repo) = crate::testing::repo_with_commits(2)?;
+    commit_file(&repo, "0.txt", "changed", "Change 0")?;
+    let head = commit_file(&repo, "0.txt", "0", "Revert 0")?;
+    let refused = |options: &SquashOptions| matches!(squash(&repo, options), Err(SquashError::Refused(_)));
+
+    let options = SquashOptions::new(Target::Last(2));
+    assert!(refused(&options), "the squash would be empty");
+    let options = options.allow_empty(true);
+    repo.tag_lightweight("v1", &repo.find_object(head, None)?, false)?;
+    assert!(refused(&options), "a tag points into the range");
+    let options = options.retag(true);
+
+    let config = Config {
+      conventional: Some(true),
+      ..Config::default()
+    };
+    assert!(matches!(squash(&repo, &options.clone().config(config)), Err(SquashError::Other(_))));
+
+    let outcome = squash(&repo, &options)?;
+    assert_eq!(outcome.tags, ["v1"]);
+    assert_eq!(repo.refname_to_id("refs/tags/v1")?, outcome.new_head.unwrap());
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_refuses_merges() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(2)?;
+    let base = repo.head()?.peel_to_commit()?;
+    crate::testing::commit_file(&repo, "side.txt", "side", "Side")?;
+    let side = repo.head()?.peel_to_commit()?;
+    repo.reset(base.as_object(), git2::ResetType::Hard, None)?;
+    crate::testing::commit_file(&repo, "main.txt", "main", "Main")?;
+    let main = repo.head()?.peel_to_commit()?;
+    let tree = repo.find_tree(repo.merge_commits(&main, &side, None)?.write_tree_to(&repo)?)?;
+    let signature = repo.signature()?;
+    let merge = repo.commit(Some("HEAD"), &signature, &signature, "Merge side", &tree, &[&main, &side])?;
+    repo.reset(repo.find_commit(merge)?.as_object(), git2::ResetType::Hard, None)?;
+
+    let options = SquashOptions::new(Target::Last(2));
+    assert!(matches!(squash(&repo, &options), Err(SquashError::Refused(_))));
+    squash(&repo, &options.merges(true))?;
+    Ok(())
+  }
+
+  #[test]
+  fn test_failing_post_squash_hook_is_a_warning() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(3)?;
+    let config = Config {
+      hooks: crate::hooks::Hooks {
+        pre_squash: None, post_squash: Some("exit 3".to_string())
+      },
+      ..Config::default()
+    };
+    let outcome = squash(&repo, &SquashOptions::new(Target::Last(2)).config(config))?;
+    assert_eq!(outcome.warnings, ["post-squash hook exited with exit status: 3"]);
+    assert_eq!(commits(&repo, 10)?.len(), 2);
+    Ok(())
+  }
+
+  #[test]
+  fn test_squash_locks_enforces_policy_and_audits() -> Result<()> {
+    let (dir, repo) = crate::testing::repo_with_commits(4)?;
+    let options = SquashOptions::new(Target::Last(2)).message(MessageStrategy::Custom("fix: squashed".to_string()));
+
+    // Another git-squash is running
+    std::fs::write(repo.path().join("squash.lock"), "12345\n")?;
+    assert!(matches!(squash(&repo, &options), Err(SquashError::Refused(_))));
+    std::fs::remove_file(repo.path().join("squash.lock"))?;
+
+    std::fs::write(dir.path().join(".git-squash.toml"), "[policy]\nmessage_pattern = \"^feat\"\n")?;
+    let error = squash(&repo, &options).unwrap_err();
+    assert_eq!(error.status(), crate::ExitStatus::Refused);
+    assert_eq!(commits(&repo, 10)?.len(), 4);
+
+    std::fs::write(dir.path().join(".git-squash.toml"), "[policy]\nmessage_pattern = \"^fix\"\n")?;
+    repo.config()?.set_str("squash.auditLog", "audit.jsonl")?;
+    let outcome = squash(&repo, &options)?;
+    let log = std::fs::read_to_string(dir.path().join("audit.jsonl"))?;
+    assert!(log.contains(&format!("\"new_head\":\"{}\"", outcome.new_head.unwrap())));
+    assert!(log.contains("\"count\":2,\"message\":\"fix: squashed\""));
+    Ok(())
+  }
+
+  #[test]
+  fn test_walk_squashed_commits() -> Result<()> {
+    let (_dir, repo) = crate::testing::repo_with_commits(4)?;
+    let squashed = Squashed::walk(&repo, &Libgit2, 3)?;
+    let subjects = squashed
+      .load(&repo)?
+      .iter()
+      .map(|commit| commit.summary().unwrap_or_default().to_string())
+      .collect::<Vec<_>>();
+    assert_eq!(subjects, ["Commit 3", "Commit 2", "Commit 1"]);
+    assert_eq!(
+      squashed
+        .base_commit(&repo)?
+        .and_then(|base| base.summary().map(str::to_string))
+        .as_deref(),
+      Some("Commit 0")
+    );
+
+    assert_eq!(squashed.newest(&repo)?.map(|commit| commit.id()), Some(squashed.commits[0]));
+    assert_eq!(squashed.oldest(&repo)?.map(|commit| commit.id()), Some(squashed.commits[2]));
+    assert!(!squashed.has_merges(&repo)?);
+
+    assert_eq!(Squashed::walk(&repo, &Libgit2, 4)?.base, None);
+    Ok(())
+  }
+}
@@ -0,0 +1,126 @@
+use std::path::PathBuf;
+use anyhow::{Context, Result};
+use git2::{Oid, Repository};
+use serde::{Deserialize, Serialize};
+use crate::finish::Finish;
+use crate::rebase::Plan;
+
+const STATE_FILE: &str = "squash-state";
+
+/// A rebase-backed squash stopped at a conflict, saved under `.git/` so
+/// `git squash continue` and `git squash abort` can pick it up
+#[derive(Debug, Deserialize, PartialEq, Serialize)]
+pub struct State {
+  /// The branch being rewritten, `None` when `HEAD` was detached
+  pub head_name: Option<String>,
+  #[serde(with = "oid")]
+  pub orig_head: Oid,
+  pub plan:      Plan,
+  /// The step that conflicted
+  pub next:      usize,
+  pub reflog:    String,
+  /// What's left to do once the plan is replayed
+  #[serde(default)]
+  pub finish:    Finish
+}
+
+impl State {
+  pub fn path(repo: &Repository) -> PathBuf {
+    repo.path().join(STATE_FILE)
+  }
+
+  /// The saved state, or `None` when no squash is stopped
+  pub fn load(repo: &Repository) -> Result<Option<Self>> {
+    let path = Self::path(repo);
+    if !path.exists() {
+      return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+    serde_json::from_str(&content)
+      .with_context(|| format!("Failed to parse {}", path.display()))
+      .map(Some)
+  }
+
+  pub fn save(&self, repo: &Repository) -> Result<()> {
+    let path = Self::path(repo);
+    let content = serde_json::to_string_pretty(self).context("Failed to serialize squash state")?;
+    std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+  }
+
+  pub fn remove(repo: &Repository) -> Result<()> {
+    let path = Self::path(repo);
+    std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))
+  }
+}
+
+/// (De)serializes an `Oid` as its hex string
+pub mod oid {
+  use git2::Oid;
+  use serde::{Deserialize, Deserializer, Serializer};
+
+  pub fn serialize<S: Serializer>(oid: &Oid, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&oid.to_string())
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Oid, D::Error> {
+    let hex = String::deserialize(deserializer)?;
+    Oid::from_str(&hex).map_err(serde::de::Error::custom)
+  }
+
+  /// (De)serializes an `Option<Oid>` as its hex string or `null`
+  pub mod optional {
+    use git2::Oid;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(oid: &Option<Oid>, serializer: S) -> Result<S::Ok, S::Error> {
+      match oid {
+        Some(oid) => super::serialize(oid, serializer),
+        None => serializer.serialize_none()
+      }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Oid>, D::Error> {
+      match Option::<String>::deserialize(deserializer)? {
+        Some(hex) => Oid::from_str(&hex).map(Some).map_err(serde::de::Error::custom),
+        None => Ok(None)
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::rebase::Action;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_round_trip() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    assert_eq!(State::load(&repo)?, None);
+
+    let head = repo.head()?.peel_to_commit()?;
+    let mut plan = Plan::from_range(&repo, head.parent(0)?.parent_id(0)?, head.id())?;
+    plan.steps[1].action = Action::Squash;
+    plan.steps[1].message = Some("Combined".to_string());
+    let state = State {
+      head_name: Some("refs/heads/main".to_string()),
+      orig_head: head.id(),
+      plan,
+      next: 1,
+      reflog: "squash: test".to_string(),
+      finish: Finish {
+        tags: vec!["v1".to_string()],
+        base: Some(head.parent_id(0)?),
+        ..Finish::default()
+      }
+    };
+
+    state.save(&repo)?;
+    assert_eq!(State::load(&repo)?, Some(state));
+    State::remove(&repo)?;
+    assert!(!State::path(&repo).exists());
+    Ok(())
+  }
+}
@@ -0,0 +1,221 @@
+use std::collections::HashSet;
+use anyhow::{bail, Context, Result};
+use git2::{Oid, Reference, Repository};
+use crate::i18n::t;
+use crate::{identity, sign, SquashError};
+
+/// How signatures of each format git signs tags with start, appended to the
+/// tag message
+const SIGNATURES: [&str; 3] =
+  ["-----BEGIN PGP SIGNATURE-----", "-----BEGIN SSH SIGNATURE-----", "-----BEGIN SIGNED MESSAGE-----"];
+
+/// A tag whose commit is about to be rewritten
+#[derive(Debug, PartialEq)]
+pub struct Tag {
+  pub name:       String,
+  pub commit:     Oid,
+  /// The tag object for annotated tags, `None` for lightweight ones
+  pub annotation: Option<Oid>,
+  /// Whether the annotated tag is signed, which moving it would undo
+  pub signed:     bool
+}
+
+/// Tags pointing at any of `commits`
+pub fn pointing_into(repo: &Repository, commits: &[Oid]) -> Result<Vec<Tag>> {
+  let commits = commits.iter().collect::<HashSet<&Oid>>();
+  let mut tags = Vec::new();
+
+  let references = repo.references_glob("refs/tags/*").context("Failed to list tags")?;
+  for reference in references {
+    let reference = reference.context("Failed to read tag")?;
+    let Ok(commit) = reference.peel_to_commit() else {
+      // Tags of trees and blobs can't be affected by a squash
+      continue;
+    };
+
+    if commits.contains(&commit.id()) {
+      let annotation = reference.peel_to_tag().ok();
+      let signed = annotation.as_ref().is_some_and(|tag| is_signed(tag.message_bytes().unwrap_or_default()));
+      let name = reference.shorthand().unwrap_or_default().to_string();
+      tags.push(Tag {
+        name,
+        commit: commit.id(),
+        annotation: annotation.map(|tag| tag.id()),
+        signed
+      });
+    }
+  }
+
+  Ok(tags)
+}
+
+fn is_signed(message: &[u8]) -> bool {
+  let message = String::from_utf8_lossy(message);
+  message.lines().any(|line| SIGNATURES.contains(&line))
+}
+
+/// Refuses to rewrite the commits `tags` point at, as the tags would keep
+/// the old history alive, unless they may be moved along with `retag`
+pub fn ensure_rewritable(tags: &[Tag], retag: bool) -> Result<()> {
+  if !tags.is_empty() && !retag {
+    let names = tags
+      .iter()
+      .map(|tag| format!("{} ({})", tag.name, &tag.commit.to_string()[..7]))
+      .collect::<Vec<_>>();
+    bail!(SquashError::Refused(t!("tags-in-range", tags = names.join(", "))));
+  }
+
+  ensure_movable(tags)
+}
+
+/// Refuses to move signed tags, as a signature covers the commit it was made
+/// for and would be lost, before any history is rewritten
+pub fn ensure_movable(tags: &[Tag]) -> Result<()> {
+  let signed = tags.iter().filter(|tag| tag.signed).map(|tag| tag.name.as_str()).collect::<Vec<_>>();
+  if !signed.is_empty() {
+    bail!(SquashError::Refused(t!("tags-signed", tags = signed.join(", "))));
+  }
+
+  Ok(())
+}
+
+/// Recreates `tags` on `commit`, keeping the tagger and message of annotated tags
+pub fn retag(repo: &Repository, tags: &[Tag], commit: Oid) -> Result<()> {
+  ensure_movable(tags)?;
+  let target = repo.find_object(commit, None).context("Failed to find new commit")?;
+
+  for tag in tags {
+    match tag.annotation {
+      Some(annotation) => {
+        let old = repo.find_tag(annotation).context("Failed to find annotated tag")?;
+        let tagger = old.tagger().map(|tagger| tagger.to_owned());
+        let tagger = match tagger {
+          Some(tagger) => tagger,
+          None => identity::committer(repo)?
+        };
+        repo.tag(&tag.name, &target, &tagger, old.message().unwrap_or_default(), true)
+      },
+      None => repo.tag_lightweight(&tag.name, &target, true)
+    }
+    .with_context(|| format!("Failed to move tag {}", tag.name))?;
+  }
+
+  Ok(())
+}
+
+/// Moves each of `tags` to the commit its commit became, given as pairs of
+/// old and new commits
+pub fn move_along(repo: &Repository, tags: &[Tag], rewritten: &[(Oid, Oid)]) -> Result<()> {
+  for tag in tags {
+    if let Some((_, new)) = rewritten.iter().find(|(old, _)| *old == tag.commit) {
+      retag(repo, std::slice::from_ref(tag), *new)?;
+    }
+  }
+
+  Ok(())
+}
+
+/// Refuses to squash when `name` can't be used for a new tag, before any
+/// history is rewritten
+pub fn ensure_available(repo: &Repository, name: &str) -> Result<()> {
+  let reference = format!("refs/tags/{}", name);
+  if !Reference::is_valid_name(&reference) {
+    bail!(SquashError::Refused(t!("tag-invalid", name = name)));
+  }
+  if repo.find_reference(&reference).is_ok() {
+    bail!(SquashError::Refused(t!("tag-exists", name = name)));
+  }
+
+  Ok(())
+}
+
+/// Whether `tag.gpgSign` asks for annotated tags to be signed
+pub fn sign_by_default(repo: &Repository) -> bool {
+  repo.config().and_then(|config| config.get_bool("tag.gpgSign")).unwrap_or(false)
+}
+
+/// Creates the annotated tag `name` on `commit` with `message`, signed with
+/// the key used for commits when `sign`
+pub fn create(repo: &Repository, name: &str, commit: Oid, message: &str, sign: bool) -> Result<Oid> {
+  // Ends in a newline like the messages `git tag -a` writes
+  let message = format!("{}\n", message.trim_end());
+  let oid = match sign {
+    true => {
+      let oid = sign::create_signed_tag(repo, name, commit, &message)?;
+      repo
+        .reference(&format!("refs/tags/{}", name), oid, false, "tag: squash")
+        .with_context(|| format!("Failed to create tag {}", name))?;
+      oid
+    },
+    false => {
+      let target = repo.find_object(commit, None).context("Failed to find new commit")?;
+      let tagger = identity::committer(repo)?;
+      repo
+        .tag(name, &target, &tagger, &message, false)
+        .with_context(|| format!("Failed to create tag {}", name))?
+    }
+  };
+
+  Ok(oid)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_finds_and_moves_tags() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let middle = head.parent(0)?;
+    let base = middle.parent(0)?;
+    let signature = repo.signature()?;
+
+    repo.tag_lightweight("light", middle.as_object(), false)?;
+    repo.tag("annotated", head.as_object(), &signature, "Release", false)?;
+    repo.tag_lightweight("outside", base.as_object(), false)?;
+
+    let tags = pointing_into(&repo, &[head.id(), middle.id()])?;
+    let names = tags.iter().map(|tag| tag.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["annotated", "light"]);
+
+    retag(&repo, &tags, base.id())?;
+    let annotated = repo.revparse_single("annotated")?.peel_to_tag()?;
+    assert_eq!(annotated.target_id(), base.id());
+    assert_eq!(annotated.message(), Some("Release"));
+    assert_eq!(repo.revparse_single("light")?.id(), base.id());
+    Ok(())
+  }
+
+  #[test]
+  fn test_refuses_to_move_signed_tags() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let head = repo.head()?.peel_to_commit()?;
+    let signature = repo.signature()?;
+    let message = "Release\n-----BEGIN PGP SIGNATURE-----\n\niQEz\n-----END PGP SIGNATURE-----\n";
+    repo.tag("signed", head.as_object(), &signature, message, false)?;
+
+    let tags = pointing_into(&repo, &[head.id()])?;
+    assert!(tags[0].signed);
+    let error = retag(&repo, &tags, head.parent_id(0)?).unwrap_err();
+    assert!(matches!(error.downcast_ref(), Some(SquashError::Refused(_))));
+    assert_eq!(repo.revparse_single("signed")?.peel_to_commit()?.id(), head.id());
+    Ok(())
+  }
+
+  #[test]
+  fn test_create() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let head = repo.head()?.target().unwrap();
+
+    ensure_available(&repo, "v1.0.0")?;
+    create(&repo, "v1.0.0", head, "Release 1.0.0\n\nWith lists", false)?;
+    let tag = repo.revparse_single("v1.0.0")?.peel_to_tag()?;
+    assert_eq!((tag.target_id(), tag.message()), (head, Some("Release 1.0.0\n\nWith lists\n")));
+
+    assert!(ensure_available(&repo, "v1.0.0").is_err());
+    assert!(ensure_available(&repo, "v1..0").is_err());
+    Ok(())
+  }
+}
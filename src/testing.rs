@@ -0,0 +1,35 @@
+use std::fs::File;
+use std::io::Write;
+use anyhow::{Context, Result};
+use git2::{IndexAddOption, Repository};
+use tempdir::TempDir;
+use crate::squash::Commitable;
+
+/// Creates a repository with `amount` commits, each adding `<n>.txt`
+pub fn repo_with_commits(amount: usize) -> Result<(TempDir, Repository)> {
+  let dir = TempDir::new("git_squash_test").context("Failed to create temp dir")?;
+  let repo = Repository::init(dir.path()).context("Failed to init repo")?;
+
+  for n in 0..amount {
+    commit_file(&repo, &format!("{}.txt", n), &n.to_string(), &format!("Commit {}", n))?;
+  }
+
+  Ok((dir, repo))
+}
+
+/// Writes `content` to `name` in the working directory and commits it
+pub fn commit_file(repo: &Repository, name: &str, content: &str, message: &str) -> Result<git2::Oid> {
+  let workdir = repo.workdir().context("Repository has no working directory")?;
+  let file_path = workdir.join(name);
+  if let Some(parent) = file_path.parent() {
+    std::fs::create_dir_all(parent).context("Failed to create directory")?;
+  }
+
+  let mut file = File::create(file_path).context("Failed to create file")?;
+  file.write_all(content.as_bytes()).context("Failed to write file")?;
+
+  let mut index = repo.index().context("Failed to get index")?;
+  index.add_all([name], IndexAddOption::DEFAULT, None).context("Failed to add file")?;
+  index.write().context("Failed to write index")?;
+  repo.commit_with_msg(message).context("Failed to commit")
+}
@@ -0,0 +1,38 @@
+use anyhow::{Context, Result};
+use regex::Regex;
+
+/// The first match of `pattern` (e.g. `PROJ-\d+`) in the branch name
+pub fn from_branch(pattern: &str, branch: &str) -> Result<Option<String>> {
+  let regex = Regex::new(pattern).with_context(|| format!("Invalid ticket_pattern `{}`", pattern))?;
+  Ok(regex.find(branch).map(|found| found.as_str().to_string()))
+}
+
+/// Whether the subject line already mentions `key`
+pub fn in_subject(message: &str, key: &str) -> bool {
+  message.lines().next().unwrap_or_default().contains(key)
+}
+
+/// Puts `key: ` in front of the subject line
+pub fn prefix(message: &str, key: &str) -> String {
+  format!("{}: {}", key, message)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_ticket_from_branch() -> Result<()> {
+    assert_eq!(from_branch(r"PROJ-\d+", "feature/PROJ-482-login")?, Some("PROJ-482".to_string()));
+    assert_eq!(from_branch(r"PROJ-\d+", "main")?, None);
+    assert!(from_branch("(", "main").is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_prefix_subject() {
+    assert_eq!(prefix("Add login\n\nBody", "PROJ-1"), "PROJ-1: Add login\n\nBody");
+    assert!(in_subject("PROJ-1: Add login", "PROJ-1"));
+    assert!(!in_subject("Add login\n\nPROJ-1", "PROJ-1"));
+  }
+}
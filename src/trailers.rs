@@ -0,0 +1,210 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::{bail, Context, Result};
+use git2::{ObjectType, Oid, Repository};
+use crate::encoding;
+use crate::hooks;
+use crate::i18n::t;
+use crate::identity;
+use crate::SquashError;
+
+/// Values of the `key` trailers in `message`, compared case-insensitively
+pub fn values(message: &str, key: &str) -> Result<Vec<String>> {
+  let trailers = git2::message_trailers_strs(message).context("Failed to parse message trailers")?;
+  Ok(
+    trailers
+      .iter()
+      .filter(|(name, _)| name.eq_ignore_ascii_case(key))
+      .map(|(_, value)| value.to_string())
+      .collect()
+  )
+}
+
+/// Adds `key: value` to the trailer block of `message`, starting one if the
+/// message has none. Does nothing when the exact trailer is already there.
+pub fn append(message: &str, key: &str, value: &str) -> Result<String> {
+  if values(message, key)?.iter().any(|existing| existing == value) {
+    return Ok(message.to_string());
+  }
+
+  let has_trailers = git2::message_trailers_strs(message).context("Failed to parse message trailers")?.len() > 0;
+  let separator = if has_trailers { "\n" } else { "\n\n" };
+  Ok(format!("{}{}{}: {}", message.trim_end(), separator, key, value))
+}
+
+/// Drops every `key` trailer from the last paragraph of `message`
+pub fn remove(message: &str, key: &str) -> String {
+  let message = message.trim_end();
+  let (body, trailers) = match message.rsplit_once("\n\n") {
+    Some((body, trailers)) => (Some(body), trailers),
+    None => (None, message)
+  };
+
+  let prefix = format!("{}:", key.to_lowercase());
+  let kept = trailers
+    .lines()
+    .filter(|line| !line.to_lowercase().starts_with(&prefix))
+    .collect::<Vec<_>>()
+    .join("\n");
+
+  match (body, kept.is_empty()) {
+    (Some(body), true) => body.to_string(),
+    (Some(body), false) => format!("{}\n\n{}", body, kept),
+    (None, _) => kept
+  }
+}
+
+/// Keeps the squash mapped to the same Gerrit change by carrying over the
+/// `Change-Id` of the oldest commit in `range` (newest first). When none has
+/// one but Gerrit is in use, a new id is generated like its commit-msg hook would.
+pub fn change_id(repo: &Repository, range: &[Oid], message: &str) -> Result<String> {
+  let mut existing = None;
+  for oid in range.iter().rev() {
+    let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+    if let Some(id) = values(&encoding::message(&commit), "Change-Id")?.into_iter().next() {
+      existing = Some(id);
+      break;
+    }
+  }
+
+  let id = match existing {
+    Some(id) => id,
+    None if uses_gerrit(repo) => generate_change_id(repo, message)?,
+    None => return Ok(message.to_string())
+  };
+
+  append(&remove(message, "Change-Id"), "Change-Id", &id)
+}
+
+/// Carries the distinct `Co-authored-by` trailers of the squashed commits
+/// over, oldest first. With `include_authors`, the authors of the commits
+/// themselves are credited too, except whoever makes or authors the squash commit.
+pub fn co_authors(repo: &Repository, range: &[Oid], message: &str, include_authors: bool) -> Result<String> {
+  let mailmap = repo.mailmap().context("Failed to load mailmap")?;
+  let committer = identity::committer(repo)?;
+  let committer = format!("{} <{}>", committer.name().unwrap_or_default(), committer.email().unwrap_or_default());
+  let author = identity::Identity::of(&identity::author(repo)?).to_string();
+  let mut message = message.to_string();
+
+  for oid in range.iter().rev() {
+    let commit = repo.find_commit(*oid).context("Failed to find commit")?;
+    let mut co_authors = values(&encoding::message(&commit), "Co-authored-by")?;
+
+    if include_authors {
+      let author = commit.author_with_mailmap(&mailmap).context("Failed to resolve author")?;
+      co_authors.insert(0, format!("{} <{}>", author.name().unwrap_or_default(), author.email().unwrap_or_default()));
+    }
+
+    for co_author in co_authors
+      .iter()
+      .filter(|co_author| co_author.as_str() != committer && **co_author != author)
+    {
+      message = append(&message, "Co-authored-by", co_author)?;
+    }
+  }
+
+  Ok(message)
+}
+
+/// Refuses `message` unless it carries a `Signed-off-by` trailer for the
+/// committer, as projects following the Developer Certificate of Origin require
+pub fn ensure_signoff(repo: &Repository, message: &str) -> Result<()> {
+  let committer = identity::committer(repo)?;
+  let identity = format!("{} <{}>", committer.name().unwrap_or_default(), committer.email().unwrap_or_default());
+  if values(message, "Signed-off-by")?.iter().any(|value| value.trim() == identity) {
+    return Ok(());
+  }
+
+  bail!(SquashError::Refused(t!("missing-signoff", identity = identity)))
+}
+
+/// Gerrit's commit-msg hook is installed or `gerrit.createChangeId` is set
+fn uses_gerrit(repo: &Repository) -> bool {
+  let configured = repo.config().and_then(|config| config.get_bool("gerrit.createChangeId")).unwrap_or(false);
+  let hook = std::fs::read_to_string(hooks::directory(repo).join("commit-msg")).unwrap_or_default();
+  configured || hook.contains("Change-Id")
+}
+
+fn generate_change_id(repo: &Repository, message: &str) -> Result<String> {
+  let head = repo.head().ok().and_then(|head| head.target()).unwrap_or_else(Oid::zero);
+  let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+  let seed = format!("{}\n{}\n{}", head, now, message);
+  let oid = Oid::hash_object(ObjectType::Blob, seed.as_bytes()).context("Failed to hash Change-Id")?;
+  Ok(format!("I{}", oid))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_append_and_remove() -> Result<()> {
+    let message = append("Subject\n\nBody", "Signed-off-by", "A <a@b>")?;
+    assert_eq!(message, "Subject\n\nBody\n\nSigned-off-by: A <a@b>");
+
+    let message = append(&message, "Change-Id", "I1")?;
+    assert_eq!(message, "Subject\n\nBody\n\nSigned-off-by: A <a@b>\nChange-Id: I1");
+    assert_eq!(append(&message, "Change-Id", "I1")?, message);
+
+    assert_eq!(remove(&message, "change-id"), "Subject\n\nBody\n\nSigned-off-by: A <a@b>");
+    assert_eq!(remove("Subject\n\nChange-Id: I1", "Change-Id"), "Subject");
+    Ok(())
+  }
+
+  #[test]
+  fn test_ensure_signoff() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    let committer = identity::committer(&repo)?;
+    let identity = format!("{} <{}>", committer.name().unwrap(), committer.email().unwrap());
+
+    ensure_signoff(&repo, &format!("Subject\n\nSigned-off-by: {}", identity))?;
+    assert!(ensure_signoff(&repo, "Subject").is_err());
+    assert!(ensure_signoff(&repo, "Subject\n\nSigned-off-by: Someone Else <else@example.com>").is_err());
+    Ok(())
+  }
+
+  #[test]
+  fn test_keeps_oldest_change_id() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "a.txt", "a", "First\n\nChange-Id: Iaaa")?;
+    commit_file(&repo, "b.txt", "b", "Second\n\nChange-Id: Ibbb")?;
+    let head = repo.head()?.peel_to_commit()?;
+    let range = [head.id(), head.parent_id(0)?];
+
+    let message = change_id(&repo, &range, "Second\n\nChange-Id: Ibbb")?;
+    assert_eq!(message, "Second\n\nChange-Id: Iaaa");
+    Ok(())
+  }
+
+  #[test]
+  fn test_collects_co_authors() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(1)?;
+    commit_file(&repo, "a.txt", "a", "First\n\nCo-authored-by: Ann <ann@example.com>")?;
+    commit_file(
+      &repo,
+      "b.txt",
+      "b",
+      "Second\n\nCo-authored-by: Bob <bob@example.com>\nCo-authored-by: Ann <ann@example.com>"
+    )?;
+    let head = repo.head()?.peel_to_commit()?;
+    let range = [head.id(), head.parent_id(0)?];
+
+    let message = co_authors(&repo, &range, "Squashed", true)?;
+    let expected = ["Ann <ann@example.com>", "Bob <bob@example.com>"];
+    assert_eq!(values(&message, "Co-authored-by")?, expected, "the squashing author is left out");
+    Ok(())
+  }
+
+  #[test]
+  fn test_generates_change_id_for_gerrit() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let range = [repo.head()?.peel_to_commit()?.id()];
+    assert_eq!(change_id(&repo, &range, "Squashed")?, "Squashed");
+
+    repo.config()?.set_bool("gerrit.createChangeId", true)?;
+    let ids = values(&change_id(&repo, &range, "Squashed")?, "Change-Id")?;
+    assert_eq!(ids.len(), 1);
+    assert!(ids[0].starts_with('I') && ids[0].len() == 41);
+    Ok(())
+  }
+}
@@ -0,0 +1,173 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use anyhow::{Context, Result};
+use git2::{Oid, Repository, ResetType};
+use crate::i18n::t;
+use crate::retry::retry_on_lock;
+
+/// The transaction currently in flight, if any, so the Ctrl-C handler can
+/// undo a half-finished squash from its own thread.
+static IN_FLIGHT: Mutex<Option<Checkpoint>> = Mutex::new(None);
+
+/// Pre-squash state of a repository: where `HEAD` pointed and the tree the
+/// index described (`None` when the index could not be written as a tree).
+#[derive(Clone, Debug, PartialEq)]
+struct Checkpoint {
+  git_dir: PathBuf,
+  head:    Oid,
+  index:   Option<Oid>
+}
+
+impl Checkpoint {
+  fn capture(repo: &Repository) -> Result<Self> {
+    let head = repo
+      .head()
+      .context("Failed to get HEAD")?
+      .peel_to_commit()
+      .context("Failed to resolve HEAD to a commit")?
+      .id();
+    let index = repo.index().and_then(|mut index| index.write_tree()).ok();
+
+    Ok(Self {
+      git_dir: repo.path().to_path_buf(),
+      head,
+      index
+    })
+  }
+
+  fn restore(&self, repo: &Repository) -> Result<()> {
+    let commit = repo.find_commit(self.head).context("Failed to find original HEAD")?;
+    retry_on_lock(repo, || repo.reset(commit.as_object(), ResetType::Soft, None)).context("Failed to restore original HEAD")?;
+
+    let Some(tree_id) = self.index else {
+      return Ok(());
+    };
+
+    let mut index = repo.index().context("Failed to get index")?;
+    if index.write_tree().ok() == Some(tree_id) {
+      return Ok(());
+    }
+
+    let tree = repo.find_tree(tree_id).context("Failed to find original index tree")?;
+    index.read_tree(&tree).context("Failed to restore index")?;
+    retry_on_lock(repo, || index.write()).context("Failed to write index")
+  }
+}
+
+/// Remembers where `HEAD` pointed before a squash and restores it unless the
+/// transaction is explicitly committed. Dropping an uncommitted transaction
+/// (e.g. via `?` after the reset) rolls back automatically.
+pub struct Transaction<'repo> {
+  repo:       &'repo Repository,
+  checkpoint: Checkpoint,
+  finished:   bool
+}
+
+impl<'repo> Transaction<'repo> {
+  pub fn begin(repo: &'repo Repository) -> Result<Self> {
+    let checkpoint = Checkpoint::capture(repo)?;
+    *IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner()) = Some(checkpoint.clone());
+
+    Ok(Self {
+      repo,
+      checkpoint,
+      finished: false
+    })
+  }
+
+  pub fn original(&self) -> Oid {
+    self.checkpoint.head
+  }
+
+  pub fn commit(mut self) {
+    self.finished = true;
+    drop(self.unregister());
+  }
+
+  /// Clears the in-flight slot if it still refers to this transaction and
+  /// returns the guard, so callers can restore while holding it
+  fn unregister(&self) -> std::sync::MutexGuard<'static, Option<Checkpoint>> {
+    let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+    if in_flight.as_ref() == Some(&self.checkpoint) {
+      in_flight.take();
+    }
+    in_flight
+  }
+}
+
+impl<'repo> Drop for Transaction<'repo> {
+  fn drop(&mut self) {
+    if self.finished {
+      return;
+    }
+
+    // Hold the slot while restoring so a concurrent Ctrl-C waits for us
+    let _in_flight = self.unregister();
+    if let Err(error) = self.checkpoint.restore(self.repo) {
+      eprintln!("{}", t!("rollback-failed", error = format!("{:#}", error)));
+      eprintln!("{}", t!("rollback-hint", head = self.checkpoint.head));
+    }
+  }
+}
+
+/// Restores the repository touched by the in-flight transaction, if any.
+/// Meant to be called from the Ctrl-C handler right before exiting.
+pub fn restore_interrupted() -> Result<Option<Oid>> {
+  let mut in_flight = IN_FLIGHT.lock().unwrap_or_else(|e| e.into_inner());
+  let Some(checkpoint) = in_flight.take() else {
+    return Ok(None);
+  };
+
+  let repo = Repository::open(&checkpoint.git_dir).context("Failed to open repo")?;
+  checkpoint.restore(&repo)?;
+  Ok(Some(checkpoint.head))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_drop_restores_head() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let original = repo.head()?.peel_to_commit()?;
+    let parent = original.parent(0)?;
+
+    {
+      let _transaction = Transaction::begin(&repo)?;
+      repo.reset(parent.as_object(), ResetType::Soft, None)?;
+    }
+
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), original.id());
+    Ok(())
+  }
+
+  #[test]
+  fn test_commit_keeps_new_head() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(3)?;
+    let parent = repo.head()?.peel_to_commit()?.parent(0)?;
+
+    let transaction = Transaction::begin(&repo)?;
+    repo.reset(parent.as_object(), ResetType::Soft, None)?;
+    transaction.commit();
+
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), parent.id());
+    Ok(())
+  }
+
+  #[test]
+  fn test_checkpoint_restores_head_and_index() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    let checkpoint = Checkpoint::capture(&repo)?;
+    let parent = repo.head()?.peel_to_commit()?.parent(0)?;
+
+    commit_file(&repo, "extra.txt", "extra", "Extra")?;
+    repo.reset(parent.as_object(), ResetType::Mixed, None)?;
+    checkpoint.restore(&repo)?;
+
+    assert_eq!(repo.head()?.peel_to_commit()?.id(), checkpoint.head);
+    assert_eq!(repo.index()?.write_tree()?, checkpoint.index.unwrap());
+    Ok(())
+  }
+}
@@ -0,0 +1,109 @@
+use std::fmt;
+use anyhow::{Context, Result};
+use git2::{BranchType, Repository};
+
+/// Where the current branch stands relative to its upstream
+#[derive(Debug, PartialEq)]
+pub struct Tracking {
+  pub branch:   String,
+  pub upstream: String,
+  pub ahead:    usize,
+  pub behind:   usize
+}
+
+impl fmt::Display for Tracking {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{}: {} ahead, {} behind {}", self.branch, self.ahead, self.behind, self.upstream)
+  }
+}
+
+/// `None` when `HEAD` is detached or the branch has no upstream
+pub fn tracking(repo: &Repository) -> Result<Option<Tracking>> {
+  let Some(head) = repo.head().ok().filter(|head| head.is_branch()) else {
+    return Ok(None);
+  };
+
+  let name = head.shorthand().unwrap_or_default().to_string();
+  let branch = repo.find_branch(&name, BranchType::Local).context("Failed to find branch")?;
+  let Ok(upstream) = branch.upstream() else {
+    return Ok(None);
+  };
+
+  let local = head.target().context("Failed to resolve HEAD")?;
+  let remote = upstream.get().target().context("Failed to resolve upstream")?;
+  let (ahead, behind) = repo.graph_ahead_behind(local, remote).context("Failed to compare with upstream")?;
+
+  Ok(Some(Tracking {
+    branch: name,
+    upstream: upstream.name().ok().flatten().unwrap_or_default().to_string(),
+    ahead,
+    behind
+  }))
+}
+
+/// Where pushing the current branch goes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Destination {
+  pub remote:  String,
+  /// The branch pushed to the branch it tracks, like `refs/heads/a:refs/heads/b`
+  pub refspec: String
+}
+
+impl fmt::Display for Destination {
+  fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+    write!(f, "{} {}", self.remote, self.refspec)
+  }
+}
+
+/// The remote and branch set by `branch.<name>.remote` and `.merge`, or
+/// `None` when `HEAD` is detached or the branch doesn't track a remote branch
+pub fn destination(repo: &Repository) -> Result<Option<Destination>> {
+  let Some(head) = repo.head().ok().filter(|head| head.is_branch()) else {
+    return Ok(None);
+  };
+
+  let name = head.name().context("Branch name is not valid UTF-8")?;
+  let branch = head.shorthand().context("Branch name is not valid UTF-8")?;
+  let config = repo.config().context("Failed to get git config")?;
+  let remote = config.get_string(&format!("branch.{}.remote", branch));
+  let merge = config.get_string(&format!("branch.{}.merge", branch));
+  match (remote, merge) {
+    // Tracking a local branch
+    (Ok(remote), _) if remote == "." => Ok(None),
+    (Ok(remote), Ok(merge)) => {
+      Ok(Some(Destination {
+        remote,
+        refspec: format!("{}:{}", name, merge)
+      }))
+    },
+    _ => Ok(None)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::repo_with_commits;
+
+  #[test]
+  fn test_counts_commits_ahead_of_upstream() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(4)?;
+    assert_eq!(tracking(&repo)?, None);
+
+    let base = repo.head()?.peel_to_commit()?.parent(0)?.parent(0)?;
+    let name = repo.head()?.shorthand().unwrap().to_string();
+    repo.remote("origin", "https://example.com/repo.git")?;
+    repo.reference(&format!("refs/remotes/origin/{}", name), base.id(), false, "test")?;
+    repo
+      .find_branch(&name, BranchType::Local)?
+      .set_upstream(Some(&format!("origin/{}", name)))?;
+
+    let tracking = tracking(&repo)?.unwrap();
+    assert_eq!((tracking.ahead, tracking.behind), (2, 0));
+    assert_eq!(tracking.to_string(), format!("{}: 2 ahead, 0 behind origin/{}", name, name));
+
+    let destination = destination(&repo)?.unwrap();
+    assert_eq!(destination.to_string(), format!("origin refs/heads/{}:refs/heads/{}", name, name));
+    Ok(())
+  }
+}
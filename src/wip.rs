@@ -0,0 +1,116 @@
+use anyhow::{Context, Result};
+use git2::{Repository, Sort};
+use crate::encoding;
+
+/// Subjects that say nothing on their own when they are the whole subject
+const NOISE: [&str; 16] = [
+  "wip", "tmp", "temp", "fix", "fixes", "fixup", "typo", "oops", "asdf", "test", "update", "updates", "changes", "stuff", "more",
+  "save"
+];
+
+/// Words that mark a commit as unfinished when the subject starts with them
+const MARKERS: [&str; 3] = ["wip", "tmp", "temp"];
+
+/// Prefixes `git commit --fixup` and `--squash` put in front of the target's subject
+const AUTOSQUASH: [&str; 3] = ["fixup!", "squash!", "amend!"];
+
+/// Run of work-in-progress commits at the tip of the branch
+#[derive(Debug, PartialEq)]
+pub struct Noise {
+  /// Work-in-progress commits counted back from `HEAD`
+  pub count:  usize,
+  /// Subject of the commit below them, which they get squashed into
+  pub target: Option<String>
+}
+
+impl Noise {
+  /// Commits to squash: the noise plus the commit it builds on, if any
+  pub fn amount(&self) -> usize {
+    self.count + usize::from(self.target.is_some())
+  }
+}
+
+/// Whether a subject looks like a work-in-progress commit: `wip`, `tmp: x`,
+/// `fixup! Add x`, a lone `.` and the like
+pub fn is_noise(subject: &str) -> bool {
+  let subject = subject.trim().to_lowercase();
+  if AUTOSQUASH.iter().any(|prefix| subject.starts_with(prefix)) {
+    return true;
+  }
+
+  if !subject.chars().any(char::is_alphanumeric) {
+    return true;
+  }
+
+  let words = subject
+    .split(|c: char| !c.is_alphanumeric())
+    .filter(|word| !word.is_empty())
+    .collect::<Vec<_>>();
+  words.first().is_some_and(|word| MARKERS.contains(word)) || (words.len() == 1 && NOISE.contains(&words[0]))
+}
+
+/// The work-in-progress commits at the tip of the branch, looking at most
+/// `limit` commits back. Merges end the run, as they can't be noise.
+pub fn detect(repo: &Repository, limit: usize) -> Result<Option<Noise>> {
+  let mut revwalk = repo.revwalk().context("Failed to get revwalk")?;
+  revwalk.set_sorting(Sort::TOPOLOGICAL).context("Failed to set sorting")?;
+  revwalk.push_head().context("Failed to push HEAD")?;
+
+  let mut count = 0;
+  let mut target = None;
+  for oid in revwalk.take(limit) {
+    let commit = repo
+      .find_commit(oid.context("Failed to walk history")?)
+      .context("Failed to find commit")?;
+    let subject = &encoding::summary(&commit);
+
+    if commit.parent_count() > 1 || !is_noise(subject) {
+      target = (commit.parent_count() <= 1).then(|| subject.to_string());
+      break;
+    }
+    count += 1;
+  }
+
+  let noise = Noise {
+    count,
+    target
+  };
+  Ok((noise.count > 0 && noise.amount() > 1).then_some(noise))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::testing::{commit_file, repo_with_commits};
+
+  #[test]
+  fn test_is_noise() {
+    for subject in ["wip", "WIP: parser", "[wip] parser", "tmp", ".", "...", "fixup! Add parser", "squash! Add parser", "Typo"] {
+      assert!(is_noise(subject), "{}", subject);
+    }
+
+    for subject in ["Add parser", "Fix crash on empty input", "Update README", "Wipe cache on logout", "temperature sensor"] {
+      assert!(!is_noise(subject), "{}", subject);
+    }
+  }
+
+  #[test]
+  fn test_detects_wip_at_the_tip() -> Result<()> {
+    let (_dir, repo) = repo_with_commits(2)?;
+    assert_eq!(detect(&repo, 10)?, None);
+
+    commit_file(&repo, "a.txt", "a", "wip")?;
+    commit_file(&repo, "b.txt", "b", "fixup! Commit 1")?;
+    commit_file(&repo, "c.txt", "c", ".")?;
+
+    let noise = detect(&repo, 10)?.unwrap();
+    assert_eq!(noise, Noise {
+      count: 3, target: Some("Commit 1".to_string())
+    });
+    assert_eq!(noise.amount(), 4);
+
+    let noise = detect(&repo, 2)?.unwrap();
+    assert_eq!((noise.count, noise.amount()), (2, 2));
+    Ok(())
+  }
+}
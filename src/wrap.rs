@@ -0,0 +1,155 @@
+use console::measure_text_width;
+
+/// Column the body is wrapped at, following the 50/72 convention
+pub const BODY_WIDTH: usize = 72;
+
+/// Re-wraps the paragraphs in the body of `message` at `width` columns. The
+/// subject, indented and fenced code and the trailer block are kept as they
+/// are, and list items are wrapped with a hanging indent.
+pub fn body(message: &str, width: usize) -> String {
+  let message = message.trim_end();
+  let Some((subject, body)) = message.split_once("\n\n") else {
+    return message.to_string();
+  };
+
+  let has_trailers = git2::message_trailers_strs(message).map(|trailers| trailers.len() > 0).unwrap_or(false);
+  let (body, trailers) = match (has_trailers, body.rsplit_once("\n\n")) {
+    (false, _) => (body, None),
+    (true, Some((body, trailers))) => (body, Some(trailers)),
+    (true, None) => return message.to_string()
+  };
+
+  let mut lines = vec![subject.to_string(), String::new()];
+  let mut paragraph: Option<Paragraph> = None;
+  let mut in_fence = false;
+
+  for line in body.lines() {
+    let fence = line.trim_start().starts_with("```");
+    if in_fence || fence {
+      flush(&mut lines, paragraph.take(), width);
+      lines.push(line.to_string());
+      in_fence ^= fence;
+    } else if line.trim().is_empty() {
+      flush(&mut lines, paragraph.take(), width);
+      lines.push(String::new());
+    } else if let Some(marker) = list_marker(line) {
+      flush(&mut lines, paragraph.take(), width);
+      paragraph = Some(Paragraph::new(marker, &line[marker.len()..]));
+    } else if line.starts_with([' ', '\t']) {
+      match &mut paragraph {
+        // Continuation of a list item
+        Some(item) if !item.marker.is_empty() => item.words.extend(line.split_whitespace().map(str::to_string)),
+        _ => {
+          flush(&mut lines, paragraph.take(), width);
+          lines.push(line.to_string());
+        }
+      }
+    } else {
+      match &mut paragraph {
+        Some(current) if current.marker.is_empty() => current.words.extend(line.split_whitespace().map(str::to_string)),
+        _ => {
+          flush(&mut lines, paragraph.take(), width);
+          paragraph = Some(Paragraph::new("", line));
+        }
+      }
+    }
+  }
+  flush(&mut lines, paragraph, width);
+
+  if let Some(trailers) = trailers {
+    lines.push(String::new());
+    lines.push(trailers.to_string());
+  }
+
+  lines.join("\n")
+}
+
+/// A run of text lines to be refilled, optionally starting with a list marker
+struct Paragraph<'a> {
+  marker: &'a str,
+  words:  Vec<String>
+}
+
+impl<'a> Paragraph<'a> {
+  fn new(marker: &'a str, text: &str) -> Self {
+    Self {
+      marker,
+      words: text.split_whitespace().map(str::to_string).collect()
+    }
+  }
+}
+
+fn flush(lines: &mut Vec<String>, paragraph: Option<Paragraph>, width: usize) {
+  let Some(paragraph) = paragraph else {
+    return;
+  };
+
+  let indent = " ".repeat(measure_text_width(paragraph.marker));
+  let mut line = paragraph.marker.to_string();
+  let mut empty = true;
+
+  for word in paragraph.words {
+    // Words longer than the width (e.g. URLs) get a line of their own
+    if !empty && measure_text_width(&line) + 1 + measure_text_width(&word) > width {
+      lines.push(std::mem::replace(&mut line, indent.clone()));
+      empty = true;
+    }
+
+    if !empty {
+      line.push(' ');
+    }
+    line.push_str(&word);
+    empty = false;
+  }
+
+  lines.push(line);
+}
+
+/// The bullet or number (including the space after it) starting a list item
+fn list_marker(line: &str) -> Option<&str> {
+  let indent = line.len() - line.trim_start().len();
+  let rest = &line[indent..];
+
+  let marker = match rest.find(' ') {
+    Some(end) if matches!(&rest[..end], "-" | "*" | "+") => end + 1,
+    Some(end) if end > 1 && rest[..end - 1].chars().all(|c| c.is_ascii_digit()) && rest[..end].ends_with(['.', ')']) => end + 1,
+    _ => return None
+  };
+
+  Some(&line[..indent + marker])
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_wraps_paragraphs() {
+    let message = "Subject that is left alone even though it is rather long, really\n\nThis body was joined from several commits and runs on and on for much longer than seventy-two columns.\nA second line.";
+    let wrapped = body(message, BODY_WIDTH);
+
+    assert_eq!(
+      wrapped,
+      "Subject that is left alone even though it is rather long, really\n\nThis body was joined from several commits and runs on and on for much\nlonger than seventy-two columns. A second line."
+    );
+  }
+
+  #[test]
+  fn test_keeps_lists_code_and_trailers() {
+    let message = "Subject\n\n- first item that is long enough to need wrapping across two separate lines\n- second\n\n    indented code that should never be touched by the wrapping logic at all\n\n```\nfenced   code\n```\n\nSigned-off-by: A very long name that goes on and on and on and on <someone@example.com>";
+    let wrapped = body(message, 40);
+
+    assert_eq!(
+      wrapped,
+      "Subject\n\n- first item that is long enough to need\n  wrapping across two separate lines\n- second\n\n    indented code that should never be touched by the wrapping logic at all\n\n```\nfenced   code\n```\n\nSigned-off-by: A very long name that goes on and on and on and on <someone@example.com>"
+    );
+  }
+
+  #[test]
+  fn test_numbered_list_marker() {
+    assert_eq!(list_marker("12. item"), Some("12. "));
+    assert_eq!(list_marker("  1) item"), Some("  1) "));
+    assert_eq!(list_marker("-not a list"), None);
+    assert_eq!(list_marker("2024 was a year"), None);
+  }
+}